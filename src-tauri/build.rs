@@ -32,5 +32,33 @@ fn main() {
         }
     }
 
+    emit_build_info(&triple);
+
     tauri_build::build()
 }
+
+/// Expose build metadata to the crate as `BUILD_*` env vars (see `get_build_info`)
+fn emit_build_info(triple: &str) {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    println!("cargo:rustc-env=BUILD_TIMESTAMP={}", timestamp);
+    println!("cargo:rustc-env=BUILD_TARGET={}", triple);
+
+    // rmcp's resolved version only lives in the lockfile
+    let rmcp_version = std::fs::read_to_string("Cargo.lock")
+        .ok()
+        .and_then(|lock| {
+            let mut lines = lock.lines();
+            lines.find(|l| *l == "name = \"rmcp\"")?;
+            lines
+                .next()?
+                .strip_prefix("version = \"")?
+                .strip_suffix('"')
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=BUILD_RMCP_VERSION={}", rmcp_version);
+    println!("cargo:rerun-if-changed=Cargo.lock");
+}
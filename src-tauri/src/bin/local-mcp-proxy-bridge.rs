@@ -6,18 +6,47 @@
 //!
 //! Usage:
 //!   local-mcp-proxy-bridge --mcp-id <SERVER_ID> [--port <PORT>]
+//!     [--startup-timeout <SECS>] [--poll-interval <MS>] [--token <TOKEN>]
+//!
+//! Before reading stdin the bridge polls the proxy's `/health` endpoint until it
+//! answers, backing off exponentially from `--poll-interval` up to
+//! `MAX_POLL_INTERVAL`. A `--startup-timeout` of 0 skips the readiness check.
+//!
+//! If the proxy requires an auth token, pass it with `--token` or the
+//! `LOCAL_MCP_PROXY_TOKEN` environment variable.
 
+use std::borrow::Cow;
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 
+const DEFAULT_STARTUP_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_POLL_INTERVAL_MS: u64 = 100;
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const TOKEN_ENV: &str = "LOCAL_MCP_PROXY_TOKEN";
+
 struct Args {
     port: u16,
     mcp_id: String,
+    startup_timeout: Duration,
+    poll_interval: Duration,
+    token: Option<String>,
 }
 
 fn parse_args() -> Result<Args, String> {
-    let mut args = std::env::args().skip(1);
+    parse_args_from(std::env::args().skip(1), std::env::var(TOKEN_ENV).ok())
+}
+
+/// Parse CLI args (without the program name); `env_token` is the value of
+/// `LOCAL_MCP_PROXY_TOKEN`, used unless `--token` is given
+fn parse_args_from(
+    mut args: impl Iterator<Item = String>,
+    env_token: Option<String>,
+) -> Result<Args, String> {
     let mut port: u16 = 3001;
     let mut mcp_id: Option<String> = None;
+    let mut startup_timeout = Duration::from_secs(DEFAULT_STARTUP_TIMEOUT_SECS);
+    let mut poll_interval = Duration::from_millis(DEFAULT_POLL_INTERVAL_MS);
+    let mut token = env_token.filter(|t| !t.is_empty());
 
     while let Some(arg) = args.next() {
         match arg.as_str() {
@@ -28,6 +57,25 @@ fn parse_args() -> Result<Args, String> {
             "--mcp-id" => {
                 mcp_id = Some(args.next().ok_or("--mcp-id requires a value")?);
             }
+            "--token" => {
+                token = Some(args.next().ok_or("--token requires a value")?);
+            }
+            "--startup-timeout" => {
+                let val = args.next().ok_or("--startup-timeout requires a value")?;
+                let secs: u64 = val
+                    .parse()
+                    .map_err(|_| format!("invalid startup timeout: {}", val))?;
+                startup_timeout = Duration::from_secs(secs);
+            }
+            "--poll-interval" => {
+                let val = args.next().ok_or("--poll-interval requires a value")?;
+                let ms: u64 = val
+                    .parse()
+                    .ok()
+                    .filter(|ms| *ms > 0)
+                    .ok_or_else(|| format!("invalid poll interval: {}", val))?;
+                poll_interval = Duration::from_millis(ms);
+            }
             other => return Err(format!("unknown argument: {}", other)),
         }
     }
@@ -35,34 +83,105 @@ fn parse_args() -> Result<Args, String> {
     Ok(Args {
         port,
         mcp_id: mcp_id.ok_or("--mcp-id is required")?,
+        startup_timeout,
+        poll_interval,
+        token,
     })
 }
 
+/// Poll `/health` until the proxy answers or `timeout` elapses. Any HTTP
+/// response counts as ready: a 503 only means upstream MCPs are down, and the
+/// proxy connects those lazily on the first request.
+async fn wait_for_proxy(
+    client: &reqwest::Client,
+    port: u16,
+    timeout: Duration,
+    initial_interval: Duration,
+) -> Result<Duration, String> {
+    let health_url = format!("http://127.0.0.1:{}/health", port);
+    let started = Instant::now();
+    let mut interval = initial_interval;
+
+    loop {
+        let remaining = timeout.saturating_sub(started.elapsed());
+        if let Ok(Ok(_)) = tokio::time::timeout(remaining, client.get(&health_url).send()).await {
+            return Ok(started.elapsed());
+        }
+
+        let remaining = timeout.saturating_sub(started.elapsed());
+        if remaining.is_zero() {
+            return Err(format!(
+                "proxy at 127.0.0.1:{} did not become ready within {}s — is Local MCP Proxy running?",
+                port,
+                timeout.as_secs()
+            ));
+        }
+        tokio::time::sleep(interval.min(remaining)).await;
+        interval = (interval * 2).min(MAX_POLL_INTERVAL);
+    }
+}
+
 #[tokio::main]
 async fn main() -> std::process::ExitCode {
     let args = match parse_args() {
         Ok(a) => a,
         Err(e) => {
             eprintln!("local-mcp-proxy-bridge: {}", e);
-            eprintln!("Usage: local-mcp-proxy-bridge --mcp-id <ID> [--port <PORT>]");
+            eprintln!(
+                "Usage: local-mcp-proxy-bridge --mcp-id <ID> [--port <PORT>] [--startup-timeout <SECS>] [--poll-interval <MS>] [--token <TOKEN>]"
+            );
             return std::process::ExitCode::from(1);
         }
     };
 
     let url = format!("http://127.0.0.1:{}/mcp/{}", args.port, args.mcp_id);
-    let client = reqwest::Client::new();
+    let mut default_headers = reqwest::header::HeaderMap::new();
+    if let Some(token) = &args.token {
+        match reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token)) {
+            Ok(value) => {
+                default_headers.insert(reqwest::header::AUTHORIZATION, value);
+            }
+            Err(_) => {
+                eprintln!("local-mcp-proxy-bridge: token contains invalid characters");
+                return std::process::ExitCode::from(1);
+            }
+        }
+    }
+    let client = match reqwest::Client::builder().default_headers(default_headers).build() {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("local-mcp-proxy-bridge: failed to build HTTP client: {}", e);
+            return std::process::ExitCode::from(1);
+        }
+    };
+
+    if !args.startup_timeout.is_zero() {
+        match wait_for_proxy(&client, args.port, args.startup_timeout, args.poll_interval).await {
+            Ok(waited) => eprintln!(
+                "local-mcp-proxy-bridge: proxy ready after {}ms",
+                waited.as_millis()
+            ),
+            Err(e) => {
+                eprintln!("local-mcp-proxy-bridge: {}", e);
+                return std::process::ExitCode::from(1);
+            }
+        }
+    }
 
     eprintln!("local-mcp-proxy-bridge: proxying stdio <-> {}", url);
 
-    let stdin = BufReader::new(tokio::io::stdin());
+    let mut stdin = BufReader::new(tokio::io::stdin());
     let mut stdout = tokio::io::stdout();
-    let mut lines = stdin.lines();
+    let mut raw = Vec::new();
 
     loop {
+        raw.clear();
         tokio::select! {
-            line = lines.next_line() => {
-                match line {
-                    Ok(Some(line)) => {
+            read = stdin.read_until(b'\n', &mut raw) => {
+                match read {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        let line = decode_line(&raw);
                         if line.trim().is_empty() {
                             continue;
                         }
@@ -70,7 +189,6 @@ async fn main() -> std::process::ExitCode {
                             eprintln!("local-mcp-proxy-bridge: error: {}", e);
                         }
                     }
-                    Ok(None) => break,
                     Err(e) => {
                         eprintln!("local-mcp-proxy-bridge: stdin error: {}", e);
                         break;
@@ -90,6 +208,19 @@ async fn main() -> std::process::ExitCode {
     std::process::ExitCode::SUCCESS
 }
 
+/// Decode a raw stdin line.  Lines are read as bytes so a stray non-UTF-8
+/// byte can't end the session; undecodable lines are decoded lossily, then
+/// fail JSON parsing and are skipped.
+fn decode_line(raw: &[u8]) -> Cow<'_, str> {
+    match std::str::from_utf8(raw) {
+        Ok(line) => Cow::Borrowed(line),
+        Err(_) => {
+            eprintln!("local-mcp-proxy-bridge: input line is not valid UTF-8, decoding lossily");
+            String::from_utf8_lossy(raw)
+        }
+    }
+}
+
 async fn handle_line(
     client: &reqwest::Client,
     url: &str,
@@ -159,3 +290,142 @@ async fn handle_line(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        parse_args_from(args.iter().map(|a| a.to_string()), None)
+    }
+
+    #[test]
+    fn startup_options_default_and_parse() {
+        let args = parse(&["--mcp-id", "fs"]).unwrap();
+        assert_eq!(
+            args.startup_timeout,
+            Duration::from_secs(DEFAULT_STARTUP_TIMEOUT_SECS)
+        );
+        assert_eq!(
+            args.poll_interval,
+            Duration::from_millis(DEFAULT_POLL_INTERVAL_MS)
+        );
+
+        let args = parse(&[
+            "--mcp-id",
+            "fs",
+            "--startup-timeout",
+            "0",
+            "--poll-interval",
+            "250",
+        ])
+        .unwrap();
+        assert!(args.startup_timeout.is_zero());
+        assert_eq!(args.poll_interval, Duration::from_millis(250));
+    }
+
+    #[test]
+    fn startup_options_reject_bad_values() {
+        assert!(parse(&["--mcp-id", "fs", "--poll-interval", "0"]).is_err());
+        assert!(parse(&["--mcp-id", "fs", "--startup-timeout", "soon"]).is_err());
+        assert!(parse(&["--mcp-id", "fs", "--startup-timeout"]).is_err());
+    }
+
+    #[test]
+    fn token_comes_from_the_flag_over_the_env_var() {
+        let from_env = |args: &[&str], env: Option<&str>| {
+            parse_args_from(args.iter().map(|a| a.to_string()), env.map(str::to_string))
+                .unwrap()
+                .token
+        };
+
+        assert_eq!(from_env(&["--mcp-id", "fs"], None), None);
+        assert_eq!(from_env(&["--mcp-id", "fs"], Some("")), None);
+        assert_eq!(
+            from_env(&["--mcp-id", "fs"], Some("env")).as_deref(),
+            Some("env")
+        );
+        assert_eq!(
+            from_env(&["--mcp-id", "fs", "--token", "flag"], Some("env")).as_deref(),
+            Some("flag")
+        );
+    }
+
+    async fn free_port() -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().port()
+    }
+
+    #[tokio::test]
+    async fn wait_for_proxy_gives_up_after_the_timeout() {
+        let port = free_port().await;
+        let started = Instant::now();
+
+        let result = wait_for_proxy(
+            &reqwest::Client::new(),
+            port,
+            Duration::from_millis(300),
+            Duration::from_millis(50),
+        )
+        .await;
+
+        assert!(result.unwrap_err().contains("did not become ready"));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn wait_for_proxy_returns_once_health_answers() {
+        let port = free_port().await;
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let listener = tokio::net::TcpListener::bind(("127.0.0.1", port))
+                .await
+                .unwrap();
+            // Any HTTP status counts as ready
+            let router = axum::Router::new().route(
+                "/health",
+                axum::routing::get(|| async { axum::http::StatusCode::SERVICE_UNAVAILABLE }),
+            );
+            axum::serve(listener, router).await
+        });
+
+        let waited = wait_for_proxy(
+            &reqwest::Client::new(),
+            port,
+            Duration::from_secs(5),
+            Duration::from_millis(50),
+        )
+        .await
+        .unwrap();
+
+        assert!(waited >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn valid_lines_decode_unchanged() {
+        let line = decode_line(b"{\"jsonrpc\":\"2.0\",\"id\":1}\n");
+        assert!(matches!(line, Cow::Borrowed(_)));
+        assert_eq!(line, "{\"jsonrpc\":\"2.0\",\"id\":1}\n");
+    }
+
+    #[tokio::test]
+    async fn invalid_bytes_do_not_stop_later_lines() {
+        let input: &[u8] = b"{\"id\":\xff}\n{\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"ping\"}\n";
+        let mut reader = BufReader::new(input);
+        let mut raw = Vec::new();
+        let mut parsed = Vec::new();
+
+        while reader.read_until(b'\n', &mut raw).await.unwrap() > 0 {
+            let line = decode_line(&raw);
+            if let Ok(value) = serde_json::from_str::<serde_json::Value>(&line) {
+                parsed.push(value);
+            } else {
+                assert!(line.contains('\u{FFFD}'));
+            }
+            raw.clear();
+        }
+
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0]["method"], "ping");
+    }
+}
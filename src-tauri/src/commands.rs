@@ -1,5 +1,6 @@
 use crate::config::ConfigManager;
-use crate::mcp::manager::McpManager;
+use crate::mcp::connection::McpConnection;
+use crate::mcp::manager::{McpManager, MAX_HEALTH_PAUSE_SECS};
 use crate::types::*;
 use std::sync::Arc;
 use std::sync::Mutex as StdMutex;
@@ -7,11 +8,18 @@ use std::collections::VecDeque;
 use tauri::State;
 use tokio::sync::Mutex;
 
+/// Environment variable the bridge reads the proxy auth token from
+const BRIDGE_TOKEN_ENV: &str = "LOCAL_MCP_PROXY_TOKEN";
+
 /// Shared application state accessible to all commands
 pub struct AppState {
     pub manager: Arc<Mutex<McpManager>>,
     pub config_manager: Arc<Mutex<ConfigManager>>,
     pub log_store: Arc<StdMutex<VecDeque<LogEntry>>>,
+    /// Port the proxy server was started on; port changes need a restart
+    pub proxy_port: u16,
+    /// Set when a corrupt config file was replaced by defaults at startup
+    pub config_warning: Option<String>,
 }
 
 /// Helper to persist config after any modification
@@ -36,6 +44,76 @@ pub async fn get_mcp_detail(id: String, state: State<'_, AppState>) -> Result<Mc
     mgr.get_detail(&id).await.map_err(|e| e.to_string())
 }
 
+/// `get_mcp_detail` for an MCP identified by its name
+#[tauri::command]
+pub async fn get_mcp_detail_by_name(
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<McpDetail, String> {
+    let id = resolve_mcp_name(&name, &state).await?;
+    get_mcp_detail(id, state).await
+}
+
+/// Get a single tool's input schema (by upstream name), and whether it is enabled
+#[tauri::command]
+pub async fn get_tool_schema(
+    mcp_id: String,
+    tool_name: String,
+    state: State<'_, AppState>,
+) -> Result<ToolSchema, String> {
+    tool_schema(&state.manager, &mcp_id, &tool_name).await
+}
+
+async fn tool_schema(
+    manager: &Mutex<McpManager>,
+    mcp_id: &str,
+    tool_name: &str,
+) -> Result<ToolSchema, String> {
+    let (conn, disabled) = {
+        let mgr = manager.lock().await;
+        let conn = mgr.get_connection(mcp_id).ok_or("MCP not found")?;
+        (conn, mgr.get_disabled_items(mcp_id).await)
+    };
+
+    let tool = conn
+        .find_tool(tool_name)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Tool '{}' not found", tool_name))?;
+
+    Ok(ToolSchema {
+        enabled: disabled.is_tool_enabled(&tool.name),
+        name: tool.name,
+        input_schema: tool.input_schema,
+    })
+}
+
+/// Get the proxied requests recorded for an MCP (`record_requests`), oldest first
+#[tauri::command]
+pub async fn get_recent_requests(
+    mcp_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<RecordedRequest>, String> {
+    let conn = {
+        let mgr = state.manager.lock().await;
+        mgr.get_connection(&mcp_id).ok_or("MCP not found")?
+    };
+    Ok(conn.get_recorded_requests().await)
+}
+
+/// Get the connection lifecycle events recorded for an MCP, oldest first
+#[tauri::command]
+pub async fn get_connection_events(
+    mcp_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<ConnectionEvent>, String> {
+    let conn = {
+        let mgr = state.manager.lock().await;
+        mgr.get_connection(&mcp_id).ok_or("MCP not found")?
+    };
+    Ok(conn.get_events().await)
+}
+
 /// Add a new MCP server
 #[tauri::command]
 pub async fn add_mcp(
@@ -83,6 +161,63 @@ pub async fn remove_mcp(id: String, state: State<'_, AppState>) -> Result<(), St
     Ok(())
 }
 
+/// Get status counts without the full per-MCP status list
+#[tauri::command]
+pub async fn get_status_summary(state: State<'_, AppState>) -> Result<StatusSummary, String> {
+    let mgr = state.manager.lock().await;
+    Ok(mgr.status_summary().await)
+}
+
+/// Bytes and message counts proxied per MCP, to spot chatty servers
+#[tauri::command]
+pub async fn get_traffic_stats(state: State<'_, AppState>) -> Result<Vec<TrafficStats>, String> {
+    let mgr = state.manager.lock().await;
+    Ok(mgr.traffic_stats())
+}
+
+/// Child processes of stdio MCPs with their PIDs, to spot leaked servers
+#[tauri::command]
+pub async fn get_spawned_processes(
+    state: State<'_, AppState>,
+) -> Result<Vec<SpawnedProcess>, String> {
+    let mgr = state.manager.lock().await;
+    Ok(mgr.spawned_processes().await)
+}
+
+/// Force-kill the child process of a stuck stdio MCP, leaving it Disconnected
+#[tauri::command]
+pub async fn kill_process(mcp_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let conn = {
+        let mgr = state.manager.lock().await;
+        mgr.get_connection(&mcp_id)
+            .ok_or_else(|| format!("MCP '{}' not found", mcp_id))?
+    };
+    if conn.config.transport_type != TransportType::Stdio {
+        return Err(format!("MCP '{}' has no child process", mcp_id));
+    }
+    conn.force_kill("Killed by user").await;
+    Ok(())
+}
+
+/// Connect to a server without adding it, returning its raw `initialize`
+/// result (protocolVersion, capabilities, serverInfo) for diagnostics
+#[tauri::command]
+pub async fn probe_server(
+    config: McpServerConfig,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    let conn = {
+        let mgr = state.manager.lock().await;
+        mgr.detached_connection(config)
+    };
+    let result = conn.connect(ReconnectReason::UserRequested).await;
+    let info = conn.server_info().await;
+    conn.disconnect().await;
+
+    result.map_err(|e| e.to_string())?;
+    info.ok_or_else(|| "Server did not report initialize info".to_string())
+}
+
 /// Manually connect a specific MCP
 #[tauri::command]
 pub async fn connect_mcp(id: String, state: State<'_, AppState>) -> Result<(), String> {
@@ -94,7 +229,54 @@ pub async fn connect_mcp(id: String, state: State<'_, AppState>) -> Result<(), S
         mgr.get_connection(&id)
             .ok_or_else(|| format!("MCP '{}' not found", id))?
     };
-    conn.connect().await.map_err(|e| e.to_string())
+    conn.connect(ReconnectReason::UserRequested)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Force-reconnect every MCP in a reconnect group at once, e.g. after the
+/// database they share comes back up
+#[tauri::command]
+pub async fn reconnect_group(name: String, state: State<'_, AppState>) -> Result<(), String> {
+    let members = {
+        let mgr = state.manager.lock().await;
+        mgr.group_members(&name)
+    };
+    if members.is_empty() {
+        return Err(format!("No MCPs in reconnect group '{}'", name));
+    }
+    reconnect_members(&members).await
+}
+
+/// Disconnect and reconnect `members` concurrently, listing any that failed.
+/// Each gets a fresh retry budget, so the group is retried again even if
+/// its health loop retries had run out.
+async fn reconnect_members(members: &[(String, Arc<McpConnection>)]) -> Result<(), String> {
+    let results = futures::future::join_all(members.iter().map(|(id, conn)| async move {
+        conn.reset_reconnect_attempts().await;
+        conn.disconnect().await;
+        let result = conn.connect(ReconnectReason::UserRequested).await;
+        result.map_err(|e| format!("{} ({})", id, e))
+    }))
+    .await;
+    let failed: Vec<String> = results.into_iter().filter_map(Result::err).collect();
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Failed to reconnect: {}", failed.join(", ")))
+    }
+}
+
+/// `connect_mcp` for an MCP identified by its name
+#[tauri::command]
+pub async fn connect_mcp_by_name(name: String, state: State<'_, AppState>) -> Result<(), String> {
+    let id = resolve_mcp_name(&name, &state).await?;
+    connect_mcp(id, state).await
+}
+
+async fn resolve_mcp_name(name: &str, state: &State<'_, AppState>) -> Result<String, String> {
+    let mgr = state.manager.lock().await;
+    mgr.resolve_name(name).map_err(|e| e.to_string())
 }
 
 /// Manually disconnect a specific MCP
@@ -109,6 +291,21 @@ pub async fn disconnect_mcp(id: String, state: State<'_, AppState>) -> Result<()
     Ok(())
 }
 
+/// Clear a stale error on an MCP, marking it Connected again if it still
+/// answers a ping. Returns the resulting state.
+#[tauri::command]
+pub async fn clear_error(
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<ConnectionState, String> {
+    let conn = {
+        let mgr = state.manager.lock().await;
+        mgr.get_connection(&id)
+            .ok_or_else(|| format!("MCP '{}' not found", id))?
+    };
+    Ok(conn.clear_error().await)
+}
+
 /// Update disabled tools/resources for a specific MCP
 #[tauri::command]
 pub async fn set_disabled_items(
@@ -126,6 +323,119 @@ pub async fn set_disabled_items(
     Ok(())
 }
 
+/// Remove disabled entries for tools/resources the MCP no longer reports.
+/// Returns the number of entries removed.
+#[tauri::command]
+pub async fn prune_disabled_items(
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let pruned = {
+        let mut mgr = state.manager.lock().await;
+        mgr.prune_disabled_items(&id).await.map_err(|e| e.to_string())?
+    };
+    if pruned > 0 {
+        persist_config(&state).await?;
+    }
+    Ok(pruned)
+}
+
+/// Disable or re-enable tools matching a glob on the given MCPs (all when
+/// `mcp_ids` is omitted), saving the config once
+#[tauri::command]
+pub async fn bulk_set_tool_disabled(
+    pattern: String,
+    disabled: bool,
+    mcp_ids: Option<Vec<String>>,
+    state: State<'_, AppState>,
+) -> Result<Vec<BulkToolUpdate>, String> {
+    let updates = {
+        let mut mgr = state.manager.lock().await;
+        mgr.bulk_set_tool_disabled(&pattern, disabled, mcp_ids)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    persist_config(&state).await?;
+    Ok(updates)
+}
+
+/// Check whether a specific tool of an MCP is exposed through the proxy
+#[tauri::command]
+pub async fn is_tool_enabled(
+    id: String,
+    tool_name: String,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let mgr = state.manager.lock().await;
+    mgr.is_tool_enabled(&id, &tool_name).await.map_err(|e| e.to_string())
+}
+
+/// List an MCP's cached tools with their enabled flags
+#[tauri::command]
+pub async fn get_tool_states(
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<ToolState>, String> {
+    let mgr = state.manager.lock().await;
+    mgr.tool_states(&id).await.map_err(|e| e.to_string())
+}
+
+/// List the tools of every connected MCP in one call, optionally including
+/// disabled ones
+#[tauri::command]
+pub async fn get_all_tools(
+    include_disabled: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<CatalogTool>, String> {
+    let mgr = state.manager.lock().await;
+    Ok(mgr.all_tools(include_disabled.unwrap_or(false)).await)
+}
+
+/// Set a custom display order for MCPs (first id is listed first)
+#[tauri::command]
+pub async fn reorder_mcps(ids: Vec<String>, state: State<'_, AppState>) -> Result<(), String> {
+    {
+        let mut mgr = state.manager.lock().await;
+        mgr.reorder_mcps(&ids).map_err(|e| e.to_string())?;
+    }
+    persist_config(&state).await?;
+    Ok(())
+}
+
+/// Stop the health loop from pinging and reconnecting during a bulk
+/// change.  The pause lapses after `timeout_secs` (default and cap:
+/// `MAX_HEALTH_PAUSE_SECS`) if `resume_health_checks` is never called.
+#[tauri::command]
+pub async fn pause_health_checks(
+    timeout_secs: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let timeout = timeout_secs.unwrap_or(MAX_HEALTH_PAUSE_SECS);
+    let pause = state.manager.lock().await.health_pause();
+    pause.pause(std::time::Duration::from_secs(timeout));
+    tracing::info!("Health checks paused for up to {}s", timeout.min(MAX_HEALTH_PAUSE_SECS));
+    Ok(())
+}
+
+/// Undo `pause_health_checks`; the next health cycle runs normally
+#[tauri::command]
+pub async fn resume_health_checks(state: State<'_, AppState>) -> Result<(), String> {
+    state.manager.lock().await.health_pause().resume();
+    tracing::info!("Health checks resumed");
+    Ok(())
+}
+
+/// Persist the safe mode flag; it applies from the next start. Returns
+/// whether that start will be in safe mode, since the env var overrides it.
+#[tauri::command]
+pub async fn set_safe_mode(enabled: bool, state: State<'_, AppState>) -> Result<bool, String> {
+    let active = state.manager.lock().await.set_safe_mode(enabled);
+
+    persist_config(&state).await?;
+    tracing::info!("Safe mode {} for the next start", if enabled { "enabled" } else { "disabled" });
+    Ok(active)
+}
+
 /// Get the proxy URL for a specific MCP
 #[tauri::command]
 pub async fn get_proxy_url(id: String, state: State<'_, AppState>) -> Result<String, String> {
@@ -140,6 +450,163 @@ pub async fn get_app_config(state: State<'_, AppState>) -> Result<AppConfig, Str
     Ok(mgr.get_config().clone())
 }
 
+/// Write the in-memory config to disk and return once the file is written.
+/// Every mutating command already saves synchronously, so this is only
+/// needed before operations that must not race an external edit.
+#[tauri::command]
+pub async fn flush_config(state: State<'_, AppState>) -> Result<(), String> {
+    persist_config(&state).await
+}
+
+/// Re-read config.json and apply the differences, reconnecting only the
+/// MCPs whose connection settings changed
+#[tauri::command]
+pub async fn reload_config(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ReloadSummary, String> {
+    let config = {
+        let config_mgr = state.config_manager.lock().await;
+        config_mgr.load().map_err(|e| e.to_string())?
+    };
+    ConfigManager::validate(&config)?;
+
+    let (summary, statuses) = {
+        let mut mgr = state.manager.lock().await;
+        let summary = mgr.reload(config).await;
+        (summary, mgr.list_statuses().await)
+    };
+    tracing::info!(
+        "Config reloaded: {} added, {} updated ({} reconnected), {} removed",
+        summary.added.len(),
+        summary.updated.len(),
+        summary.reconnected.len(),
+        summary.removed.len()
+    );
+
+    use tauri::Emitter;
+    let _ = app.emit("mcp-statuses-changed", &statuses);
+    Ok(summary)
+}
+
+/// Names of the saved config profiles
+#[tauri::command]
+pub async fn list_profiles(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    let config_mgr = state.config_manager.lock().await;
+    config_mgr.list_profiles().map_err(|e| e.to_string())
+}
+
+/// Save the current config as a named profile and make it the active one
+#[tauri::command]
+pub async fn save_current_as_profile(name: String, state: State<'_, AppState>) -> Result<(), String> {
+    save_profile_as(&state, &name).await
+}
+
+async fn save_profile_as(state: &AppState, name: &str) -> Result<(), String> {
+    {
+        let mut mgr = state.manager.lock().await;
+        let config = mgr.get_config().clone();
+        let config_mgr = state.config_manager.lock().await;
+        config_mgr.save_profile(name, &config).map_err(|e| e.to_string())?;
+        mgr.set_active_profile(Some(name.to_string()));
+    }
+    persist_config(state).await
+}
+
+/// Shut down all connections and start over with the named profile.  The
+/// current config is saved back to the active profile first, or kept as a
+/// new `unsaved-<time>` profile when there is none.
+#[tauri::command]
+pub async fn switch_profile(
+    name: String,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ProfileSwitch, String> {
+    let (switch, statuses) = switch_to_profile(&state, &name).await?;
+
+    use tauri::Emitter;
+    let _ = app.emit("mcp-statuses-changed", &statuses);
+    Ok(switch)
+}
+
+async fn switch_to_profile(
+    state: &AppState,
+    name: &str,
+) -> Result<(ProfileSwitch, Vec<McpStatus>), String> {
+    let (pending, result) = {
+        let mut mgr = state.manager.lock().await;
+        let config_mgr = state.config_manager.lock().await;
+        let config = config_mgr.load_profile(name).map_err(|e| e.to_string())?;
+        ConfigManager::validate(&config)?;
+
+        let current = mgr.get_config();
+        let saved_as = match &current.active_profile {
+            Some(active) => {
+                config_mgr
+                    .save_profile(active, current)
+                    .map_err(|e| e.to_string())?;
+                None
+            }
+            None => {
+                // Otherwise the only copy left would be the single .bak
+                let profiles = config_mgr.list_profiles().map_err(|e| e.to_string())?;
+                let generated = unsaved_profile_name(&profiles);
+                config_mgr
+                    .save_profile(&generated, current)
+                    .map_err(|e| e.to_string())?;
+                tracing::info!("Kept the unsaved config as profile '{}'", generated);
+                Some(generated)
+            }
+        };
+        let result = ProfileSwitch {
+            saved_as,
+            restart_required: config.proxy_port != state.proxy_port,
+        };
+        (mgr.switch_config(config).await, result)
+    };
+
+    pending.run().await;
+    let statuses = state.manager.lock().await.list_statuses().await;
+    tracing::info!("Switched to profile '{}'", name);
+
+    persist_config(state).await?;
+    Ok((result, statuses))
+}
+
+/// A profile name for a config that was never saved as one
+fn unsaved_profile_name(existing: &[String]) -> String {
+    let base = format!("unsaved-{}", chrono::Utc::now().format("%Y%m%dT%H%M%SZ"));
+    let mut name = base.clone();
+    let mut n = 2;
+    while existing.contains(&name) {
+        name = format!("{}-{}", base, n);
+        n += 1;
+    }
+    name
+}
+
+/// Get the configuration as the proxy actually applies it (defaults
+/// resolved, secrets redacted), as opposed to the stored config
+#[tauri::command]
+pub async fn get_effective_config(state: State<'_, AppState>) -> Result<AppConfig, String> {
+    let mgr = state.manager.lock().await;
+    Ok(mgr.effective_config())
+}
+
+/// MCP counts and config file size, with a warning past the recommended limits
+#[tauri::command]
+pub async fn get_config_stats(state: State<'_, AppState>) -> Result<ConfigStats, String> {
+    let mgr = state.manager.lock().await;
+    let config_mgr = state.config_manager.lock().await;
+    Ok(config_mgr.stats(mgr.get_config()))
+}
+
+/// Warning about a corrupt config file that was set aside at startup, if any
+#[tauri::command]
+pub async fn get_config_warning(state: State<'_, AppState>) -> Result<Option<String>, String> {
+    Ok(state.config_warning.clone())
+}
+
 /// Update the global app configuration
 #[tauri::command]
 pub async fn update_app_config(
@@ -148,6 +615,13 @@ pub async fn update_app_config(
 ) -> Result<(), String> {
     ConfigManager::validate(&config)?;
 
+    let current_port = state.manager.lock().await.get_config().proxy_port;
+    if config.proxy_port != current_port
+        && !port_available(config.proxy_port, state.proxy_port).await
+    {
+        return Err(format!("Port {} is already in use", config.proxy_port));
+    }
+
     {
         let mut mgr = state.manager.lock().await;
         mgr.update_config(config.clone()).await;
@@ -162,6 +636,43 @@ pub async fn update_app_config(
     Ok(())
 }
 
+/// Replace the proxy auth token with a new random one and persist it.
+/// Returns the token so it can be copied into clients; requests still
+/// using the previous token are rejected from now on.
+#[tauri::command]
+pub async fn rotate_proxy_token(state: State<'_, AppState>) -> Result<String, String> {
+    let mut bytes = [0u8; 32];
+    ring::rand::SecureRandom::fill(&ring::rand::SystemRandom::new(), &mut bytes)
+        .map_err(|_| "Failed to generate a random token".to_string())?;
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    state
+        .manager
+        .lock()
+        .await
+        .set_proxy_auth_token(token.clone());
+    persist_config(&state).await?;
+    tracing::info!("Proxy auth token rotated");
+    Ok(token)
+}
+
+/// Check whether the proxy could listen on a port.  The port the proxy is
+/// currently serving on counts as available.
+#[tauri::command]
+pub async fn is_port_available(port: u16, state: State<'_, AppState>) -> Result<bool, String> {
+    Ok(port_available(port, state.proxy_port).await)
+}
+
+/// Whether the proxy could move to `port` given it is serving on
+/// `serving_port`: its own port counts as available although it is bound
+async fn port_available(port: u16, serving_port: u16) -> bool {
+    port == serving_port || port_is_free(port).await
+}
+
+async fn port_is_free(port: u16) -> bool {
+    tokio::net::TcpListener::bind(("127.0.0.1", port)).await.is_ok()
+}
+
 /// Get recent log entries
 #[tauri::command]
 pub async fn get_logs(state: State<'_, AppState>) -> Result<Vec<LogEntry>, String> {
@@ -172,64 +683,270 @@ pub async fn get_logs(state: State<'_, AppState>) -> Result<Vec<LogEntry>, Strin
     Ok(logs.iter().cloned().collect())
 }
 
-/// Check if an MCP is already configured in Claude Desktop
+/// Export the log buffer as NDJSON (one `LogEntry` per line), optionally
+/// filtered to a minimum level and an RFC 3339 time range.  Returns the
+/// NDJSON itself, or the file path when `path` is given.
 #[tauri::command]
-pub async fn check_claude_desktop(
-    mcp_id: String,
+pub async fn export_logs(
+    path: Option<String>,
+    level: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
     state: State<'_, AppState>,
-) -> Result<bool, String> {
-    let name = {
-        let mgr = state.manager.lock().await;
-        let config = mgr.get_config();
-        config
-            .mcps
-            .iter()
-            .find(|m| m.id == mcp_id)
-            .ok_or("MCP not found")?
-            .name
-            .clone()
+) -> Result<String, String> {
+    let entries: Vec<LogEntry> = {
+        let logs = state
+            .log_store
+            .lock()
+            .map_err(|_| "Log buffer unavailable".to_string())?;
+        logs.iter().cloned().collect()
     };
+    let entries = filter_logs(entries, level.as_deref(), since.as_deref(), until.as_deref())?;
+    let ndjson = logs_to_ndjson(&entries)?;
 
-    let config_path = claude_desktop_config_path()?;
-    if !config_path.exists() {
-        return Ok(false);
+    match path {
+        Some(path) => {
+            std::fs::write(&path, ndjson).map_err(|e| e.to_string())?;
+            tracing::info!("Exported {} log entries to {}", entries.len(), path);
+            Ok(path)
+        }
+        None => Ok(ndjson),
     }
-
-    let content = std::fs::read_to_string(&config_path).map_err(|e| e.to_string())?;
-    let config: serde_json::Value =
-        serde_json::from_str(&content).map_err(|e| e.to_string())?;
-
-    Ok(config
-        .get("mcpServers")
-        .and_then(|s| s.get(&name))
-        .is_some())
 }
 
-/// Add an MCP to Claude Desktop's config via the bridge sidecar
+/// Write a zip for bug reports with the redacted effective config, recent
+/// logs, MCP statuses, connection histories, build info and environment
+/// checks.  Known secret values are scrubbed from every entry.
 #[tauri::command]
-pub async fn add_to_claude_desktop(
-    mcp_id: String,
-    state: State<'_, AppState>,
-) -> Result<(), String> {
-    let (name, port) = get_mcp_name_and_port(&mcp_id, &state).await?;
-    let bridge_path = find_bridge_binary()?;
-    let config_path = claude_desktop_config_path()?;
+pub async fn export_diagnostics(path: String, state: State<'_, AppState>) -> Result<String, String> {
+    write_diagnostics(&state, &path).await?;
+    tracing::info!("Exported diagnostics bundle to {}", path);
+    Ok(path)
+}
 
-    let mut config = read_claude_desktop_config(&config_path)?;
+async fn write_diagnostics(state: &AppState, path: &str) -> Result<(), String> {
+    let (config, statuses, mut secrets, connections) = {
+        let mgr = state.manager.lock().await;
+        let connections: Vec<_> = mgr
+            .get_config()
+            .mcps
+            .iter()
+            .filter_map(|m| mgr.get_connection(&m.id))
+            .collect();
+        (mgr.effective_config(), mgr.list_statuses().await, mgr.secret_values(), connections)
+    };
+    secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
 
-    // Ensure mcpServers object exists
-    if config.get("mcpServers").is_none() {
-        config["mcpServers"] = serde_json::json!({});
+    let mut events = serde_json::Map::new();
+    for conn in connections {
+        events.insert(
+            conn.config.id.clone(),
+            serde_json::to_value(conn.get_events().await).map_err(|e| e.to_string())?,
+        );
     }
 
-    if config["mcpServers"].get(&name).is_some() {
-        return Err("Already added to Claude Desktop".to_string());
+    let logs: Vec<LogEntry> = {
+        let logs = state
+            .log_store
+            .lock()
+            .map_err(|_| "Log buffer unavailable".to_string())?;
+        logs.iter().cloned().collect()
+    };
+
+    let build_info = get_build_info().await?;
+    let environment = environment_checks(state).await;
+    let to_value = |value: Result<serde_json::Value, serde_json::Error>| {
+        let mut value = value.map_err(|e| e.to_string())?;
+        scrub_secrets(&mut value, &secrets);
+        Ok::<_, String>(value)
+    };
+    let pretty = |value| -> Result<String, String> {
+        serde_json::to_string_pretty(&to_value(value)?).map_err(|e| e.to_string())
+    };
+    let mut ndjson = String::new();
+    for entry in &logs {
+        ndjson.push_str(&to_value(serde_json::to_value(entry))?.to_string());
+        ndjson.push('\n');
     }
+    let entries = [
+        ("config.json", pretty(serde_json::to_value(&config))?),
+        ("statuses.json", pretty(serde_json::to_value(&statuses))?),
+        ("connection_events.json", pretty(Ok(serde_json::Value::Object(events)))?),
+        ("logs.ndjson", ndjson),
+        ("build_info.json", pretty(serde_json::to_value(&build_info))?),
+        ("environment.json", pretty(serde_json::to_value(&environment))?),
+    ];
 
-    config["mcpServers"][&name] = serde_json::json!({
-        "command": bridge_path,
-        "args": ["--mcp-id", &mcp_id, "--port", &port.to_string()]
-    });
+    let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    for (name, contents) in entries {
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        std::io::Write::write_all(&mut zip, contents.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Replace every occurrence of a known secret value in the strings of
+/// `value` with the placeholder, however short the secret.  Done before
+/// serializing, so secrets with quotes or backslashes match too.  Longer
+/// secrets should come first so one containing another is still masked
+/// whole.
+fn scrub_secrets(value: &mut serde_json::Value, secrets: &[String]) {
+    match value {
+        serde_json::Value::String(text) => {
+            for secret in secrets {
+                if text.contains(secret.as_str()) {
+                    *text = text.replace(secret.as_str(), crate::mcp::manager::REDACTED);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            items.iter_mut().for_each(|item| scrub_secrets(item, secrets));
+        }
+        serde_json::Value::Object(map) => {
+            map.values_mut().for_each(|item| scrub_secrets(item, secrets));
+        }
+        _ => {}
+    }
+}
+
+/// Serialize log entries as NDJSON, one entry per line
+fn logs_to_ndjson(entries: &[LogEntry]) -> Result<String, String> {
+    let mut ndjson = String::new();
+    for entry in entries {
+        ndjson.push_str(&serde_json::to_string(entry).map_err(|e| e.to_string())?);
+        ndjson.push('\n');
+    }
+    Ok(ndjson)
+}
+
+/// Keep entries at or above `level` whose timestamp lies within
+/// `since..=until` (all bounds optional)
+fn filter_logs(
+    entries: Vec<LogEntry>,
+    level: Option<&str>,
+    since: Option<&str>,
+    until: Option<&str>,
+) -> Result<Vec<LogEntry>, String> {
+    let min_level = level
+        .map(|l| {
+            l.parse::<tracing::Level>()
+                .map_err(|_| format!("Invalid log level: {}", l))
+        })
+        .transpose()?;
+    let parse_time = |t: &str| {
+        chrono::DateTime::parse_from_rfc3339(t).map_err(|e| format!("Invalid time '{}': {}", t, e))
+    };
+    let since = since.map(parse_time).transpose()?;
+    let until = until.map(parse_time).transpose()?;
+
+    Ok(entries
+        .into_iter()
+        .filter(|entry| {
+            // More verbose levels compare greater (TRACE > ... > ERROR)
+            let level_ok = match (min_level, entry.level.parse::<tracing::Level>()) {
+                (Some(min), Ok(entry_level)) => entry_level <= min,
+                _ => true,
+            };
+            let time_ok = match chrono::DateTime::parse_from_rfc3339(&entry.timestamp) {
+                Ok(t) => since.is_none_or(|s| t >= s) && until.is_none_or(|u| t <= u),
+                Err(_) => since.is_none() && until.is_none(),
+            };
+            level_ok && time_ok
+        })
+        .collect())
+}
+
+/// Check if an MCP is already configured in Claude Desktop
+#[tauri::command]
+pub async fn check_claude_desktop(
+    mcp_id: String,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let (name, _, _) = get_bridge_target(&mcp_id, &state).await?;
+
+    let config_path = claude_desktop_config_path()?;
+    if !config_path.exists() {
+        return Ok(false);
+    }
+
+    let content = std::fs::read_to_string(&config_path).map_err(|e| e.to_string())?;
+    let config: serde_json::Value =
+        serde_json::from_str(&content).map_err(|e| e.to_string())?;
+
+    Ok(config
+        .get("mcpServers")
+        .and_then(|s| s.get(&name))
+        .is_some())
+}
+
+/// Check that Claude Desktop's config parses and has the expected
+/// `mcpServers` shape, so the UI can warn before editing a broken file
+#[tauri::command]
+pub async fn check_claude_config_valid() -> Result<ClaudeConfigValidity, String> {
+    Ok(claude_config_validity(&claude_desktop_config_path()?))
+}
+
+fn claude_config_validity(config_path: &std::path::Path) -> ClaudeConfigValidity {
+    let mut validity = ClaudeConfigValidity {
+        exists: config_path.exists(),
+        valid: false,
+        has_mcp_servers: false,
+        server_names: Vec::new(),
+        error: None,
+    };
+    if !validity.exists {
+        validity.valid = true;
+        return validity;
+    }
+
+    let parsed = std::fs::read_to_string(config_path)
+        .map_err(|e| format!("Cannot read {}: {}", config_path.display(), e))
+        .and_then(|content| {
+            serde_json::from_str::<serde_json::Value>(&content)
+                .map_err(|e| format!("Not valid JSON: {}", e))
+        });
+    let config = match parsed {
+        Ok(config) => config,
+        Err(e) => {
+            validity.error = Some(e);
+            return validity;
+        }
+    };
+
+    let Some(root) = config.as_object() else {
+        validity.error = Some("Top level is not a JSON object".to_string());
+        return validity;
+    };
+    match root.get("mcpServers") {
+        None => validity.valid = true,
+        Some(serde_json::Value::Object(servers)) => {
+            validity.valid = true;
+            validity.has_mcp_servers = true;
+            validity.server_names = servers.keys().cloned().collect();
+            validity.server_names.sort();
+        }
+        Some(_) => validity.error = Some("mcpServers is not a JSON object".to_string()),
+    }
+    validity
+}
+
+/// Add an MCP to Claude Desktop's config via the bridge sidecar
+#[tauri::command]
+pub async fn add_to_claude_desktop(
+    mcp_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let (name, port, token) = get_bridge_target(&mcp_id, &state).await?;
+    let bridge_path = find_bridge_binary()?;
+    let config_path = claude_desktop_config_path()?;
+
+    let mut config = read_claude_desktop_config(&config_path)?;
+    let entry = bridge_entry(&bridge_path, &mcp_id, port, token.as_deref());
+    set_claude_entry(&mut config, &name, entry, false)?;
 
     write_claude_desktop_config(&config_path, &config)?;
     Ok(())
@@ -241,20 +958,13 @@ pub async fn update_in_claude_desktop(
     mcp_id: String,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    let (name, port) = get_mcp_name_and_port(&mcp_id, &state).await?;
+    let (name, port, token) = get_bridge_target(&mcp_id, &state).await?;
     let bridge_path = find_bridge_binary()?;
     let config_path = claude_desktop_config_path()?;
 
     let mut config = read_claude_desktop_config(&config_path)?;
-
-    if config.get("mcpServers").is_none() {
-        config["mcpServers"] = serde_json::json!({});
-    }
-
-    config["mcpServers"][&name] = serde_json::json!({
-        "command": bridge_path,
-        "args": ["--mcp-id", &mcp_id, "--port", &port.to_string()]
-    });
+    let entry = bridge_entry(&bridge_path, &mcp_id, port, token.as_deref());
+    set_claude_entry(&mut config, &name, entry, true)?;
 
     write_claude_desktop_config(&config_path, &config)?;
     Ok(())
@@ -266,43 +976,525 @@ pub async fn remove_from_claude_desktop(
     mcp_id: String,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    let name = {
+    let (name, _, _) = get_bridge_target(&mcp_id, &state).await?;
+
+    let config_path = claude_desktop_config_path()?;
+    if !config_path.exists() {
+        return Err("Claude Desktop config not found".to_string());
+    }
+
+    let mut config = read_claude_desktop_config(&config_path)?;
+    remove_claude_entry(&mut config, &name)?;
+
+    write_claude_desktop_config(&config_path, &config)?;
+    Ok(())
+}
+
+/// Return app/bridge version and build metadata
+#[tauri::command]
+pub async fn get_build_info() -> Result<BuildInfo, String> {
+    let build_timestamp = env!("BUILD_TIMESTAMP")
+        .parse::<i64>()
+        .ok()
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_default();
+    Ok(BuildInfo {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        bridge_version: env!("CARGO_PKG_VERSION").to_string(),
+        target_triple: env!("BUILD_TARGET").to_string(),
+        rmcp_version: env!("BUILD_RMCP_VERSION").to_string(),
+        build_timestamp,
+    })
+}
+
+/// Report whether common prerequisites (proxy port, bridge binary, Claude
+/// config, stdio executables) are in place
+#[tauri::command]
+pub async fn check_environment(
+    state: State<'_, AppState>,
+) -> Result<Vec<EnvironmentCheck>, String> {
+    Ok(environment_checks(&state).await)
+}
+
+async fn environment_checks(state: &AppState) -> Vec<EnvironmentCheck> {
+    let (proxy_port, stdio_commands) = {
         let mgr = state.manager.lock().await;
         let config = mgr.get_config();
-        config
+        let commands: Vec<String> = config
             .mcps
             .iter()
-            .find(|m| m.id == mcp_id)
-            .ok_or("MCP not found")?
-            .name
-            .clone()
+            .filter(|m| m.transport_type == TransportType::Stdio)
+            .filter_map(|m| m.command.as_deref())
+            .filter_map(|c| c.split_whitespace().next())
+            .map(|c| c.to_string())
+            .collect();
+        (config.proxy_port, commands)
     };
 
+    let mut checks = vec![
+        check_proxy_port(proxy_port).await,
+        check_bridge_binary(find_bridge_binary()),
+        check_claude_config_writable(claude_desktop_config_path()),
+    ];
+
+    let mut seen = std::collections::HashSet::new();
+    for executable in stdio_commands {
+        if seen.insert(executable.clone()) {
+            checks.push(check_executable(&executable));
+        }
+    }
+
+    checks
+}
+
+/// Check every bridge entry in Claude Desktop's config: the bridge binary
+/// must exist, the MCP id must still be configured, and the proxy must answer
+/// a ping on /mcp/:id. Catches stale entries pointing at removed MCPs.
+#[tauri::command]
+pub async fn verify_claude_integration(
+    state: State<'_, AppState>,
+) -> Result<Vec<ClaudeIntegrationCheck>, String> {
     let config_path = claude_desktop_config_path()?;
     if !config_path.exists() {
-        return Err("Claude Desktop config not found".to_string());
+        return Ok(Vec::new());
     }
+    let claude_config = read_claude_desktop_config(&config_path)?;
 
-    let mut config = read_claude_desktop_config(&config_path)?;
+    let (known_ids, proxy_port, token): (std::collections::HashSet<String>, u16, Option<String>) = {
+        let mgr = state.manager.lock().await;
+        let config = mgr.get_config();
+        (
+            config.mcps.iter().map(|m| m.id.clone()).collect(),
+            config.proxy_port,
+            config.proxy_auth_token.clone(),
+        )
+    };
 
-    let removed = config
-        .get_mut("mcpServers")
-        .and_then(|s| s.as_object_mut())
-        .map(|servers| servers.remove(&name).is_some())
-        .unwrap_or(false);
+    check_claude_entries(&claude_config, &known_ids, proxy_port, token.as_deref()).await
+}
 
-    if !removed {
-        return Err("MCP not found in Claude Desktop config".to_string());
+/// Check the bridge entries of a parsed Claude Desktop config against the
+/// proxy's MCP ids, port and token
+async fn check_claude_entries(
+    claude_config: &serde_json::Value,
+    known_ids: &std::collections::HashSet<String>,
+    proxy_port: u16,
+    token: Option<&str>,
+) -> Result<Vec<ClaudeIntegrationCheck>, String> {
+    let Some(servers) = claude_config.get("mcpServers").and_then(|s| s.as_object()) else {
+        return Ok(Vec::new());
+    };
+
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(5))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut checks = Vec::new();
+    for (name, entry) in servers {
+        let args: Vec<&str> = entry
+            .get("args")
+            .and_then(|a| a.as_array())
+            .map(|a| a.iter().filter_map(|v| v.as_str()).collect())
+            .unwrap_or_default();
+        // Entries not written by us carry no --mcp-id and are left alone
+        let Some(mcp_id) = bridge_arg(&args, "--mcp-id") else {
+            continue;
+        };
+        let port = bridge_arg(&args, "--port")
+            .and_then(|p| p.parse::<u16>().ok())
+            .unwrap_or(proxy_port);
+
+        let bridge_exists = entry
+            .get("command")
+            .and_then(|c| c.as_str())
+            .is_some_and(|c| is_executable(std::path::Path::new(c)));
+        let mcp_exists = known_ids.contains(mcp_id);
+        // The entry's own token is what the bridge will send
+        let entry_token = entry
+            .get("env")
+            .and_then(|e| e.get(BRIDGE_TOKEN_ENV))
+            .and_then(|t| t.as_str());
+        let proxy_reachable = ping_proxy(&client, port, mcp_id, entry_token).await;
+
+        let detail = if !mcp_exists {
+            "MCP no longer exists in the proxy config".to_string()
+        } else if !bridge_exists {
+            "Bridge binary is missing or not executable".to_string()
+        } else if port != proxy_port {
+            format!("Entry points at port {} but the proxy uses {}", port, proxy_port)
+        } else if token.is_some() && entry_token != token {
+            "Entry carries an outdated proxy token".to_string()
+        } else if !proxy_reachable {
+            "Proxy did not answer ping".to_string()
+        } else {
+            "OK".to_string()
+        };
+
+        checks.push(ClaudeIntegrationCheck {
+            name: name.clone(),
+            mcp_id: mcp_id.to_string(),
+            mcp_exists,
+            bridge_exists,
+            proxy_reachable,
+            detail,
+        });
     }
 
-    write_claude_desktop_config(&config_path, &config)?;
-    Ok(())
+    Ok(checks)
 }
 
-async fn get_mcp_name_and_port(
+fn bridge_arg<'a>(args: &[&'a str], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| *a == flag)
+        .and_then(|i| args.get(i + 1))
+        .copied()
+}
+
+async fn ping_proxy(
+    client: &reqwest::Client,
+    port: u16,
+    mcp_id: &str,
+    token: Option<&str>,
+) -> bool {
+    let url = crate::mcp::manager::proxy_url(port, mcp_id);
+    let request = serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "ping"});
+    let mut post = client.post(&url).json(&request);
+    if let Some(token) = token {
+        post = post.bearer_auth(token);
+    }
+    match post.send().await {
+        Ok(resp) => resp
+            .json::<serde_json::Value>()
+            .await
+            .map(|body| body.get("result").is_some())
+            .unwrap_or(false),
+        Err(_) => false,
+    }
+}
+
+/// Send a ping to an MCP through the proxy's own HTTP endpoint and time the
+/// round trip, split into upstream time and proxy overhead
+#[tauri::command]
+pub async fn measure_latency(
+    mcp_id: String,
+    state: State<'_, AppState>,
+) -> Result<LatencyMeasurement, String> {
+    let token = {
+        let mgr = state.manager.lock().await;
+        mgr.get_connection(&mcp_id).ok_or("MCP not found")?;
+        mgr.get_config().proxy_auth_token.clone()
+    };
+    let url = crate::mcp::manager::proxy_url(state.proxy_port, &mcp_id);
+    measure_proxy_latency(&url, token.as_deref()).await
+}
+
+async fn measure_proxy_latency(
+    url: &str,
+    token: Option<&str>,
+) -> Result<LatencyMeasurement, String> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let request = serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "ping"});
+    let mut post = client.post(url).json(&request);
+    if let Some(token) = token {
+        post = post.bearer_auth(token);
+    }
+
+    let started = std::time::Instant::now();
+    let response = post.send().await.map_err(|e| e.to_string())?;
+    let upstream_ms = response
+        .headers()
+        .get("server-timing")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split("dur=").nth(1))
+        .and_then(|ms| ms.parse::<f64>().ok());
+    let status = response.status();
+    let body = response.text().await.map_err(|e| e.to_string())?;
+    let total_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    if !status.is_success() {
+        return Err(format!("Proxy answered HTTP {}", status.as_u16()));
+    }
+    let reply: serde_json::Value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+    if let Some(message) = reply.pointer("/error/message").and_then(|m| m.as_str()) {
+        return Err(format!("Ping failed: {}", message));
+    }
+
+    Ok(LatencyMeasurement {
+        total_ms,
+        upstream_ms,
+        proxy_overhead_ms: upstream_ms.map(|upstream| (total_ms - upstream).max(0.0)),
+    })
+}
+
+/// Load-test a tool: call it `total_requests` times, at most `concurrency`
+/// at once, and report latency percentiles, error rate and throughput
+#[tauri::command]
+pub async fn benchmark_tool(
+    mcp_id: String,
+    tool_name: String,
+    args: Option<serde_json::Value>,
+    concurrency: usize,
+    total_requests: usize,
+    state: State<'_, AppState>,
+) -> Result<ToolBenchmark, String> {
+    let conn = {
+        let mgr = state.manager.lock().await;
+        mgr.get_connection(&mcp_id)
+            .ok_or_else(|| format!("MCP '{}' not found", mcp_id))?
+    };
+    let args = args.unwrap_or_else(|| serde_json::json!({}));
+    run_tool_benchmark(&conn, &tool_name, args, concurrency, total_requests).await
+}
+
+async fn run_tool_benchmark(
+    conn: &McpConnection,
+    tool_name: &str,
+    args: serde_json::Value,
+    concurrency: usize,
+    total_requests: usize,
+) -> Result<ToolBenchmark, String> {
+    use futures::StreamExt;
+
+    if concurrency == 0 || total_requests == 0 {
+        return Err("Concurrency and total requests must be at least 1".to_string());
+    }
+    if conn.get_state().await != ConnectionState::Connected {
+        return Err(format!("MCP '{}' is not connected", conn.config.id));
+    }
+    // Going past the inflight limit would only measure our own rejections
+    let concurrency = conn
+        .config
+        .max_inflight_requests
+        .map_or(concurrency, |max| concurrency.min(max.max(1)));
+    let params = serde_json::json!({"name": tool_name, "arguments": args});
+
+    let started = std::time::Instant::now();
+    let outcomes: Vec<(f64, bool)> = futures::stream::iter(0..total_requests)
+        .map(|_| {
+            let params = params.clone();
+            async move {
+                let call_started = std::time::Instant::now();
+                let ok = match conn.execute_request("tools/call", params).await {
+                    Ok(result) => result.get("isError") != Some(&serde_json::Value::Bool(true)),
+                    Err(_) => false,
+                };
+                (call_started.elapsed().as_secs_f64() * 1000.0, ok)
+            }
+        })
+        .buffer_unordered(concurrency)
+        .collect()
+        .await;
+    let elapsed = started.elapsed();
+
+    let mut latencies: Vec<f64> = outcomes
+        .iter()
+        .filter(|(_, ok)| *ok)
+        .map(|(ms, _)| *ms)
+        .collect();
+    latencies.sort_by(f64::total_cmp);
+    // Nearest-rank percentile
+    let percentile = |p: f64| -> Option<f64> {
+        let rank = ((p / 100.0) * latencies.len() as f64).ceil() as usize;
+        latencies.get(rank.saturating_sub(1)).copied()
+    };
+    let errors = total_requests - latencies.len();
+
+    Ok(ToolBenchmark {
+        total_requests,
+        concurrency,
+        errors,
+        error_rate: errors as f64 / total_requests as f64,
+        duration_ms: elapsed.as_secs_f64() * 1000.0,
+        requests_per_sec: total_requests as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+        p50_ms: percentile(50.0),
+        p95_ms: percentile(95.0),
+        p99_ms: percentile(99.0),
+    })
+}
+
+async fn check_proxy_port(port: u16) -> EnvironmentCheck {
+    let name = format!("Proxy port {}", port);
+    match tokio::net::TcpListener::bind(("127.0.0.1", port)).await {
+        Ok(_) => EnvironmentCheck {
+            name,
+            passed: true,
+            detail: "Port is free".to_string(),
+        },
+        Err(e) => {
+            // The port is expected to be taken by our own proxy server
+            let health_url = format!("http://127.0.0.1:{}/health", port);
+            let ours = match reqwest::get(&health_url).await {
+                Ok(resp) => resp
+                    .json::<serde_json::Value>()
+                    .await
+                    .map(|body| {
+                        matches!(
+                            body.get("status").and_then(|s| s.as_str()),
+                            Some("ok" | "degraded" | "down")
+                        )
+                    })
+                    .unwrap_or(false),
+                Err(_) => false,
+            };
+            if ours {
+                EnvironmentCheck {
+                    name,
+                    passed: true,
+                    detail: "Port is bound by Local MCP Proxy".to_string(),
+                }
+            } else {
+                EnvironmentCheck {
+                    name,
+                    passed: false,
+                    detail: format!("Port is not bindable: {}", e),
+                }
+            }
+        }
+    }
+}
+
+/// Check the bridge binary found by `find_bridge_binary` (or the error from
+/// looking for it)
+fn check_bridge_binary(bridge: Result<String, String>) -> EnvironmentCheck {
+    let name = "Bridge binary".to_string();
+    match bridge {
+        Ok(path) => {
+            if is_executable(std::path::Path::new(&path)) {
+                EnvironmentCheck {
+                    name,
+                    passed: true,
+                    detail: path,
+                }
+            } else {
+                EnvironmentCheck {
+                    name,
+                    passed: false,
+                    detail: format!("{} is not executable", path),
+                }
+            }
+        }
+        Err(e) => EnvironmentCheck {
+            name,
+            passed: false,
+            detail: e,
+        },
+    }
+}
+
+/// Check that Claude Desktop's config at `config_path` (or the error from
+/// locating it) can be written or created
+fn check_claude_config_writable(config_path: Result<std::path::PathBuf, String>) -> EnvironmentCheck {
+    let name = "Claude Desktop config".to_string();
+    let config_path = match config_path {
+        Ok(p) => p,
+        Err(e) => {
+            return EnvironmentCheck {
+                name,
+                passed: false,
+                detail: e,
+            }
+        }
+    };
+
+    let result = if config_path.exists() {
+        let read_only = std::fs::metadata(&config_path)
+            .map(|meta| meta.permissions().readonly())
+            .unwrap_or(false);
+        if read_only {
+            Err(format!("{} is not writable: file is read-only", config_path.display()))
+        } else {
+            std::fs::OpenOptions::new()
+                .append(true)
+                .open(&config_path)
+                .map(|_| format!("{} is writable", config_path.display()))
+                .map_err(|e| format!("{} is not writable: {}", config_path.display(), e))
+        }
+    } else {
+        // The file is created on demand; its nearest existing ancestor must be writable
+        let ancestor = config_path.ancestors().skip(1).find(|p| p.exists());
+        match ancestor.map(std::fs::metadata) {
+            Some(Ok(meta)) if !meta.permissions().readonly() => Ok(format!(
+                "{} does not exist yet and can be created",
+                config_path.display()
+            )),
+            Some(Ok(_)) => Err(format!(
+                "{} cannot be created: directory is read-only",
+                config_path.display()
+            )),
+            Some(Err(e)) => Err(format!("{} cannot be created: {}", config_path.display(), e)),
+            None => Err(format!("{} has no existing parent directory", config_path.display())),
+        }
+    };
+
+    match result {
+        Ok(detail) => EnvironmentCheck {
+            name,
+            passed: true,
+            detail,
+        },
+        Err(detail) => EnvironmentCheck {
+            name,
+            passed: false,
+            detail,
+        },
+    }
+}
+
+fn check_executable(executable: &str) -> EnvironmentCheck {
+    let name = format!("Executable '{}'", executable);
+    match resolve_executable(executable) {
+        Some(path) => EnvironmentCheck {
+            name,
+            passed: true,
+            detail: path.to_string_lossy().to_string(),
+        },
+        None => EnvironmentCheck {
+            name,
+            passed: false,
+            detail: format!("'{}' was not found on PATH", executable),
+        },
+    }
+}
+
+/// Resolve an executable the way a shell would: paths are checked directly,
+/// bare names are searched on PATH
+fn resolve_executable(executable: &str) -> Option<std::path::PathBuf> {
+    let candidate = std::path::Path::new(executable);
+    if candidate.components().count() > 1 {
+        return is_executable(candidate).then(|| candidate.to_path_buf());
+    }
+
+    let path_var = std::env::var_os("PATH")?;
+    std::env::split_paths(&path_var)
+        .map(|dir| dir.join(executable))
+        .find(|p| is_executable(p))
+}
+
+fn is_executable(path: &std::path::Path) -> bool {
+    let Ok(meta) = std::fs::metadata(path) else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        meta.is_file() && meta.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        meta.is_file()
+    }
+}
+
+/// Entry name, proxy port and proxy auth token for a Claude Desktop entry
+async fn get_bridge_target(
     mcp_id: &str,
     state: &State<'_, AppState>,
-) -> Result<(String, u16), String> {
+) -> Result<(String, u16, Option<String>), String> {
     let mgr = state.manager.lock().await;
     let config = mgr.get_config();
     let mcp = config
@@ -310,7 +1502,90 @@ async fn get_mcp_name_and_port(
         .iter()
         .find(|m| m.id == mcp_id)
         .ok_or("MCP not found")?;
-    Ok((mcp.name.clone(), config.proxy_port))
+    let name = claude_entry_name(config.claude_name_prefix.as_deref(), &mcp.name);
+    Ok((name, config.proxy_port, config.proxy_auth_token.clone()))
+}
+
+/// Key of an MCP's entry in Claude Desktop's `mcpServers`
+fn claude_entry_name(prefix: Option<&str>, mcp_name: &str) -> String {
+    format!("{}{}", prefix.unwrap_or_default(), mcp_name)
+}
+
+/// Whether a Claude Desktop entry launches our bridge (as opposed to a
+/// server the user added by hand)
+fn is_bridge_entry(entry: &serde_json::Value) -> bool {
+    let args: Vec<&str> = entry
+        .get("args")
+        .and_then(|a| a.as_array())
+        .map(|a| a.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default();
+    bridge_arg(&args, "--mcp-id").is_some()
+}
+
+/// Write our entry `name` into `mcpServers`.  An existing entry is only
+/// replaced when `overwrite` is set, and never if the user added it.
+fn set_claude_entry(
+    config: &mut serde_json::Value,
+    name: &str,
+    entry: serde_json::Value,
+    overwrite: bool,
+) -> Result<(), String> {
+    if config.get("mcpServers").is_none() {
+        config["mcpServers"] = serde_json::json!({});
+    }
+    if let Some(existing) = config["mcpServers"].get(name) {
+        if !is_bridge_entry(existing) {
+            return Err(format!(
+                "Claude Desktop already has an entry named '{}' not managed by this app",
+                name
+            ));
+        }
+        if !overwrite {
+            return Err("Already added to Claude Desktop".to_string());
+        }
+    }
+    config["mcpServers"][name] = entry;
+    Ok(())
+}
+
+/// Remove our entry `name` from `mcpServers`, refusing to touch an entry
+/// the user added
+fn remove_claude_entry(config: &mut serde_json::Value, name: &str) -> Result<(), String> {
+    let not_found = || "MCP not found in Claude Desktop config".to_string();
+    let servers = config
+        .get_mut("mcpServers")
+        .and_then(|s| s.as_object_mut())
+        .ok_or_else(not_found)?;
+    match servers.get(name) {
+        None => return Err(not_found()),
+        Some(existing) if !is_bridge_entry(existing) => {
+            return Err(format!(
+                "Claude Desktop entry '{}' is not managed by this app",
+                name
+            ));
+        }
+        Some(_) => {}
+    }
+    servers.remove(name);
+    Ok(())
+}
+
+/// Claude Desktop `mcpServers` entry launching the bridge; the proxy token
+/// is passed through the environment so it doesn't show up in `ps`
+fn bridge_entry(
+    bridge_path: &str,
+    mcp_id: &str,
+    port: u16,
+    token: Option<&str>,
+) -> serde_json::Value {
+    let mut entry = serde_json::json!({
+        "command": bridge_path,
+        "args": ["--mcp-id", mcp_id, "--port", port.to_string()]
+    });
+    if let Some(token) = token {
+        entry["env"] = serde_json::json!({ (BRIDGE_TOKEN_ENV): token });
+    }
+    entry
 }
 
 fn read_claude_desktop_config(
@@ -332,7 +1607,7 @@ fn write_claude_desktop_config(
         std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
     }
     let content = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
-    std::fs::write(config_path, content).map_err(|e| e.to_string())?;
+    crate::config::write_atomic(config_path, content.as_bytes()).map_err(|e| e.to_string())?;
     Ok(())
 }
 
@@ -357,3 +1632,661 @@ fn find_bridge_binary() -> Result<String, String> {
 
     Err("local-mcp-proxy-bridge binary not found next to the running executable".to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{
+        http_mcp_config, mcp_config, spawn_proxy, unreachable_url, FakeUpstream, ScratchDir,
+    };
+
+    fn log_entry(timestamp: &str, level: &str, message: &str) -> LogEntry {
+        LogEntry {
+            timestamp: timestamp.to_string(),
+            level: level.to_string(),
+            target: "local_mcp_proxy".to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    fn sample_logs() -> Vec<LogEntry> {
+        vec![
+            log_entry("2026-01-01T10:00:00Z", "DEBUG", "probing"),
+            log_entry("2026-01-01T10:05:00Z", "INFO", "connected"),
+            log_entry("2026-01-01T10:10:00Z", "WARN", "slow ping"),
+            log_entry("2026-01-01T10:15:00Z", "ERROR", "lost"),
+        ]
+    }
+
+    #[test]
+    fn ndjson_has_one_valid_entry_per_line() {
+        let logs = sample_logs();
+        let ndjson = logs_to_ndjson(&logs).unwrap();
+
+        let lines: Vec<&str> = ndjson.lines().collect();
+        assert_eq!(lines.len(), logs.len());
+        for (line, entry) in lines.iter().zip(&logs) {
+            let parsed: LogEntry = serde_json::from_str(line).unwrap();
+            assert_eq!(parsed.message, entry.message);
+        }
+        assert!(ndjson.ends_with('\n'));
+    }
+
+    #[test]
+    fn log_filter_applies_minimum_level_and_time_range() {
+        let messages = |entries: Vec<LogEntry>| -> Vec<String> {
+            entries.into_iter().map(|e| e.message).collect()
+        };
+
+        let warn_and_up = filter_logs(sample_logs(), Some("warn"), None, None).unwrap();
+        assert_eq!(messages(warn_and_up), ["slow ping", "lost"]);
+
+        let window = filter_logs(
+            sample_logs(),
+            None,
+            Some("2026-01-01T10:05:00Z"),
+            Some("2026-01-01T10:10:00Z"),
+        )
+        .unwrap();
+        assert_eq!(messages(window), ["connected", "slow ping"]);
+
+        assert_eq!(
+            filter_logs(sample_logs(), None, None, None).unwrap().len(),
+            4
+        );
+    }
+
+    #[test]
+    fn log_filter_rejects_bad_level_or_time() {
+        assert!(filter_logs(sample_logs(), Some("loud"), None, None).is_err());
+        assert!(filter_logs(sample_logs(), None, Some("yesterday"), None).is_err());
+    }
+
+    #[tokio::test]
+    async fn proxy_port_check_passes_for_a_free_port() {
+        let port = {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let check = check_proxy_port(port).await;
+        assert!(check.passed, "{}", check.detail);
+    }
+
+    #[tokio::test]
+    async fn proxy_port_check_fails_when_another_process_holds_it() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // Accept and hang up, like a server that isn't ours
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                drop(socket);
+            }
+        });
+        let check = check_proxy_port(port).await;
+        assert!(!check.passed);
+        assert!(check.detail.contains("not bindable"), "{}", check.detail);
+    }
+
+    #[tokio::test]
+    async fn proxy_port_check_recognizes_our_own_proxy() {
+        let manager = Arc::new(Mutex::new(McpManager::new(AppConfig::default())));
+        let url = spawn_proxy(manager).await;
+        let port = url.rsplit(':').next().unwrap().parse().unwrap();
+        let check = check_proxy_port(port).await;
+        assert!(check.passed);
+        assert_eq!(check.detail, "Port is bound by Local MCP Proxy");
+    }
+
+    #[tokio::test]
+    async fn port_is_free_only_while_nothing_listens() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(!port_is_free(port).await);
+
+        drop(listener);
+        assert!(port_is_free(port).await);
+    }
+
+    #[tokio::test]
+    async fn the_port_the_proxy_serves_on_counts_as_available() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let other = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let taken = other.local_addr().unwrap().port();
+
+        assert!(port_available(port, port).await);
+        assert!(!port_available(taken, port).await);
+        drop(other);
+        assert!(port_available(taken, port).await);
+    }
+
+    #[test]
+    fn executables_resolve_on_path_or_by_path() {
+        assert!(resolve_executable("sh").is_some());
+        assert!(resolve_executable("/bin/sh").is_some());
+        assert!(resolve_executable("no-such-executable-for-tests").is_none());
+
+        let check = check_executable("no-such-executable-for-tests");
+        assert!(!check.passed);
+        assert!(check.detail.contains("not found on PATH"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_executable_files_are_not_executables() {
+        let path = std::env::temp_dir().join(format!("not-executable-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "#!/bin/sh\n").unwrap();
+        let resolved = resolve_executable(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert!(resolved.is_none());
+    }
+
+    #[cfg(unix)]
+    fn file_with_mode(dir: &ScratchDir, name: &str, mode: u32) -> std::path::PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.path().join(name);
+        std::fs::write(&path, "{}").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+        path
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn bridge_binary_check_passes_only_for_an_executable() {
+        let dir = ScratchDir::new();
+        let bridge = file_with_mode(&dir, "local-mcp-bridge", 0o755);
+        let check = check_bridge_binary(Ok(bridge.to_string_lossy().to_string()));
+        assert!(check.passed, "{}", check.detail);
+
+        let plain = file_with_mode(&dir, "plain", 0o644);
+        let check = check_bridge_binary(Ok(plain.to_string_lossy().to_string()));
+        assert!(!check.passed);
+        assert!(check.detail.ends_with("is not executable"));
+
+        let missing = dir.path().join("missing").to_string_lossy().to_string();
+        assert!(!check_bridge_binary(Ok(missing)).passed);
+        let check = check_bridge_binary(Err("bridge binary not found".to_string()));
+        assert!(!check.passed);
+        assert_eq!(check.detail, "bridge binary not found");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn claude_config_check_passes_only_when_writable() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = ScratchDir::new();
+        let writable = file_with_mode(&dir, "writable.json", 0o644);
+        let check = check_claude_config_writable(Ok(writable));
+        assert!(check.passed, "{}", check.detail);
+        assert!(check_claude_config_writable(Ok(dir.path().join("new.json"))).passed);
+
+        let read_only = file_with_mode(&dir, "read-only.json", 0o444);
+        let check = check_claude_config_writable(Ok(read_only));
+        assert!(!check.passed);
+        assert!(check.detail.contains("is not writable"));
+
+        let locked = dir.path().join("locked");
+        std::fs::create_dir(&locked).unwrap();
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o555)).unwrap();
+        let check = check_claude_config_writable(Ok(locked.join("config.json")));
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(!check.passed);
+        assert!(check.detail.contains("directory is read-only"));
+    }
+
+    #[tokio::test]
+    async fn reconnecting_a_group_reconnects_only_its_enabled_members() {
+        let mut mgr = McpManager::new(AppConfig::default());
+        let mut upstreams = Vec::new();
+        for id in ["api", "search", "notes", "off"] {
+            let upstream = FakeUpstream::spawn(id, &[]).await;
+            let mut config = http_mcp_config(id, &upstream.url);
+            if id != "notes" {
+                config.reconnect_group = Some("db".to_string());
+            }
+            config.enabled = id != "off";
+            mgr.add_mcp(config).await.unwrap();
+            upstreams.push(upstream);
+        }
+
+        reconnect_members(&mgr.group_members("db")).await.unwrap();
+
+        let initializes: Vec<usize> = upstreams
+            .iter()
+            .map(|u| u.methods().iter().filter(|m| *m == "initialize").count())
+            .collect();
+        assert_eq!(initializes, [2, 2, 1, 0]);
+        for id in ["api", "search", "notes"] {
+            let conn = mgr.get_connection(id).unwrap();
+            assert_eq!(conn.get_state().await, ConnectionState::Connected);
+        }
+        let off = mgr.get_connection("off").unwrap();
+        assert_eq!(off.get_state().await, ConnectionState::Disconnected);
+    }
+
+    #[tokio::test]
+    async fn reconnecting_a_group_resets_the_budget_of_its_members_only() {
+        let url = unreachable_url().await;
+        let mut mgr = McpManager::new(AppConfig::default());
+        for id in ["api", "notes"] {
+            let mut config = http_mcp_config(id, &url);
+            if id == "api" {
+                config.reconnect_group = Some("db".to_string());
+            }
+            mgr.add_mcp(config).await.unwrap();
+            let conn = mgr.get_connection(id).unwrap();
+            conn.restore_reconnect_attempts(5).await;
+        }
+
+        let error = reconnect_members(&mgr.group_members("db")).await.unwrap_err();
+
+        assert!(error.contains("api"), "{}", error);
+        let api = mgr.get_connection("api").unwrap();
+        assert_eq!(api.get_reconnect_attempts().await, 0);
+        let notes = mgr.get_connection("notes").unwrap();
+        assert_eq!(notes.get_reconnect_attempts().await, 5);
+    }
+
+    #[tokio::test]
+    async fn tool_benchmark_reports_stats_for_every_request() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let mut config = http_mcp_config("fs", &upstream.url);
+        config.max_inflight_requests = Some(3);
+        let mut mgr = McpManager::new(AppConfig::default());
+        mgr.add_mcp(config).await.unwrap();
+        let conn = mgr.get_connection("fs").unwrap();
+
+        let stats = run_tool_benchmark(&conn, "echo", serde_json::json!({}), 8, 40)
+            .await
+            .unwrap();
+
+        assert_eq!(stats.total_requests, 40);
+        assert_eq!(stats.concurrency, 3);
+        assert_eq!((stats.errors, stats.error_rate), (0, 0.0));
+        assert_eq!(upstream.methods().iter().filter(|m| *m == "tools/call").count(), 40);
+        let (p50, p95, p99) = (stats.p50_ms.unwrap(), stats.p95_ms.unwrap(), stats.p99_ms.unwrap());
+        assert!(p50 > 0.0 && p50 <= p95 && p95 <= p99, "{:?}", stats);
+        assert!(stats.requests_per_sec > 0.0);
+    }
+
+    #[tokio::test]
+    async fn claude_integration_check_flags_dangling_entries() {
+        let upstream = FakeUpstream::spawn("up", &[]).await;
+        let mut mgr = McpManager::new(AppConfig::default());
+        mgr.add_mcp(http_mcp_config("fs", &upstream.url))
+            .await
+            .unwrap();
+        let base = spawn_proxy(Arc::new(Mutex::new(mgr))).await;
+        let port: u16 = base.rsplit(':').next().unwrap().parse().unwrap();
+        let bridge = |id: &str| {
+            serde_json::json!({
+                "command": "/bin/sh",
+                "args": ["--port", port.to_string(), "--mcp-id", id]
+            })
+        };
+        let claude_config = serde_json::json!({
+            "mcpServers": {
+                "fs": bridge("fs"),
+                "removed": bridge("removed"),
+                "other": {"command": "npx", "args": ["some-server"]}
+            }
+        });
+        let known_ids = std::collections::HashSet::from(["fs".to_string()]);
+
+        let mut checks = check_claude_entries(&claude_config, &known_ids, port, None)
+            .await
+            .unwrap();
+        checks.sort_by(|a, b| a.name.cmp(&b.name));
+
+        assert_eq!(checks.len(), 2);
+        assert_eq!(checks[0].name, "fs");
+        assert!(checks[0].mcp_exists && checks[0].bridge_exists && checks[0].proxy_reachable);
+        assert_eq!(checks[0].detail, "OK");
+        assert_eq!(checks[1].name, "removed");
+        assert!(!checks[1].mcp_exists);
+        assert_eq!(checks[1].detail, "MCP no longer exists in the proxy config");
+    }
+
+    #[tokio::test]
+    async fn latency_is_measured_through_the_proxy_endpoint() {
+        let upstream = FakeUpstream::spawn("up", &[]).await;
+        let mut mgr = McpManager::new(AppConfig::default());
+        mgr.add_mcp(http_mcp_config("fs", &upstream.url))
+            .await
+            .unwrap();
+        let base = spawn_proxy(Arc::new(Mutex::new(mgr))).await;
+
+        let latency = measure_proxy_latency(&format!("{}/mcp/fs", base), None)
+            .await
+            .unwrap();
+
+        assert!(latency.total_ms > 0.0 && latency.total_ms < 5000.0, "{:?}", latency);
+        let upstream_ms = latency.upstream_ms.expect("Server-Timing header");
+        assert!(upstream_ms > 0.0 && upstream_ms <= latency.total_ms, "{:?}", latency);
+        assert!(latency.proxy_overhead_ms.is_some());
+        assert_eq!(upstream.requests_for("ping").len(), 1);
+    }
+
+    #[test]
+    fn prefixed_claude_entries_leave_the_users_own_entries_alone() {
+        let name = claude_entry_name(Some("mcphub:"), "fs");
+        assert_eq!(name, "mcphub:fs");
+        assert_eq!(claude_entry_name(None, "fs"), "fs");
+        let user_entry = serde_json::json!({"command": "npx", "args": ["fs-server"]});
+        let mut config = serde_json::json!({
+            "mcpServers": { "fs": user_entry.clone() }
+        });
+
+        let entry = |port| bridge_entry("/bridge", "fs", port, None);
+
+        set_claude_entry(&mut config, &name, entry(3001), false).unwrap();
+        assert_eq!(config["mcpServers"][&name]["args"][1], "fs");
+        assert!(set_claude_entry(&mut config, &name, entry(3001), false).is_err());
+        set_claude_entry(&mut config, &name, entry(4002), true).unwrap();
+        assert_eq!(config["mcpServers"][&name]["args"][3], "4002");
+        assert_eq!(config["mcpServers"]["fs"], user_entry);
+
+        remove_claude_entry(&mut config, &name).unwrap();
+        assert!(config["mcpServers"].get(&name).is_none());
+        assert_eq!(config["mcpServers"]["fs"], user_entry);
+    }
+
+    #[test]
+    fn hand_added_claude_entries_are_never_overwritten_or_removed() {
+        let user_entry = serde_json::json!({"command": "npx", "args": ["fs-server"]});
+        let mut config = serde_json::json!({
+            "mcpServers": { "fs": user_entry.clone() }
+        });
+
+        let entry = bridge_entry("/bridge", "fs", 3001, None);
+        assert!(set_claude_entry(&mut config, "fs", entry, true).is_err());
+        assert!(remove_claude_entry(&mut config, "fs").is_err());
+        assert!(remove_claude_entry(&mut config, "missing").is_err());
+        assert_eq!(config["mcpServers"]["fs"], user_entry);
+    }
+
+    #[test]
+    fn claude_config_validity_lists_servers_of_a_valid_config() {
+        let dir = ScratchDir::new();
+        let path = dir.path().join("claude_desktop_config.json");
+        std::fs::write(
+            &path,
+            r#"{"mcpServers": {"fs": {"command": "npx"}, "db": {"command": "uvx"}}}"#,
+        )
+        .unwrap();
+
+        let validity = claude_config_validity(&path);
+        assert!(validity.exists && validity.valid && validity.has_mcp_servers);
+        assert_eq!(validity.server_names, ["db", "fs"]);
+        assert_eq!(validity.error, None);
+
+        // A file that does not exist yet is created on the first add
+        let missing = claude_config_validity(&dir.path().join("missing.json"));
+        assert!(!missing.exists && missing.valid);
+    }
+
+    #[test]
+    fn claude_config_validity_flags_invalid_json() {
+        let dir = ScratchDir::new();
+        let path = dir.path().join("claude_desktop_config.json");
+        std::fs::write(&path, r#"{"mcpServers": {"fs": "#).unwrap();
+
+        let validity = claude_config_validity(&path);
+        assert!(validity.exists && !validity.valid);
+        assert!(validity.server_names.is_empty());
+        assert!(validity.error.unwrap().starts_with("Not valid JSON"));
+    }
+
+    #[test]
+    fn claude_config_validity_reports_missing_or_malformed_mcp_servers() {
+        let dir = ScratchDir::new();
+        let path = dir.path().join("claude_desktop_config.json");
+
+        std::fs::write(&path, r#"{"globalShortcut": ""}"#).unwrap();
+        let validity = claude_config_validity(&path);
+        assert!(validity.valid && !validity.has_mcp_servers);
+        assert!(validity.server_names.is_empty());
+
+        std::fs::write(&path, r#"{"mcpServers": []}"#).unwrap();
+        let validity = claude_config_validity(&path);
+        assert!(!validity.valid && !validity.has_mcp_servers);
+        assert_eq!(validity.error.as_deref(), Some("mcpServers is not a JSON object"));
+    }
+
+    #[test]
+    fn scrub_secrets_masks_every_occurrence() {
+        let secrets = ["ghp_secret".to_string(), "hunter2".to_string(), "abc".to_string()];
+        let mut value = serde_json::json!({
+            "message": "auth failed with hunter2 (retrying hunter2), token ghp_secret",
+            "nested": [{"note": "abc is short but scrubbed too"}],
+        });
+
+        scrub_secrets(&mut value, &secrets);
+
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "message": "auth failed with ******** (retrying ********), token ********",
+                "nested": [{"note": "******** is short but scrubbed too"}],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn diagnostics_bundle_holds_every_entry_without_raw_secrets() {
+        use std::io::Read;
+
+        let dir = ScratchDir::new();
+        let secret = r#"s3cr"et\value"#;
+        let mut mcp = http_mcp_config("api", &unreachable_url().await);
+        mcp.headers = Some(std::collections::HashMap::from([(
+            "Authorization".to_string(),
+            format!("Bearer {}", secret),
+        )]));
+        let mut mgr = McpManager::new(AppConfig::default());
+        mgr.add_mcp(mcp).await.unwrap();
+        let state = AppState {
+            manager: Arc::new(Mutex::new(mgr)),
+            config_manager: Arc::new(Mutex::new(ConfigManager::new(dir.path().join("config.json")))),
+            log_store: Default::default(),
+            proxy_port: 0,
+            config_warning: None,
+        };
+        state.log_store.lock().unwrap().push_back(LogEntry {
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+            level: "WARN".to_string(),
+            target: "test".to_string(),
+            message: format!("upstream rejected Bearer {}", secret),
+        });
+        let path = dir.path().join("diagnostics.zip");
+
+        write_diagnostics(&state, path.to_str().unwrap()).await.unwrap();
+
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let mut names: Vec<_> = zip.file_names().map(|n| n.to_string()).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "build_info.json",
+                "config.json",
+                "connection_events.json",
+                "environment.json",
+                "logs.ndjson",
+                "statuses.json",
+            ]
+        );
+        let escaped = serde_json::to_string(secret).unwrap();
+        let escaped = escaped.trim_matches('"');
+        for i in 0..zip.len() {
+            let mut entry = zip.by_index(i).unwrap();
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents).unwrap();
+            assert!(!contents.contains(secret), "{}", entry.name());
+            assert!(!contents.contains(escaped), "{}", entry.name());
+        }
+    }
+
+    #[tokio::test]
+    async fn persist_config_writes_the_in_memory_config_to_disk() {
+        let dir = ScratchDir::new();
+        let config_manager = ConfigManager::new(dir.path().join("config.json"));
+        let mut mgr = McpManager::new(AppConfig::default());
+        mgr.add_mcp(mcp_config("fs")).await.unwrap();
+        let state = AppState {
+            manager: Arc::new(Mutex::new(mgr)),
+            config_manager: Arc::new(Mutex::new(config_manager)),
+            log_store: Default::default(),
+            proxy_port: 0,
+            config_warning: None,
+        };
+
+        persist_config(&state).await.unwrap();
+
+        let saved = state.config_manager.lock().await.load().unwrap();
+        let ids: Vec<_> = saved.mcps.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["fs"]);
+    }
+
+    #[tokio::test]
+    async fn switching_profiles_replaces_the_connection_set() {
+        let dir = ScratchDir::new();
+        let config_manager = ConfigManager::new(dir.path().join("config.json"));
+        let default_port = AppConfig::default().proxy_port;
+        let work = AppConfig {
+            mcps: vec![mcp_config("fs")],
+            shutdown_timeout_secs: 3,
+            ..AppConfig::default()
+        };
+        let home = AppConfig {
+            proxy_port: 4000,
+            mcps: vec![mcp_config("music"), mcp_config("notes")],
+            ..AppConfig::default()
+        };
+        config_manager.save_profile("work", &work).unwrap();
+        config_manager.save_profile("home", &home).unwrap();
+        let state = AppState {
+            manager: Arc::new(Mutex::new(McpManager::new(AppConfig {
+                active_profile: Some("work".to_string()),
+                ..work.clone()
+            }))),
+            config_manager: Arc::new(Mutex::new(config_manager)),
+            log_store: Default::default(),
+            proxy_port: default_port,
+            config_warning: None,
+        };
+        let ids = |statuses: &[McpStatus]| -> Vec<String> {
+            statuses.iter().map(|s| s.id.clone()).collect()
+        };
+
+        let (switch, statuses) = switch_to_profile(&state, "home").await.unwrap();
+        assert_eq!(switch.saved_as, None);
+        assert!(switch.restart_required);
+        assert_eq!(ids(&statuses), ["music", "notes"]);
+        {
+            let mgr = state.manager.lock().await;
+            let config = mgr.get_config();
+            assert_eq!(config.proxy_port, 4000);
+            assert_eq!(config.shutdown_timeout_secs, home.shutdown_timeout_secs);
+            assert_eq!(config.active_profile.as_deref(), Some("home"));
+        }
+        let saved = state.config_manager.lock().await.load().unwrap();
+        assert_eq!(saved.active_profile.as_deref(), Some("home"));
+        assert_eq!(saved.proxy_port, 4000);
+
+        let (switch, statuses) = switch_to_profile(&state, "work").await.unwrap();
+        assert_eq!(switch.saved_as, None);
+        assert!(!switch.restart_required);
+        assert_eq!(ids(&statuses), ["fs"]);
+        {
+            let mgr = state.manager.lock().await;
+            let config = mgr.get_config();
+            assert_eq!(config.proxy_port, default_port);
+            assert_eq!(config.shutdown_timeout_secs, 3);
+            assert_eq!(config.active_profile.as_deref(), Some("work"));
+        }
+
+        assert!(switch_to_profile(&state, "missing").await.is_err());
+        let statuses = state.manager.lock().await.list_statuses().await;
+        assert_eq!(ids(&statuses), ["fs"]);
+        let saved = state.config_manager.lock().await.load().unwrap();
+        assert_eq!(saved.active_profile.as_deref(), Some("work"));
+    }
+
+    #[tokio::test]
+    async fn switching_away_from_an_unsaved_config_keeps_it_as_a_profile() {
+        let dir = ScratchDir::new();
+        let config_manager = ConfigManager::new(dir.path().join("config.json"));
+        let other = AppConfig {
+            proxy_port: 4000,
+            mcps: vec![mcp_config("music")],
+            ..AppConfig::default()
+        };
+        config_manager.save_profile("home", &other).unwrap();
+        let mut mgr = McpManager::new(AppConfig::default());
+        mgr.add_mcp(mcp_config("fs")).await.unwrap();
+        let state = AppState {
+            manager: Arc::new(Mutex::new(mgr)),
+            config_manager: Arc::new(Mutex::new(config_manager)),
+            log_store: Default::default(),
+            proxy_port: AppConfig::default().proxy_port,
+            config_warning: None,
+        };
+
+        let (switch, statuses) = switch_to_profile(&state, "home").await.unwrap();
+        assert!(switch.restart_required);
+        assert_eq!(statuses[0].id, "music");
+
+        let saved_as = switch.saved_as.expect("unsaved config was not kept");
+        assert!(saved_as.starts_with("unsaved-"));
+        let config_mgr = state.config_manager.lock().await;
+        let kept = config_mgr.load_profile(&saved_as).unwrap();
+        assert_eq!(kept.mcps[0].id, "fs");
+    }
+
+    #[tokio::test]
+    async fn build_info_serializes_with_versions_filled_in() {
+        let info = serde_json::to_value(get_build_info().await.unwrap()).unwrap();
+
+        for field in [
+            "app_version",
+            "bridge_version",
+            "target_triple",
+            "rmcp_version",
+        ] {
+            assert!(!info[field].as_str().unwrap().is_empty(), "{}", field);
+        }
+        assert_eq!(info["app_version"], env!("CARGO_PKG_VERSION"));
+        let built_at = info["build_timestamp"].as_str().unwrap();
+        assert!(chrono::DateTime::parse_from_rfc3339(built_at).is_ok());
+    }
+
+    #[tokio::test]
+    async fn tool_schema_reports_enabled_disabled_and_unknown_tools() {
+        let upstream = FakeUpstream::spawn("up", &["read_file", "drop_table"]).await;
+        let mut config = http_mcp_config("fs", &upstream.url);
+        config.disabled_tools = vec!["drop_table".to_string()];
+        let mut mgr = McpManager::new(AppConfig::default());
+        mgr.add_mcp(config).await.unwrap();
+        let manager = Mutex::new(mgr);
+
+        let read = tool_schema(&manager, "fs", "read_file").await.unwrap();
+        assert_eq!(read.name, "read_file");
+        assert!(read.enabled);
+        assert_eq!(*read.input_schema, serde_json::json!({"type": "object"}));
+
+        let drop = tool_schema(&manager, "fs", "drop_table").await.unwrap();
+        assert!(!drop.enabled);
+
+        assert_eq!(
+            tool_schema(&manager, "fs", "nope").await.unwrap_err(),
+            "Tool 'nope' not found"
+        );
+        assert_eq!(
+            tool_schema(&manager, "other", "read_file")
+                .await
+                .unwrap_err(),
+            "MCP not found"
+        );
+    }
+}
@@ -1,7 +1,29 @@
-use crate::types::{AppConfig, TransportType};
+use crate::types::{AppConfig, ConfigStats, McpServerConfig, TransportType};
 use anyhow::{Context, Result};
 use std::path::PathBuf;
 
+/// Env var overriding the config file location
+const CONFIG_PATH_ENV: &str = "LOCAL_MCP_CONFIG";
+
+/// Env var that starts the app in safe mode regardless of the config
+const SAFE_MODE_ENV: &str = "LOCAL_MCP_SAFE_MODE";
+
+/// CLI flag overriding the config file location (takes precedence over the env var)
+const CONFIG_PATH_ARG: &str = "--config";
+
+/// Beyond this many MCPs startup and config saves get noticeably slow
+pub const RECOMMENDED_MAX_MCPS: usize = 100;
+
+/// Config file size past which saves and reloads get noticeably slow
+pub const RECOMMENDED_MAX_CONFIG_BYTES: u64 = 1024 * 1024;
+
+/// MCP protocol versions an upstream can be pinned to
+pub const KNOWN_PROTOCOL_VERSIONS: &[&str] = &["2024-11-05", "2025-03-26", "2025-06-18"];
+
+/// Client headers carrying the client's own credentials, which are never
+/// forwarded upstream (lowercase)
+const UNFORWARDABLE_HEADERS: &[&str] = &["authorization", "cookie"];
+
 /// Manages loading and saving the JSON config file
 pub struct ConfigManager {
     config_path: PathBuf,
@@ -13,9 +35,15 @@ impl ConfigManager {
         Self { config_path }
     }
 
-    /// Initialize ConfigManager using the Tauri app data directory
+    /// Initialize ConfigManager using `--config <path>` or `LOCAL_MCP_CONFIG`
+    /// if given, otherwise the Tauri app data directory
     pub fn from_app_handle(app_handle: &tauri::AppHandle) -> Result<Self> {
         use tauri::Manager;
+        if let Some(config_path) = config_path_override() {
+            tracing::info!("Using config path override {:?}", config_path);
+            return Ok(Self::new(config_path));
+        }
+
         let app_dir = app_handle
             .path()
             .app_data_dir()
@@ -47,7 +75,52 @@ impl ConfigManager {
         Ok(config)
     }
 
-    /// Save config to disk with atomic write
+    /// Like `load`, but a config file that fails to parse is moved aside to
+    /// `config.json.corrupt-<timestamp>` and defaults are used instead of
+    /// failing startup.  The second value is a warning for the user when
+    /// that happened.
+    pub fn load_or_recover(&self) -> Result<(AppConfig, Option<String>)> {
+        if !self.config_path.exists() {
+            return Ok((self.load()?, None));
+        }
+
+        let data = std::fs::read_to_string(&self.config_path)
+            .context("Failed to read config file")?;
+        let e = match serde_json::from_str::<AppConfig>(&data) {
+            Ok(config) => {
+                tracing::info!(
+                    "Loaded config with {} MCPs from {:?}",
+                    config.mcps.len(),
+                    self.config_path
+                );
+                return Ok((config, None));
+            }
+            Err(e) => e,
+        };
+        let mut corrupt_name = self.config_path.file_name().unwrap_or_default().to_os_string();
+        corrupt_name.push(format!(
+            ".corrupt-{}",
+            chrono::Utc::now().format("%Y%m%dT%H%M%SZ")
+        ));
+        let corrupt_path = self.config_path.with_file_name(corrupt_name);
+        std::fs::rename(&self.config_path, &corrupt_path)
+            .context("Failed to move corrupt config file aside")?;
+        tracing::error!(
+            "Config file {:?} is not valid ({}); moved it to {:?} and started with defaults",
+            self.config_path,
+            e,
+            corrupt_path
+        );
+        let warning = format!(
+            "The config file could not be parsed ({}). It was moved to {} and the default config is in use.",
+            e,
+            corrupt_path.display()
+        );
+        Ok((AppConfig::default(), Some(warning)))
+    }
+
+    /// Save config to disk with atomic write.  The previous file is kept as
+    /// `config.json.bak`, and a failed write leaves the current file intact.
     pub fn save(&self, config: &AppConfig) -> Result<()> {
         // Ensure parent directory exists
         if let Some(parent) = self.config_path.parent() {
@@ -58,23 +131,155 @@ impl ConfigManager {
         let data = serde_json::to_string_pretty(config)
             .context("Failed to serialize config")?;
 
-        std::fs::write(&self.config_path, data)
+        if self.config_path.exists() {
+            let backup = self.config_path.with_extension("json.bak");
+            if let Err(e) = std::fs::copy(&self.config_path, &backup) {
+                tracing::warn!("Failed to back up config to {:?}: {}", backup, e);
+            }
+        }
+
+        write_atomic(&self.config_path, data.as_bytes())
             .context("Failed to write config file")?;
 
         tracing::info!("Saved config to {:?}", self.config_path);
         Ok(())
     }
 
+    /// MCP counts and file size of `config`, flagged when past the
+    /// recommended limits
+    pub fn stats(&self, config: &AppConfig) -> ConfigStats {
+        let mcp_count = config.mcps.len();
+        let config_file_bytes = std::fs::metadata(&self.config_path)
+            .map(|meta| meta.len())
+            .unwrap_or(0);
+
+        let mut problems = Vec::new();
+        if mcp_count > RECOMMENDED_MAX_MCPS {
+            problems.push(format!(
+                "{} MCPs configured (recommended at most {})",
+                mcp_count, RECOMMENDED_MAX_MCPS
+            ));
+        }
+        if config_file_bytes > RECOMMENDED_MAX_CONFIG_BYTES {
+            problems.push(format!(
+                "config file is {} KiB (recommended at most {} KiB)",
+                config_file_bytes / 1024,
+                RECOMMENDED_MAX_CONFIG_BYTES / 1024
+            ));
+        }
+
+        ConfigStats {
+            mcp_count,
+            enabled_mcp_count: config.mcps.iter().filter(|m| m.enabled).count(),
+            config_file_bytes,
+            exceeds_recommended: !problems.is_empty(),
+            warning: (!problems.is_empty()).then(|| {
+                format!("{}; expect slower startup and saves", problems.join(", "))
+            }),
+        }
+    }
+
+    /// Directory holding named profiles, next to the config file
+    fn profiles_dir(&self) -> PathBuf {
+        self.config_path
+            .parent()
+            .map(|dir| dir.join("profiles"))
+            .unwrap_or_else(|| PathBuf::from("profiles"))
+    }
+
+    fn profile_path(&self, name: &str) -> Result<PathBuf> {
+        validate_profile_name(name).map_err(anyhow::Error::msg)?;
+        Ok(self.profiles_dir().join(format!("{}.json", name)))
+    }
+
+    /// Names of the saved profiles, sorted
+    pub fn list_profiles(&self) -> Result<Vec<String>> {
+        let dir = self.profiles_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(&dir).context("Failed to read profiles directory")? {
+            let path = entry.context("Failed to read profiles directory")?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Load a saved profile, marked as the active one
+    pub fn load_profile(&self, name: &str) -> Result<AppConfig> {
+        let path = self.profile_path(name)?;
+        if !path.exists() {
+            anyhow::bail!("Profile '{}' not found", name);
+        }
+        let data = std::fs::read_to_string(&path).context("Failed to read profile")?;
+        let mut config: AppConfig =
+            serde_json::from_str(&data).context("Failed to parse profile")?;
+        config.active_profile = Some(name.to_string());
+        Ok(config)
+    }
+
+    /// Save `config` as the named profile, replacing any previous one
+    pub fn save_profile(&self, name: &str, config: &AppConfig) -> Result<()> {
+        let path = self.profile_path(name)?;
+        std::fs::create_dir_all(self.profiles_dir())
+            .context("Failed to create profiles directory")?;
+        let mut config = config.clone();
+        config.active_profile = None;
+        let data = serde_json::to_string_pretty(&config).context("Failed to serialize profile")?;
+        write_atomic(&path, data.as_bytes()).context("Failed to write profile")?;
+        tracing::info!("Saved profile '{}' to {:?}", name, path);
+        Ok(())
+    }
+
     /// Validate a config structure
     pub fn validate(config: &AppConfig) -> Result<(), String> {
         if config.proxy_port < 1024 {
             return Err("Proxy port must be >= 1024".to_string());
         }
 
+        if config.proxy_auth_token.as_deref() == Some("") {
+            return Err("Proxy auth token cannot be empty".to_string());
+        }
+
+        for addr in &config.extra_listen_addresses {
+            let Ok(parsed) = addr.parse::<std::net::SocketAddr>() else {
+                return Err(format!(
+                    "Extra listen address '{}' must be an ip:port pair",
+                    addr
+                ));
+            };
+            // Anything beyond loopback exposes every MCP to the network
+            if !parsed.ip().is_loopback() && config.proxy_token().is_none() {
+                return Err(format!(
+                    "Extra listen address '{}' is not loopback and requires a proxy auth token",
+                    addr
+                ));
+            }
+        }
+
         if config.health_check_interval_secs < 5 {
             return Err("Health check interval must be >= 5 seconds".to_string());
         }
 
+        if config.max_concurrent_connects == 0 {
+            return Err("Max concurrent connects must be >= 1".to_string());
+        }
+
+        if let Some(sampling) = &config.sampling {
+            if reqwest::Url::parse(&sampling.endpoint).is_err() {
+                return Err("Sampling endpoint must be a valid URL".to_string());
+            }
+            if sampling.model.is_empty() {
+                return Err("Sampling model cannot be empty".to_string());
+            }
+        }
+
         for mcp in &config.mcps {
             if mcp.id.is_empty() {
                 return Err("MCP ID cannot be empty".to_string());
@@ -83,6 +288,83 @@ impl ConfigManager {
                 return Err("MCP name cannot be empty".to_string());
             }
 
+            for dep in &mcp.depends_on {
+                if dep == &mcp.id {
+                    return Err(format!("MCP '{}' cannot depend on itself", mcp.name));
+                }
+                if !config.mcps.iter().any(|m| &m.id == dep) {
+                    return Err(format!(
+                        "MCP '{}' depends on unknown MCP ID '{}'",
+                        mcp.name, dep
+                    ));
+                }
+            }
+
+            if let Some(color) = &mcp.color {
+                if !is_hex_color(color) {
+                    return Err(format!(
+                        "MCP '{}': color '{}' must be a hex code like #1a2b3c",
+                        mcp.name, color
+                    ));
+                }
+            }
+
+            let mut aliases = std::collections::HashSet::new();
+            for alias in mcp.tool_aliases.values() {
+                if alias.is_empty() {
+                    return Err(format!("MCP '{}': tool aliases cannot be empty", mcp.name));
+                }
+                if !aliases.insert(alias) {
+                    return Err(format!(
+                        "MCP '{}': tool alias '{}' is used more than once",
+                        mcp.name, alias
+                    ));
+                }
+            }
+
+            if let Some(tool) = mcp.tool_defaults.iter().find(|(_, v)| !v.is_object()).map(|(k, _)| k) {
+                return Err(format!(
+                    "MCP '{}': defaults for tool '{}' must be a JSON object",
+                    mcp.name, tool
+                ));
+            }
+
+            if let Some(version) = &mcp.upstream_protocol_version {
+                if !KNOWN_PROTOCOL_VERSIONS.contains(&version.as_str()) {
+                    return Err(format!(
+                        "MCP '{}': unknown protocol version '{}' (expected one of {})",
+                        mcp.name,
+                        version,
+                        KNOWN_PROTOCOL_VERSIONS.join(", ")
+                    ));
+                }
+            }
+
+            if let Some(header) = mcp
+                .forward_headers
+                .iter()
+                .find(|h| UNFORWARDABLE_HEADERS.contains(&h.to_ascii_lowercase().as_str()))
+            {
+                return Err(format!(
+                    "MCP '{}': the client's '{}' header cannot be forwarded",
+                    mcp.name, header
+                ));
+            }
+
+            if let Some(signing) = &mcp.request_signing {
+                if signing.secret.is_empty() {
+                    return Err(format!("MCP '{}': signing secret cannot be empty", mcp.name));
+                }
+                for header in [&signing.header, &signing.timestamp_header] {
+                    if reqwest::header::HeaderName::from_bytes(header.as_bytes()).is_err() {
+                        return Err(format!(
+                            "MCP '{}': '{}' is not a valid header name",
+                            mcp.name, header
+                        ));
+                    }
+                }
+            }
+
             match mcp.transport_type {
                 TransportType::Stdio => {
                     if mcp.command.as_ref().map_or(true, |c| c.is_empty()) {
@@ -91,8 +373,19 @@ impl ConfigManager {
                             mcp.name
                         ));
                     }
+                    if let Some(raw) = &mcp.args_raw {
+                        if mcp.args.as_ref().is_some_and(|a| !a.is_empty()) {
+                            return Err(format!(
+                                "MCP '{}': set either args or args_raw, not both",
+                                mcp.name
+                            ));
+                        }
+                        if let Err(e) = split_shell_words(raw) {
+                            return Err(format!("MCP '{}': invalid args_raw: {}", mcp.name, e));
+                        }
+                    }
                 }
-                TransportType::Sse | TransportType::StreamableHttp => {
+                TransportType::Sse | TransportType::StreamableHttp | TransportType::Auto => {
                     if mcp.url.as_ref().map_or(true, |u| u.is_empty()) {
                         return Err(format!(
                             "MCP '{}': HTTP/SSE transport requires a URL",
@@ -103,6 +396,585 @@ impl ConfigManager {
             }
         }
 
+        let (_, cyclic) = dependency_waves(&config.mcps);
+        if !cyclic.is_empty() {
+            let names: Vec<&str> = config
+                .mcps
+                .iter()
+                .filter(|m| cyclic.contains(&m.id))
+                .map(|m| m.name.as_str())
+                .collect();
+            return Err(format!(
+                "MCP dependencies form a cycle (involving {})",
+                names.join(", ")
+            ));
+        }
+
         Ok(())
     }
 }
+
+/// Group MCP IDs into startup waves so every MCP comes after the ones it
+/// `depends_on` (unknown IDs are ignored).  IDs that can never be placed,
+/// because they sit on or behind a dependency cycle, are returned second.
+pub fn dependency_waves(mcps: &[McpServerConfig]) -> (Vec<Vec<String>>, Vec<String>) {
+    let mut remaining: Vec<&McpServerConfig> = mcps.iter().collect();
+    let mut waves = Vec::new();
+
+    loop {
+        let (ready, blocked): (Vec<_>, Vec<_>) = remaining.iter().copied().partition(|mcp| {
+            mcp.depends_on
+                .iter()
+                .all(|dep| !remaining.iter().any(|m| &m.id == dep))
+        });
+        if ready.is_empty() {
+            break;
+        }
+        waves.push(ready.iter().map(|mcp| mcp.id.clone()).collect());
+        remaining = blocked;
+    }
+
+    (waves, remaining.into_iter().map(|mcp| mcp.id.clone()).collect())
+}
+
+/// Write `data` to a temp file next to `path`, flush it to disk and rename
+/// it over `path`, so a full disk or crash never leaves a truncated file
+pub fn write_atomic(path: &std::path::Path, data: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let result = std::fs::File::create(&tmp_path).and_then(|mut file| {
+        file.write_all(data)?;
+        file.sync_all()
+    });
+    if let Err(e) = result.and_then(|_| std::fs::rename(&tmp_path, path)) {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Profile names become file names, so only letters, digits, `-` and `_`
+/// are allowed
+pub fn validate_profile_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Profile name cannot be empty".to_string());
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Profile name '{}' may only contain letters, digits, '-' and '_'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Split a command-line string into words the way a POSIX shell would,
+/// without expansions: whitespace separates words, single quotes are
+/// literal, double quotes allow `\"` and `\\` escapes, and a backslash
+/// outside quotes escapes the next character
+pub fn split_shell_words(input: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unterminated double quote".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                match chars.next() {
+                    Some(c) => word.push(c),
+                    None => return Err("trailing backslash".to_string()),
+                }
+            }
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// `#rrggbb` with hex digits in either case
+fn is_hex_color(color: &str) -> bool {
+    color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Whether `LOCAL_MCP_SAFE_MODE` asks for safe mode
+pub fn safe_mode_from_env() -> bool {
+    safe_mode_env_value(std::env::var(SAFE_MODE_ENV).ok().as_deref())
+}
+
+/// Any value other than empty, `0`, `false`, `no` or `off` turns safe mode on
+fn safe_mode_env_value(value: Option<&str>) -> bool {
+    match value.map(|v| v.trim().to_ascii_lowercase()) {
+        None => false,
+        Some(v) => !matches!(v.as_str(), "" | "0" | "false" | "no" | "off"),
+    }
+}
+
+/// Config path from the command line or environment, if either is set
+fn config_path_override() -> Option<PathBuf> {
+    config_path_from(std::env::args().skip(1), std::env::var_os(CONFIG_PATH_ENV))
+}
+
+/// Pick the config path from CLI args (without the program name), falling
+/// back to the env var value
+fn config_path_from(
+    mut args: impl Iterator<Item = String>,
+    env_value: Option<std::ffi::OsString>,
+) -> Option<PathBuf> {
+    while let Some(arg) = args.next() {
+        if arg == CONFIG_PATH_ARG {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(path));
+        }
+    }
+
+    env_value.filter(|path| !path.is_empty()).map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path_from(args: &[&str], env_value: Option<&str>) -> Option<PathBuf> {
+        config_path_from(
+            args.iter().map(|a| a.to_string()),
+            env_value.map(std::ffi::OsString::from),
+        )
+    }
+
+    #[test]
+    fn config_path_comes_from_the_cli_flag_in_either_form() {
+        assert_eq!(
+            path_from(&["--config", "/tmp/a.json"], None),
+            Some(PathBuf::from("/tmp/a.json"))
+        );
+        assert_eq!(
+            path_from(&["--verbose", "--config=/tmp/b.json"], None),
+            Some(PathBuf::from("/tmp/b.json"))
+        );
+        assert_eq!(path_from(&["--config"], None), None);
+    }
+
+    #[test]
+    fn config_path_cli_flag_wins_over_the_env_var() {
+        assert_eq!(
+            path_from(&["--config", "/tmp/cli.json"], Some("/tmp/env.json")),
+            Some(PathBuf::from("/tmp/cli.json"))
+        );
+        assert_eq!(
+            path_from(&[], Some("/tmp/env.json")),
+            Some(PathBuf::from("/tmp/env.json"))
+        );
+    }
+
+    #[test]
+    fn config_path_ignores_an_empty_env_var() {
+        assert_eq!(path_from(&[], Some("")), None);
+        assert_eq!(path_from(&["--other"], None), None);
+    }
+
+    #[test]
+    fn safe_mode_env_var_accepts_common_truthy_values() {
+        for on in ["1", "true", "YES", " on "] {
+            assert!(safe_mode_env_value(Some(on)), "{on}");
+        }
+        for off in ["", "0", "false", "Off", "no"] {
+            assert!(!safe_mode_env_value(Some(off)), "{off}");
+        }
+        assert!(!safe_mode_env_value(None));
+    }
+
+    #[test]
+    fn save_keeps_the_previous_file_as_a_backup() {
+        let dir = crate::test_support::ScratchDir::new();
+        let path = dir.path().join("config.json");
+        let manager = ConfigManager::new(path.clone());
+        let mut config = AppConfig::default();
+        manager.save(&config).unwrap();
+        assert!(!path.with_extension("json.bak").exists());
+
+        config.proxy_port = 4000;
+        manager.save(&config).unwrap();
+
+        assert_eq!(manager.load().unwrap().proxy_port, 4000);
+        let backup = std::fs::read_to_string(path.with_extension("json.bak")).unwrap();
+        let backup: AppConfig = serde_json::from_str(&backup).unwrap();
+        assert_eq!(backup.proxy_port, AppConfig::default().proxy_port);
+        assert!(!dir.path().join("config.json.tmp").exists());
+    }
+
+    #[test]
+    fn stats_count_mcps_and_flag_oversized_configs() {
+        let dir = crate::test_support::ScratchDir::new();
+        let manager = ConfigManager::new(dir.path().join("config.json"));
+        let mut config = AppConfig::default();
+        config.mcps = (0..3).map(|i| crate::test_support::mcp_config(&format!("m{}", i))).collect();
+        config.mcps[0].enabled = true;
+
+        let unsaved = manager.stats(&config);
+        assert_eq!((unsaved.mcp_count, unsaved.enabled_mcp_count), (3, 1));
+        assert_eq!(unsaved.config_file_bytes, 0);
+        assert!(!unsaved.exceeds_recommended && unsaved.warning.is_none());
+
+        manager.save(&config).unwrap();
+        let saved = manager.stats(&config);
+        let on_disk = std::fs::metadata(dir.path().join("config.json")).unwrap().len();
+        assert_eq!(saved.config_file_bytes, on_disk);
+        assert!(!saved.exceeds_recommended);
+
+        config.mcps = (0..=RECOMMENDED_MAX_MCPS)
+            .map(|i| crate::test_support::mcp_config(&format!("m{}", i)))
+            .collect();
+        let crowded = manager.stats(&config);
+        assert_eq!(crowded.mcp_count, RECOMMENDED_MAX_MCPS + 1);
+        assert!(crowded.exceeds_recommended);
+        assert!(crowded.warning.unwrap().contains("MCPs configured"));
+    }
+
+    #[test]
+    fn profiles_round_trip_and_reject_unsafe_names() {
+        let dir = crate::test_support::ScratchDir::new();
+        let manager = ConfigManager::new(dir.path().join("config.json"));
+        assert!(manager.list_profiles().unwrap().is_empty());
+
+        let mut config = AppConfig::default();
+        config.proxy_port = 4100;
+        config.active_profile = Some("stale".to_string());
+        manager.save_profile("work", &config).unwrap();
+        manager.save_profile("home", &AppConfig::default()).unwrap();
+
+        assert_eq!(manager.list_profiles().unwrap(), ["home", "work"]);
+        let loaded = manager.load_profile("work").unwrap();
+        assert_eq!(loaded.proxy_port, 4100);
+        assert_eq!(loaded.active_profile.as_deref(), Some("work"));
+        assert!(manager.load_profile("missing").is_err());
+
+        for name in ["", "../config", "a/b", "with space"] {
+            assert!(manager.save_profile(name, &config).is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn failed_atomic_write_leaves_no_temp_file() {
+        let dir = crate::test_support::ScratchDir::new();
+        // Renaming a file over a non-empty directory fails
+        let target = dir.path().join("config.json");
+        std::fs::create_dir(&target).unwrap();
+        std::fs::write(target.join("keep"), "x").unwrap();
+
+        assert!(write_atomic(&target, b"{}").is_err());
+
+        assert!(target.join("keep").exists());
+        assert!(!dir.path().join("config.json.tmp").exists());
+    }
+
+    #[test]
+    fn corrupt_config_is_moved_aside_and_defaults_are_used() {
+        let dir = crate::test_support::ScratchDir::new();
+        let path = dir.path().join("config.json");
+        std::fs::write(&path, "{ not json").unwrap();
+        let manager = ConfigManager::new(path.clone());
+
+        let (config, warning) = manager.load_or_recover().unwrap();
+
+        assert_eq!(config.proxy_port, AppConfig::default().proxy_port);
+        assert!(warning.unwrap().contains("could not be parsed"));
+        assert!(!path.exists());
+        let moved: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        assert_eq!(moved.len(), 1);
+        assert!(moved[0].starts_with("config.json.corrupt-"), "{:?}", moved);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join(&moved[0])).unwrap(),
+            "{ not json"
+        );
+    }
+
+    #[test]
+    fn valid_config_loads_without_a_warning() {
+        let dir = crate::test_support::ScratchDir::new();
+        let manager = ConfigManager::new(dir.path().join("config.json"));
+        let config = AppConfig {
+            proxy_port: 4000,
+            ..AppConfig::default()
+        };
+        manager.save(&config).unwrap();
+
+        let (loaded, warning) = manager.load_or_recover().unwrap();
+
+        assert_eq!(loaded.proxy_port, 4000);
+        assert_eq!(warning, None);
+    }
+
+    #[test]
+    fn validate_requires_at_least_one_concurrent_connect() {
+        let mut config = AppConfig::default();
+        assert!(ConfigManager::validate(&config).is_ok());
+
+        config.max_concurrent_connects = 0;
+        assert_eq!(
+            ConfigManager::validate(&config).unwrap_err(),
+            "Max concurrent connects must be >= 1"
+        );
+    }
+
+    #[test]
+    fn validate_requires_extra_listen_addresses_to_be_ip_port_pairs() {
+        let mut config = AppConfig {
+            extra_listen_addresses: vec!["192.168.1.10:3001".to_string()],
+            proxy_auth_token: Some("secret".to_string()),
+            ..AppConfig::default()
+        };
+        assert!(ConfigManager::validate(&config).is_ok());
+
+        config.extra_listen_addresses.push("lan-host".to_string());
+        assert_eq!(
+            ConfigManager::validate(&config).unwrap_err(),
+            "Extra listen address 'lan-host' must be an ip:port pair"
+        );
+    }
+
+    #[test]
+    fn validate_requires_a_token_for_non_loopback_listen_addresses() {
+        let mut config = AppConfig {
+            extra_listen_addresses: vec![
+                "127.0.0.2:3001".to_string(),
+                "[::1]:3001".to_string(),
+            ],
+            ..AppConfig::default()
+        };
+        assert!(ConfigManager::validate(&config).is_ok());
+
+        config.extra_listen_addresses.push("0.0.0.0:3001".to_string());
+        assert_eq!(
+            ConfigManager::validate(&config).unwrap_err(),
+            "Extra listen address '0.0.0.0:3001' is not loopback and requires a proxy auth token"
+        );
+
+        config.proxy_auth_token = Some("secret".to_string());
+        assert!(ConfigManager::validate(&config).is_ok());
+    }
+
+    #[test]
+    fn validate_accepts_only_hex_colors() {
+        let mut mcp = crate::test_support::mcp_config("fs");
+        for color in ["#1a2b3c", "#ABCDEF"] {
+            mcp.color = Some(color.to_string());
+            let config = AppConfig {
+                mcps: vec![mcp.clone()],
+                ..AppConfig::default()
+            };
+            assert!(ConfigManager::validate(&config).is_ok(), "{}", color);
+        }
+
+        for color in ["red", "#12345", "#12345g", "1a2b3c0"] {
+            mcp.color = Some(color.to_string());
+            let config = AppConfig {
+                mcps: vec![mcp.clone()],
+                ..AppConfig::default()
+            };
+            assert_eq!(
+                ConfigManager::validate(&config).unwrap_err(),
+                format!("MCP 'fs': color '{}' must be a hex code like #1a2b3c", color)
+            );
+        }
+    }
+
+    /// Disabled MCP configs where each `(id, deps)` depends on `deps`
+    fn with_deps(specs: &[(&str, &[&str])]) -> Vec<McpServerConfig> {
+        specs
+            .iter()
+            .map(|(id, deps)| {
+                let mut mcp = crate::test_support::mcp_config(id);
+                mcp.depends_on = deps.iter().map(|d| d.to_string()).collect();
+                mcp
+            })
+            .collect()
+    }
+
+    #[test]
+    fn dependency_waves_put_dependencies_first_and_set_cycles_aside() {
+        let mcps = with_deps(&[
+            ("app", &["db", "cache"]),
+            ("db", &[]),
+            ("cache", &["db"]),
+            ("ping", &["pong"]),
+            ("pong", &["ping"]),
+            ("behind", &["ping"]),
+        ]);
+
+        let (waves, cyclic) = dependency_waves(&mcps);
+
+        assert_eq!(waves, [vec!["db"], vec!["cache"], vec!["app"]]);
+        assert_eq!(cyclic, ["ping", "pong", "behind"]);
+    }
+
+    #[test]
+    fn validate_rejects_bad_dependencies() {
+        let error_for = |specs: &[(&str, &[&str])]| {
+            let config = AppConfig {
+                mcps: with_deps(specs),
+                ..AppConfig::default()
+            };
+            ConfigManager::validate(&config).unwrap_err()
+        };
+
+        assert_eq!(
+            error_for(&[("a", &["a"])]),
+            "MCP 'a' cannot depend on itself"
+        );
+        assert_eq!(
+            error_for(&[("a", &["gone"])]),
+            "MCP 'a' depends on unknown MCP ID 'gone'"
+        );
+        assert_eq!(
+            error_for(&[("a", &["b"]), ("b", &["a"]), ("c", &[])]),
+            "MCP dependencies form a cycle (involving a, b)"
+        );
+    }
+
+    #[test]
+    fn validate_requires_tool_defaults_to_be_objects() {
+        let mut mcp = crate::test_support::mcp_config("fs");
+        mcp.tool_defaults =
+            std::collections::HashMap::from([("search".to_string(), serde_json::json!(10))]);
+        let config = AppConfig {
+            mcps: vec![mcp],
+            ..AppConfig::default()
+        };
+
+        assert_eq!(
+            ConfigManager::validate(&config).unwrap_err(),
+            "MCP 'fs': defaults for tool 'search' must be a JSON object"
+        );
+    }
+
+    #[test]
+    fn validate_refuses_to_forward_client_credentials() {
+        let mut mcp = crate::test_support::http_mcp_config("api", "http://127.0.0.1:1/mcp");
+        mcp.forward_headers = vec!["X-Tenant-Id".to_string()];
+        let mut config = AppConfig {
+            mcps: vec![mcp],
+            ..AppConfig::default()
+        };
+        assert!(ConfigManager::validate(&config).is_ok());
+
+        for header in ["Authorization", "cookie"] {
+            config.mcps[0].forward_headers = vec![header.to_string()];
+            assert_eq!(
+                ConfigManager::validate(&config).unwrap_err(),
+                format!("MCP 'api': the client's '{}' header cannot be forwarded", header)
+            );
+        }
+    }
+
+    #[test]
+    fn validate_rejects_duplicate_tool_aliases() {
+        let mut mcp = crate::test_support::mcp_config("fs");
+        mcp.tool_aliases = std::collections::HashMap::from([
+            ("read_file".to_string(), "read".to_string()),
+            ("read_text".to_string(), "read".to_string()),
+        ]);
+        let config = AppConfig {
+            mcps: vec![mcp],
+            ..AppConfig::default()
+        };
+
+        assert_eq!(
+            ConfigManager::validate(&config).unwrap_err(),
+            "MCP 'fs': tool alias 'read' is used more than once"
+        );
+    }
+
+    #[test]
+    fn validate_accepts_only_known_protocol_versions() {
+        let mut mcp = crate::test_support::mcp_config("fs");
+        mcp.upstream_protocol_version = Some("2025-03-26".to_string());
+        let mut config = AppConfig {
+            mcps: vec![mcp],
+            ..AppConfig::default()
+        };
+        assert!(ConfigManager::validate(&config).is_ok());
+
+        config.mcps[0].upstream_protocol_version = Some("2025-13-01".to_string());
+        assert!(ConfigManager::validate(&config)
+            .unwrap_err()
+            .starts_with("MCP 'fs': unknown protocol version '2025-13-01'"));
+    }
+
+    #[test]
+    fn shell_words_follow_posix_quoting() {
+        let split = |input: &str| split_shell_words(input).unwrap();
+
+        assert_eq!(split("  -y  server  "), ["-y", "server"]);
+        assert_eq!(
+            split(r#"--root '/My Files' --name "a \"b\" c""#),
+            ["--root", "/My Files", "--name", r#"a "b" c"#]
+        );
+        assert_eq!(split(r"C:\\tmp dir\ name"), [r"C:\tmp", "dir name"]);
+        assert_eq!(split(r#""\n" '\n'"#), [r"\n", r"\n"]);
+        assert_eq!(split("''"), [""]);
+        assert!(split("").is_empty());
+    }
+
+    #[test]
+    fn shell_words_reject_unbalanced_input() {
+        assert_eq!(split_shell_words("'open").unwrap_err(), "unterminated single quote");
+        assert_eq!(split_shell_words("\"open").unwrap_err(), "unterminated double quote");
+        assert_eq!(split_shell_words("trailing\\").unwrap_err(), "trailing backslash");
+    }
+}
@@ -2,12 +2,16 @@ mod commands;
 mod config;
 mod mcp;
 mod proxy;
+mod snapshot;
+#[cfg(test)]
+mod test_support;
 mod types;
 
 use commands::AppState;
 use tauri::Emitter;
 use config::ConfigManager;
-use mcp::manager::{McpManager, start_health_loop};
+use mcp::manager::{McpManager, shutdown_shared, start_health_loop};
+use snapshot::{start_snapshot_loop, SnapshotStore};
 use std::sync::Arc;
 use tauri::Manager;
 use tokio::sync::Mutex;
@@ -18,9 +22,50 @@ use tracing_subscriber::layer::{Context, SubscriberExt};
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::Layer;
 use tracing_subscriber::EnvFilter;
-use crate::types::LogEntry;
+use crate::types::{AppConfig, ConnectionState, LogEntry, McpStatus, StartupComplete};
 
 const LOG_BUFFER_CAPACITY: usize = 500;
+/// Event field that overrides a log entry's target, used for logs relayed
+/// from upstream servers under their own logger name
+const UPSTREAM_TARGET_FIELD: &str = "upstream_target";
+
+/// Env var selecting the terminal log format: full (default), pretty,
+/// compact or json
+const LOG_FORMAT_ENV: &str = "LOCAL_MCP_LOG_FORMAT";
+
+/// Output format of the stderr fmt layer (the in-memory log store is unaffected)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogFormat {
+    Full,
+    Pretty,
+    Compact,
+    Json,
+}
+
+impl LogFormat {
+    fn from_env_value(value: Option<&str>) -> Self {
+        match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            Some("pretty") => LogFormat::Pretty,
+            Some("compact") => LogFormat::Compact,
+            Some("json") => LogFormat::Json,
+            _ => LogFormat::Full,
+        }
+    }
+}
+
+/// Build the fmt layer for the selected format
+fn fmt_layer<S>(format: LogFormat) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    let layer = tracing_subscriber::fmt::layer();
+    match format {
+        LogFormat::Full => layer.boxed(),
+        LogFormat::Pretty => layer.pretty().boxed(),
+        LogFormat::Compact => layer.compact().boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
 
 struct LogLayer {
     store: Arc<StdMutex<VecDeque<LogEntry>>>,
@@ -78,6 +123,15 @@ where
         let mut visitor = MessageVisitor::default();
         event.record(&mut visitor);
 
+        let mut target = event.metadata().target().to_string();
+        if let Some(pos) = visitor
+            .fields
+            .iter()
+            .position(|(name, _)| name == UPSTREAM_TARGET_FIELD)
+        {
+            target = visitor.fields.remove(pos).1;
+        }
+
         let mut message = visitor.message.unwrap_or_else(|| "log event".to_string());
         if !visitor.fields.is_empty() {
             let extras = visitor
@@ -92,7 +146,7 @@ where
         let entry = LogEntry {
             timestamp: chrono::Utc::now().to_rfc3339(),
             level: event.metadata().level().to_string(),
-            target: event.metadata().target().to_string(),
+            target,
             message,
         };
 
@@ -100,6 +154,25 @@ where
     }
 }
 
+/// Build the `startup-complete` payload from the post-initialization statuses
+fn startup_complete_payload(
+    proxy_port: u16,
+    proxy_bound_address: Option<std::net::SocketAddr>,
+    statuses: &[McpStatus],
+    init_duration: std::time::Duration,
+) -> StartupComplete {
+    StartupComplete {
+        proxy_port,
+        proxy_bound_address: proxy_bound_address.map(|addr| addr.to_string()),
+        mcps_total: statuses.len(),
+        mcps_connected: statuses
+            .iter()
+            .filter(|s| s.state == ConnectionState::Connected)
+            .count(),
+        init_duration_ms: init_duration.as_millis() as u64,
+    }
+}
+
 /// Main Tauri application setup
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -109,7 +182,8 @@ pub fn run() {
     let env_filter = EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| EnvFilter::new("info"));
 
-    let fmt_layer = tracing_subscriber::fmt::layer();
+    let log_format = LogFormat::from_env_value(std::env::var(LOG_FORMAT_ENV).ok().as_deref());
+    let fmt_layer = fmt_layer(log_format);
     let log_layer = LogLayer {
         store: Arc::clone(&log_store),
         emitter: Arc::clone(&log_emitter),
@@ -136,9 +210,9 @@ pub fn run() {
             let config_manager = ConfigManager::from_app_handle(&app_handle)
                 .expect("Failed to initialize config manager");
 
-            // Load config
-            let app_config = config_manager
-                .load()
+            // Load config; a corrupt file is set aside rather than aborting
+            let (app_config, config_warning) = config_manager
+                .load_or_recover()
                 .expect("Failed to load config");
 
             tracing::info!(
@@ -148,6 +222,27 @@ pub fn run() {
             );
 
             let proxy_port = app_config.proxy_port;
+            // Loopback first: the bridge and startup payload rely on it
+            let mut listen_addrs = vec![std::net::SocketAddr::from(([127, 0, 0, 1], proxy_port))];
+            for addr in &app_config.extra_listen_addresses {
+                match addr.parse::<std::net::SocketAddr>() {
+                    Ok(parsed)
+                        if !parsed.ip().is_loopback() && app_config.proxy_token().is_none() =>
+                    {
+                        tracing::warn!(
+                            "Ignoring extra listen address '{}': not loopback and no proxy auth token is set",
+                            addr
+                        )
+                    }
+                    Ok(parsed) => listen_addrs.push(parsed),
+                    Err(_) => tracing::warn!("Ignoring invalid extra listen address '{}'", addr),
+                }
+            }
+
+            // Runtime state from the previous run, if any
+            let snapshot_store = SnapshotStore::from_app_handle(&app_handle)
+                .expect("Failed to initialize snapshot store");
+            let previous_snapshot = snapshot_store.load();
 
             // Create MCP manager
             let manager = Arc::new(Mutex::new(McpManager::new(app_config)));
@@ -162,8 +257,13 @@ pub fn run() {
                 manager: Arc::clone(&manager),
                 config_manager: Arc::clone(&config_mgr),
                 log_store: Arc::clone(&log_store),
+                proxy_port,
+                config_warning,
             });
 
+            let startup_started = std::time::Instant::now();
+            let (bound_tx, bound_rx) = tokio::sync::oneshot::channel();
+
             // Spawn initialization in background
             let mgr_init = Arc::clone(&manager);
             let handle_init = app_handle.clone();
@@ -171,27 +271,46 @@ pub fn run() {
                 // Initialize all MCP connections
                 {
                     let mut mgr = mgr_init.lock().await;
-                    mgr.initialize().await;
+                    mgr.initialize(previous_snapshot.as_ref()).await;
                 }
 
                 // Emit initial statuses
-                {
+                let statuses = {
                     let mgr = mgr_init.lock().await;
-                    let statuses = mgr.list_statuses().await;
-                    let _ = handle_init.emit("mcp-statuses-changed", &statuses);
-                }
+                    mgr.list_statuses().await
+                };
+                let _ = handle_init.emit("mcp-statuses-changed", &statuses);
 
                 tracing::info!("MCP initialization complete");
+
+                // Wait for the proxy listener too (None if binding failed)
+                let bound_address = bound_rx.await.ok();
+                let payload = startup_complete_payload(
+                    proxy_port,
+                    bound_address,
+                    &statuses,
+                    startup_started.elapsed(),
+                );
+                tracing::info!(
+                    "Startup complete in {} ms: {}/{} MCPs connected",
+                    payload.init_duration_ms,
+                    payload.mcps_connected,
+                    payload.mcps_total
+                );
+                let _ = handle_init.emit("startup-complete", &payload);
             });
 
             // Start health check loop
             let mgr_health = Arc::clone(&manager);
             start_health_loop(mgr_health, app_handle.clone());
 
+            // Periodically persist runtime state for crash recovery
+            start_snapshot_loop(Arc::clone(&manager), snapshot_store);
+
             // Start proxy server (HTTP)
             let mgr_proxy = Arc::clone(&manager);
             tauri::async_runtime::spawn(async move {
-                if let Err(e) = proxy::server::start_proxy_server(proxy_port, mgr_proxy).await {
+                if let Err(e) = proxy::server::start_proxy_server(listen_addrs, mgr_proxy, bound_tx).await {
                     tracing::error!("Proxy server error: {}", e);
                 }
             });
@@ -201,31 +320,170 @@ pub fn run() {
         })
         .invoke_handler(tauri::generate_handler![
             commands::list_mcps,
+            commands::get_status_summary,
+            commands::probe_server,
             commands::get_mcp_detail,
+            commands::get_mcp_detail_by_name,
+            commands::get_tool_schema,
+            commands::get_connection_events,
+            commands::get_recent_requests,
             commands::add_mcp,
             commands::update_mcp,
             commands::remove_mcp,
             commands::connect_mcp,
+            commands::connect_mcp_by_name,
             commands::disconnect_mcp,
+            commands::clear_error,
+            commands::reconnect_group,
             commands::set_disabled_items,
+            commands::prune_disabled_items,
+            commands::bulk_set_tool_disabled,
+            commands::reorder_mcps,
+            commands::pause_health_checks,
+            commands::resume_health_checks,
+            commands::set_safe_mode,
+            commands::is_tool_enabled,
+            commands::get_tool_states,
+            commands::get_all_tools,
             commands::get_proxy_url,
             commands::get_app_config,
+            commands::get_effective_config,
+            commands::get_config_warning,
+            commands::get_config_stats,
+            commands::reload_config,
+            commands::list_profiles,
+            commands::save_current_as_profile,
+            commands::switch_profile,
+            commands::flush_config,
             commands::update_app_config,
+            commands::is_port_available,
+            commands::rotate_proxy_token,
             commands::get_logs,
+            commands::export_logs,
+            commands::export_diagnostics,
             commands::check_claude_desktop,
+            commands::check_claude_config_valid,
             commands::add_to_claude_desktop,
             commands::update_in_claude_desktop,
             commands::remove_from_claude_desktop,
+            commands::check_environment,
+            commands::get_build_info,
+            commands::verify_claude_integration,
+            commands::measure_latency,
+            commands::benchmark_tool,
+            commands::get_traffic_stats,
+            commands::get_spawned_processes,
+            commands::kill_process,
         ])
         .on_window_event(|window, event| {
-            if let tauri::WindowEvent::CloseRequested { .. } = event {
-                let manager = window.app_handle().state::<AppState>().manager.clone();
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                // Keep the window until MCPs are drained (bounded by
+                // shutdown_timeout_secs) so stdio children aren't orphaned
+                api.prevent_close();
+                let window = window.clone();
+                let state = window.app_handle().state::<AppState>();
+                let manager = state.manager.clone();
+                let config_manager = state.config_manager.clone();
                 tauri::async_runtime::spawn(async move {
-                    let mgr = manager.lock().await;
-                    mgr.shutdown().await;
+                    // The saved config, not the manager's, since the manager
+                    // lock may be held and its wait is what this bounds
+                    let timeout_secs = config_manager
+                        .lock()
+                        .await
+                        .load()
+                        .map_or(AppConfig::default().shutdown_timeout_secs, |config| {
+                            config.shutdown_timeout_secs
+                        });
+                    shutdown_shared(&manager, timeout_secs).await;
+                    let _ = window.destroy();
                 });
             }
         })
         .run(tauri::generate_context!())
         .expect("error while running Local MCP Proxy");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::manager::McpManager;
+    use crate::test_support::{http_mcp_config, mcp_config, FakeUpstream};
+    use crate::types::AppConfig;
+
+    #[tokio::test]
+    async fn startup_complete_payload_counts_connected_mcps() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let mut mgr = McpManager::new(AppConfig::default());
+        mgr.add_mcp(http_mcp_config("up", &upstream.url))
+            .await
+            .unwrap();
+        mgr.add_mcp(mcp_config("off")).await.unwrap();
+        let statuses = mgr.list_statuses().await;
+        let bound = "127.0.0.1:27270".parse().unwrap();
+
+        let payload = startup_complete_payload(
+            27270,
+            Some(bound),
+            &statuses,
+            std::time::Duration::from_millis(1500),
+        );
+
+        assert_eq!(payload.proxy_port, 27270);
+        assert_eq!(
+            payload.proxy_bound_address.as_deref(),
+            Some("127.0.0.1:27270")
+        );
+        assert_eq!(payload.mcps_total, 2);
+        assert_eq!(payload.mcps_connected, 1);
+        assert_eq!(payload.init_duration_ms, 1500);
+    }
+
+    #[test]
+    fn startup_complete_payload_without_a_bound_proxy() {
+        let payload = startup_complete_payload(27270, None, &[], std::time::Duration::ZERO);
+        assert_eq!(payload.proxy_bound_address, None);
+        assert_eq!(payload.mcps_total, 0);
+        assert_eq!(payload.mcps_connected, 0);
+    }
+
+    #[test]
+    fn relayed_upstream_logs_take_the_upstream_target() {
+        let store: Arc<StdMutex<VecDeque<LogEntry>>> = Default::default();
+        let layer = LogLayer {
+            store: Arc::clone(&store),
+            emitter: Default::default(),
+        };
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(upstream_target = %"github", "rate limited");
+            tracing::info!(attempt = 2, "plain event");
+        });
+
+        let logs = store.lock().unwrap();
+        assert_eq!(logs[0].target, "github");
+        assert_eq!(logs[0].level, "WARN");
+        assert_eq!(logs[0].message, "rate limited");
+        assert_eq!(logs[1].target, module_path!());
+        assert_eq!(logs[1].message, "plain event | attempt=2");
+    }
+
+    #[test]
+    fn log_format_is_read_from_the_env_value() {
+        assert_eq!(LogFormat::from_env_value(Some("pretty")), LogFormat::Pretty);
+        assert_eq!(
+            LogFormat::from_env_value(Some("compact")),
+            LogFormat::Compact
+        );
+        assert_eq!(LogFormat::from_env_value(Some("json")), LogFormat::Json);
+        assert_eq!(LogFormat::from_env_value(Some(" JSON\n")), LogFormat::Json);
+        assert_eq!(LogFormat::from_env_value(Some("full")), LogFormat::Full);
+    }
+
+    #[test]
+    fn log_format_defaults_to_full() {
+        assert_eq!(LogFormat::from_env_value(None), LogFormat::Full);
+        assert_eq!(LogFormat::from_env_value(Some("")), LogFormat::Full);
+        assert_eq!(LogFormat::from_env_value(Some("yaml")), LogFormat::Full);
+    }
+}
@@ -0,0 +1,413 @@
+//! Client-side handler for requests that upstream servers send to the proxy.
+//!
+//! The proxy is the MCP *client* towards every upstream server, so
+//! server -> client requests such as `sampling/createMessage` and
+//! `roots/list` land here rather than at the application talking to the
+//! proxy.  Roots come from the MCP's config.  When a sampling backend is
+//! configured, completions are relayed to an OpenAI-compatible
+//! `/chat/completions` endpoint.  Upstream `notifications/message` log
+//! entries are re-emitted as tracing events so they reach the log buffer.
+
+use crate::types::SamplingConfig;
+use rmcp::handler::client::progress::ProgressDispatcher;
+use rmcp::model::{
+    ClientInfo, CreateMessageRequestMethod, CreateMessageRequestParams, CreateMessageResult,
+    ListRootsResult, LoggingLevel, LoggingMessageNotificationParam, ProgressNotificationParam,
+    Role, Root, RootsCapabilities,
+    SamplingCapability, SamplingMessage, SamplingMessageContent,
+};
+use rmcp::service::{NotificationContext, RequestContext};
+use rmcp::{ClientHandler, ErrorData as McpError, RoleClient};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Handler passed to `serve()` for every upstream connection
+pub struct ProxyClientHandler {
+    info: ClientInfo,
+    sampling: Arc<Mutex<Option<SamplingConfig>>>,
+    roots: Arc<Mutex<Vec<String>>>,
+    /// Routes upstream progress notifications to the request awaiting them
+    progress: ProgressDispatcher,
+    http: reqwest::Client,
+}
+
+impl ProxyClientHandler {
+    /// Build a handler, advertising the sampling capability only when a
+    /// backend is configured
+    pub async fn new(
+        mut info: ClientInfo,
+        sampling: Arc<Mutex<Option<SamplingConfig>>>,
+        roots: Arc<Mutex<Vec<String>>>,
+        progress: ProgressDispatcher,
+    ) -> Self {
+        if sampling.lock().await.is_some() {
+            info.capabilities.sampling = Some(SamplingCapability::default());
+        }
+        info.capabilities.roots = Some(RootsCapabilities {
+            list_changed: Some(true),
+        });
+        Self {
+            info,
+            sampling,
+            roots,
+            progress,
+            http: reqwest::Client::new(),
+        }
+    }
+}
+
+impl ClientHandler for ProxyClientHandler {
+    fn get_info(&self) -> ClientInfo {
+        self.info.clone()
+    }
+
+    async fn create_message(
+        &self,
+        params: CreateMessageRequestParams,
+        _context: RequestContext<RoleClient>,
+    ) -> Result<CreateMessageResult, McpError> {
+        let Some(config) = self.sampling.lock().await.clone() else {
+            return Err(McpError::method_not_found::<CreateMessageRequestMethod>());
+        };
+        tracing::info!(
+            "Relaying sampling request ({} messages) to {}",
+            params.messages.len(),
+            config.endpoint
+        );
+        complete(&self.http, &config, &params).await
+    }
+
+    async fn list_roots(
+        &self,
+        _context: RequestContext<RoleClient>,
+    ) -> Result<ListRootsResult, McpError> {
+        let roots = self.roots.lock().await;
+        Ok(ListRootsResult {
+            roots: roots
+                .iter()
+                .map(|root| Root {
+                    uri: root_uri(root),
+                    name: None,
+                })
+                .collect(),
+        })
+    }
+
+    async fn on_progress(
+        &self,
+        params: ProgressNotificationParam,
+        _context: NotificationContext<RoleClient>,
+    ) {
+        self.progress.handle_notification(params).await;
+    }
+
+    async fn on_logging_message(
+        &self,
+        params: LoggingMessageNotificationParam,
+        context: NotificationContext<RoleClient>,
+    ) {
+        // The upstream logger name becomes the log entry's target
+        let target = params
+            .logger
+            .or_else(|| {
+                context
+                    .peer
+                    .peer_info()
+                    .map(|info| info.server_info.name.clone())
+            })
+            .unwrap_or_else(|| "upstream".to_string());
+        let message = match params.data {
+            serde_json::Value::String(text) => text,
+            other => other.to_string(),
+        };
+        match mcp_log_level(params.level) {
+            tracing::Level::ERROR => tracing::error!(upstream_target = %target, "{}", message),
+            tracing::Level::WARN => tracing::warn!(upstream_target = %target, "{}", message),
+            tracing::Level::INFO => tracing::info!(upstream_target = %target, "{}", message),
+            _ => tracing::debug!(upstream_target = %target, "{}", message),
+        }
+    }
+}
+
+/// Map an MCP (syslog-style) log level onto the closest tracing level:
+/// notice folds into info, and critical/alert/emergency into error
+fn mcp_log_level(level: LoggingLevel) -> tracing::Level {
+    match level {
+        LoggingLevel::Debug => tracing::Level::DEBUG,
+        LoggingLevel::Info | LoggingLevel::Notice => tracing::Level::INFO,
+        LoggingLevel::Warning => tracing::Level::WARN,
+        LoggingLevel::Error
+        | LoggingLevel::Critical
+        | LoggingLevel::Alert
+        | LoggingLevel::Emergency => tracing::Level::ERROR,
+    }
+}
+
+/// Roots must be URIs; plain filesystem paths are turned into `file://` URIs
+fn root_uri(root: &str) -> String {
+    if root.contains("://") {
+        return root.to_string();
+    }
+    reqwest::Url::from_file_path(root)
+        .map(|url| url.to_string())
+        .unwrap_or_else(|_| format!("file://{}", root))
+}
+
+/// Translate an MCP sampling request to an OpenAI chat completion and back
+async fn complete(
+    http: &reqwest::Client,
+    config: &SamplingConfig,
+    params: &CreateMessageRequestParams,
+) -> Result<CreateMessageResult, McpError> {
+    let mut messages = Vec::new();
+    if let Some(system) = &params.system_prompt {
+        messages.push(serde_json::json!({ "role": "system", "content": system }));
+    }
+    for message in &params.messages {
+        messages.push(chat_message(message)?);
+    }
+
+    let mut body = serde_json::json!({
+        "model": config.model,
+        "messages": messages,
+        "max_tokens": params.max_tokens,
+    });
+    if let Some(temperature) = params.temperature {
+        body["temperature"] = serde_json::json!(temperature);
+    }
+    if let Some(stop) = &params.stop_sequences {
+        body["stop"] = serde_json::json!(stop);
+    }
+
+    let url = format!("{}/chat/completions", config.endpoint.trim_end_matches('/'));
+    let mut request = http.post(&url).json(&body);
+    if let Some(key) = &config.api_key {
+        request = request.bearer_auth(key);
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| McpError::internal_error(format!("Sampling backend unreachable: {}", e), None))?;
+    let status = response.status();
+    let reply: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| McpError::internal_error(format!("Invalid sampling backend response: {}", e), None))?;
+    if !status.is_success() {
+        return Err(McpError::internal_error(
+            format!("Sampling backend returned HTTP {}", status.as_u16()),
+            Some(reply),
+        ));
+    }
+
+    let choice = reply
+        .get("choices")
+        .and_then(|c| c.get(0))
+        .ok_or_else(|| McpError::internal_error("Sampling backend returned no choices", None))?;
+    let text = choice
+        .pointer("/message/content")
+        .and_then(|c| c.as_str())
+        .unwrap_or_default();
+    let stop_reason = match choice.get("finish_reason").and_then(|r| r.as_str()) {
+        Some("length") => CreateMessageResult::STOP_REASON_END_MAX_TOKEN,
+        Some("tool_calls") => CreateMessageResult::STOP_REASON_TOOL_USE,
+        _ => CreateMessageResult::STOP_REASON_END_TURN,
+    };
+
+    Ok(CreateMessageResult {
+        model: reply
+            .get("model")
+            .and_then(|m| m.as_str())
+            .unwrap_or(&config.model)
+            .to_string(),
+        stop_reason: Some(stop_reason.to_string()),
+        message: SamplingMessage::new(Role::Assistant, SamplingMessageContent::text(text)),
+    })
+}
+
+/// Convert one sampling message into an OpenAI chat message
+fn chat_message(message: &SamplingMessage) -> Result<serde_json::Value, McpError> {
+    let role = match message.role {
+        Role::User => "user",
+        Role::Assistant => "assistant",
+    };
+    let mut parts = Vec::new();
+    for content in message.content.clone().into_vec() {
+        match content {
+            SamplingMessageContent::Text(text) => {
+                parts.push(serde_json::json!({ "type": "text", "text": text.text }));
+            }
+            SamplingMessageContent::Image(image) => {
+                parts.push(serde_json::json!({
+                    "type": "image_url",
+                    "image_url": { "url": format!("data:{};base64,{}", image.mime_type, image.data) }
+                }));
+            }
+            _ => {
+                return Err(McpError::invalid_params(
+                    "Sampling backend only supports text and image content",
+                    None,
+                ));
+            }
+        }
+    }
+    Ok(serde_json::json!({ "role": role, "content": parts }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::connection::McpConnection;
+    use crate::mcp::manager::ConnectPermits;
+    use crate::test_support::{http_mcp_config, FakeUpstream};
+    use crate::types::{McpServerConfig, ReconnectReason, TransportType};
+    use axum::extract::State;
+    use axum::http::HeaderMap;
+    use axum::Json;
+    use serde_json::{json, Value};
+    use std::sync::Mutex as StdMutex;
+
+    type Captured = Arc<StdMutex<Vec<(HeaderMap, Value)>>>;
+
+    /// An OpenAI-compatible endpoint that always answers "hi there",
+    /// recording each request.  Returns the base URL.
+    async fn mock_llm(captured: Captured) -> String {
+        async fn completions(
+            State(captured): State<Captured>,
+            headers: HeaderMap,
+            Json(body): Json<Value>,
+        ) -> Json<Value> {
+            captured.lock().unwrap().push((headers, body));
+            Json(json!({
+                "model": "mock-1",
+                "choices": [{"message": {"role": "assistant", "content": "hi there"}, "finish_reason": "stop"}]
+            }))
+        }
+        let router = axum::Router::new()
+            .route("/v1/chat/completions", axum::routing::post(completions))
+            .with_state(captured);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        format!("http://{}/v1", addr)
+    }
+
+    /// Config for the fake upstream's legacy SSE endpoint, which lets the
+    /// upstream push requests to the proxy
+    fn sse_config(upstream: &FakeUpstream) -> McpServerConfig {
+        let mut config = http_mcp_config("fs", &upstream.sse_url);
+        config.transport_type = TransportType::Sse;
+        config
+    }
+
+    async fn connect(config: McpServerConfig, sampling: Option<SamplingConfig>) -> McpConnection {
+        let conn = McpConnection::new(
+            config,
+            5,
+            Arc::new(ConnectPermits::new(1)),
+            Arc::new(Mutex::new(sampling)),
+            Arc::new(Mutex::new(None)),
+        );
+        conn.connect(ReconnectReason::Initial).await.unwrap();
+        conn
+    }
+
+    fn sampling_request(id: &str) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "sampling/createMessage",
+            "params": {
+                "messages": [{"role": "user", "content": {"type": "text", "text": "hello"}}],
+                "systemPrompt": "be brief",
+                "maxTokens": 50
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn upstream_sampling_requests_are_relayed_to_the_backend() {
+        let captured = Captured::default();
+        let endpoint = mock_llm(captured.clone()).await;
+        let upstream = FakeUpstream::spawn("up", &[]).await;
+        let sampling = SamplingConfig {
+            endpoint,
+            api_key: Some("sk-test".to_string()),
+            model: "gpt-test".to_string(),
+        };
+        let _conn = connect(sse_config(&upstream), Some(sampling)).await;
+        let initialize = &upstream.params_of("initialize")[0];
+        assert!(initialize["capabilities"]["sampling"].is_object());
+
+        upstream.send_sse(sampling_request("s1"));
+        let reply = upstream.wait_for_response("s1").await;
+
+        assert_eq!(reply["result"]["model"], "mock-1");
+        assert_eq!(reply["result"]["role"], "assistant");
+        assert_eq!(reply["result"]["content"]["text"], "hi there");
+        assert_eq!(reply["result"]["stopReason"], "endTurn");
+
+        let captured = captured.lock().unwrap();
+        let (headers, body) = &captured[0];
+        assert_eq!(headers["authorization"], "Bearer sk-test");
+        assert_eq!(body["model"], "gpt-test");
+        assert_eq!(body["max_tokens"], 50);
+        assert_eq!(
+            body["messages"][0],
+            json!({"role": "system", "content": "be brief"})
+        );
+        assert_eq!(body["messages"][1]["role"], "user");
+    }
+
+    #[tokio::test]
+    async fn sampling_is_refused_without_a_backend() {
+        let upstream = FakeUpstream::spawn("up", &[]).await;
+        let _conn = connect(sse_config(&upstream), None).await;
+        let initialize = &upstream.params_of("initialize")[0];
+        assert!(initialize["capabilities"].get("sampling").is_none());
+
+        upstream.send_sse(sampling_request("s1"));
+        let reply = upstream.wait_for_response("s1").await;
+
+        assert_eq!(reply["error"]["code"], -32601);
+    }
+
+    #[test]
+    fn mcp_log_levels_fold_onto_tracing_levels() {
+        let cases = [
+            (LoggingLevel::Debug, tracing::Level::DEBUG),
+            (LoggingLevel::Info, tracing::Level::INFO),
+            (LoggingLevel::Notice, tracing::Level::INFO),
+            (LoggingLevel::Warning, tracing::Level::WARN),
+            (LoggingLevel::Error, tracing::Level::ERROR),
+            (LoggingLevel::Critical, tracing::Level::ERROR),
+            (LoggingLevel::Alert, tracing::Level::ERROR),
+            (LoggingLevel::Emergency, tracing::Level::ERROR),
+        ];
+        for (level, expected) in cases {
+            assert_eq!(mcp_log_level(level), expected, "{:?}", level);
+        }
+    }
+
+    #[tokio::test]
+    async fn roots_list_serves_the_configured_roots_as_uris() {
+        let upstream = FakeUpstream::spawn("up", &[]).await;
+        let mut config = sse_config(&upstream);
+        config.roots = vec![
+            "/srv/data".to_string(),
+            "https://example.com/repo".to_string(),
+        ];
+        let _conn = connect(config, None).await;
+        let initialize = &upstream.params_of("initialize")[0];
+        assert_eq!(initialize["capabilities"]["roots"]["listChanged"], true);
+
+        upstream.send_sse(json!({"jsonrpc": "2.0", "id": "r1", "method": "roots/list"}));
+        let reply = upstream.wait_for_response("r1").await;
+
+        assert_eq!(
+            reply["result"]["roots"],
+            json!([{"uri": "file:///srv/data"}, {"uri": "https://example.com/repo"}])
+        );
+    }
+}
@@ -1,47 +1,274 @@
+use crate::config::split_shell_words;
+use crate::mcp::client_handler::ProxyClientHandler;
+use crate::mcp::manager::{ConnectPermits, DisabledItems, ToolAliases, REDACTED};
+use crate::mcp::signing::RequestSigner;
 use crate::types::*;
 use anyhow::{anyhow, Context, Result};
-use rmcp::model::CallToolRequestParams;
+use rmcp::handler::client::progress::ProgressDispatcher;
+use rmcp::model::{
+    CallToolRequest, CallToolRequestParams, CallToolResult, ClientInfo, ClientJsonRpcMessage,
+    ClientRequest, ErrorCode, Extensions, GetExtensions, Implementation, PingRequest,
+    ProgressNotificationParam, Request, ServerResult,
+};
+use rmcp::service::PeerRequestOptions;
 use rmcp::service::RunningService;
-use rmcp::transport::TokioChildProcess;
 use rmcp::RoleClient;
 use rmcp::ServiceExt;
+use std::collections::{HashMap, VecDeque};
 use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
-use tokio::process::Command;
-use tokio::sync::Mutex;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::{Mutex, Semaphore};
+
+/// Client name presented to upstream servers unless overridden per MCP
+const DEFAULT_CLIENT_NAME: &str = "local-mcp-proxy";
+
+/// Receives progress notifications for a streamed `tools/call`
+pub type ProgressSender = tokio::sync::mpsc::UnboundedSender<ProgressNotificationParam>;
+
+/// Client request headers named in `forward_headers`, copied onto the
+/// upstream HTTP request.  They ride along in the rmcp request's extensions
+/// until `GracefulHttpClient` sends it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ForwardedHeaders(pub Vec<(String, String)>);
+
+impl ForwardedHeaders {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn to_extensions(&self) -> Extensions {
+        let mut extensions = Extensions::new();
+        if !self.is_empty() {
+            extensions.insert(self.clone());
+        }
+        extensions
+    }
+}
+
+/// How long a stdio child may take to exit after its stdin closes before
+/// it is killed
+const CHILD_EXIT_GRACE_SECS: u64 = 3;
+
+/// Base delay between request retries; doubled after each attempt
+const RETRY_BACKOFF_MS: u64 = 200;
+
+/// Methods without side effects, which are always safe to retry
+const RETRY_SAFE_METHODS: &[&str] = &[
+    "ping",
+    "tools/list",
+    "resources/list",
+    "resources/read",
+    "resources/templates/list",
+    "prompts/list",
+    "prompts/get",
+];
+
+/// Number of stderr lines kept per stdio server
+const STDERR_BUFFER_LINES: usize = 50;
+
+/// Connection lifecycle events kept per MCP
+const CONNECTION_EVENT_LIMIT: usize = 100;
+
+/// Proxied requests kept per MCP when `record_requests` is enabled
+const RECORDED_REQUEST_LIMIT: usize = 50;
+
+/// Object keys whose values are redacted from recorded requests.  A key
+/// matches when its last words are one of these, ignoring case and how
+/// words are separated: `accessToken` and `X-Api-Key` match, `max_tokens`
+/// and `token_count` don't.
+const SENSITIVE_KEYS: &[&str] = &[
+    "authorization",
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "api_key",
+    "apikey",
+    "credential",
+    "credentials",
+];
+
+/// MCP protocol fields that end like a sensitive key but carry nothing
+/// secret
+const PROTOCOL_KEYS: &[&str] = &["progressToken"];
+
+/// Default TCP keepalive for HTTP upstreams; keeps long-lived streams from
+/// being silently dropped by NAT/load-balancer idle timeouts
+const DEFAULT_TCP_KEEPALIVE_SECS: u64 = 60;
+
+/// Default HTTP/2 keepalive ping interval for HTTP upstreams
+const DEFAULT_HTTP2_KEEP_ALIVE_INTERVAL_SECS: u64 = 30;
+
+/// Default connect timeout for HTTP upstreams
+const DEFAULT_HTTP_CONNECT_TIMEOUT_SECS: u64 = 10;
+
+/// Default idle timeout for pooled HTTP connections
+const DEFAULT_HTTP_POOL_IDLE_TIMEOUT_SECS: u64 = 90;
+
+/// Fill in the defaults a connection applies for unset per-MCP options, so
+/// the result shows the values actually in use
+pub fn resolve_effective_config(config: &McpServerConfig) -> McpServerConfig {
+    let mut effective = config.clone();
+    effective.tcp_keepalive_secs.get_or_insert(DEFAULT_TCP_KEEPALIVE_SECS);
+    effective
+        .http2_keep_alive_interval_secs
+        .get_or_insert(DEFAULT_HTTP2_KEEP_ALIVE_INTERVAL_SECS);
+    effective.http2_adaptive_window.get_or_insert(true);
+    effective
+        .http_connect_timeout_secs
+        .get_or_insert(DEFAULT_HTTP_CONNECT_TIMEOUT_SECS);
+    effective
+        .http_pool_idle_timeout_secs
+        .get_or_insert(DEFAULT_HTTP_POOL_IDLE_TIMEOUT_SECS);
+    effective.client_info.get_or_insert_with(default_client_identity);
+    effective
+}
+
+fn default_client_identity() -> ClientIdentity {
+    ClientIdentity {
+        name: DEFAULT_CLIENT_NAME.to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+    }
+}
 
 /// A wrapper around `reqwest::Client` that tolerates servers returning 404
 /// (or other non-405 errors) on DELETE session requests.  The upstream rmcp
 /// library only treats 405 as "not supported" and logs everything else at
 /// `error` level.  Many real-world servers (especially behind reverse proxies)
 /// return 404 for DELETE, so we handle that gracefully here.
+///
+/// When `request_signing` is configured, POSTs also carry an HMAC signature
+/// of their body (see `RequestSigner`), and requests carrying
+/// `ForwardedHeaders` get those headers added.
 #[derive(Clone)]
-struct GracefulHttpClient(reqwest::Client);
+struct GracefulHttpClient(reqwest::Client, Option<Arc<RequestSigner>>);
 
 impl rmcp::transport::streamable_http_client::StreamableHttpClient for GracefulHttpClient {
     type Error = reqwest::Error;
 
-    fn post_message(
+    async fn post_message(
         &self,
         uri: Arc<str>,
         message: rmcp::model::ClientJsonRpcMessage,
         session_id: Option<Arc<str>>,
         auth_header: Option<String>,
-    ) -> impl std::future::Future<
-        Output = std::result::Result<
-            rmcp::transport::streamable_http_client::StreamableHttpPostResponse,
-            rmcp::transport::streamable_http_client::StreamableHttpError<Self::Error>,
-        >,
-    > + Send + '_ {
-        // Delegate directly to the inner reqwest::Client impl
-        rmcp::transport::streamable_http_client::StreamableHttpClient::post_message(
-            &self.0,
-            uri,
-            message,
-            session_id,
-            auth_header,
-        )
+    ) -> std::result::Result<
+        rmcp::transport::streamable_http_client::StreamableHttpPostResponse,
+        rmcp::transport::streamable_http_client::StreamableHttpError<Self::Error>,
+    > {
+        use rmcp::transport::common::http_header::{
+            EVENT_STREAM_MIME_TYPE, HEADER_SESSION_ID, JSON_MIME_TYPE,
+        };
+        use rmcp::transport::streamable_http_client::{
+            StreamableHttpError, StreamableHttpPostResponse,
+        };
+
+        let forwarded = match &message {
+            ClientJsonRpcMessage::Request(request) => {
+                request.request.extensions().get::<ForwardedHeaders>().cloned()
+            }
+            _ => None,
+        };
+
+        // Without signing or extra headers, delegate directly to the inner
+        // reqwest::Client impl
+        if self.1.is_none() && forwarded.is_none() {
+            return rmcp::transport::streamable_http_client::StreamableHttpClient::post_message(
+                &self.0,
+                uri,
+                message,
+                session_id,
+                auth_header,
+            )
+            .await;
+        }
+
+        // Serialize up front so the signature covers the exact bytes sent
+        let body = serde_json::to_vec(&message)?;
+        let mut request = self
+            .0
+            .post(uri.as_ref())
+            .header(
+                reqwest::header::ACCEPT,
+                [EVENT_STREAM_MIME_TYPE, JSON_MIME_TYPE].join(", "),
+            )
+            .header(reqwest::header::CONTENT_TYPE, JSON_MIME_TYPE);
+        if let Some(signer) = &self.1 {
+            for (name, value) in signer.headers(&body) {
+                request = request.header(name, value);
+            }
+        }
+        for (name, value) in forwarded.iter().flat_map(|f| &f.0) {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        if let Some(auth_header) = auth_header {
+            request = request.bearer_auth(auth_header);
+        }
+        if let Some(session_id) = session_id {
+            request = request.header(HEADER_SESSION_ID, session_id.as_ref());
+        }
+        let response = request
+            .body(body)
+            .send()
+            .await
+            .map_err(StreamableHttpError::Client)?;
+
+        // Same as the inner impl: a 401 with a challenge means auth is needed
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            if let Some(header) = response.headers().get(reqwest::header::WWW_AUTHENTICATE) {
+                let header = header.to_str().map_err(|_| {
+                    StreamableHttpError::UnexpectedServerResponse(
+                        "invalid www-authenticate header value".into(),
+                    )
+                })?;
+                return Err(StreamableHttpError::AuthRequired(
+                    rmcp::transport::streamable_http_client::AuthRequiredError {
+                        www_authenticate_header: header.to_string(),
+                    },
+                ));
+            }
+        }
+        // Other rejections of our credentials keep their status, for
+        // `classify_connect_error`
+        if matches!(
+            response.status(),
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN
+        ) {
+            if let Err(e) = response.error_for_status_ref() {
+                return Err(StreamableHttpError::Client(e));
+            }
+        }
+        if matches!(
+            response.status(),
+            reqwest::StatusCode::ACCEPTED | reqwest::StatusCode::NO_CONTENT
+        ) {
+            return Ok(StreamableHttpPostResponse::Accepted);
+        }
+        let session_id = response
+            .headers()
+            .get(HEADER_SESSION_ID)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string());
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .map(|ct| String::from_utf8_lossy(ct.as_bytes()).to_string());
+        match content_type {
+            Some(ct) if ct.starts_with(EVENT_STREAM_MIME_TYPE) => {
+                use futures::StreamExt;
+                let events = sse_stream::SseStream::from_byte_stream(response.bytes_stream()).boxed();
+                Ok(StreamableHttpPostResponse::Sse(events, session_id))
+            }
+            Some(ct) if ct.starts_with(JSON_MIME_TYPE) => {
+                let message = response.json().await.map_err(StreamableHttpError::Client)?;
+                Ok(StreamableHttpPostResponse::Json(message, session_id))
+            }
+            other => Err(StreamableHttpError::UnexpectedContentType(other)),
+        }
     }
 
     fn get_stream(
@@ -112,23 +339,115 @@ impl rmcp::transport::streamable_http_client::StreamableHttpClient for GracefulH
     }
 }
 
+/// Returned when an MCP already has `max_inflight_requests` requests running
+#[derive(Debug, thiserror::Error)]
+#[error("Too many concurrent requests ({0} in flight)")]
+pub struct TooManyRequests(pub usize);
+
+/// Returned when a request's params don't match its method
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid {method} params: {source}")]
+pub struct InvalidParams {
+    pub method: String,
+    source: serde_json::Error,
+}
+
+/// Running totals behind `TrafficStats`
+#[derive(Default)]
+struct TrafficCounters {
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    request_count: AtomicU64,
+}
+
+/// Counts a request as in flight for as long as it is alive
+struct InFlightGuard<'a>(&'a AtomicUsize);
+
+impl<'a> InFlightGuard<'a> {
+    fn new(counter: &'a AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Self(counter)
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
 /// Represents a single MCP server connection
 pub struct McpConnection {
     pub config: McpServerConfig,
     state: Arc<Mutex<ConnectionState>>,
-    service: Arc<Mutex<Option<RunningService<RoleClient, ()>>>>,
+    service: Arc<Mutex<Option<RunningService<RoleClient, ProxyClientHandler>>>>,
     tools: Arc<Mutex<Vec<Tool>>>,
     resources: Arc<Mutex<Vec<Resource>>>,
     connected_at: Arc<Mutex<Option<SystemTime>>>,
     last_ping: Arc<Mutex<Option<SystemTime>>>,
     error_message: Arc<Mutex<Option<String>>>,
+    error_kind: Arc<Mutex<Option<ConnectErrorKind>>>,
     reconnect_attempts: Arc<Mutex<u32>>,
     connection_timeout_secs: Arc<Mutex<u64>>,
+    active_upstream: Arc<Mutex<usize>>,
+    /// Transport found by `Auto` probing, per upstream URL, so later
+    /// connects skip the probe
+    detected_transports: Arc<Mutex<HashMap<String, TransportType>>>,
+    /// Liveness of the current legacy SSE stream, replaced on each SSE connect
+    sse_alive: Arc<Mutex<Option<Arc<AtomicBool>>>>,
+    last_activity: Arc<Mutex<Option<SystemTime>>>,
+    /// When tools/resources were last listed, by whichever path
+    capabilities_fetched_at: Arc<Mutex<Option<SystemTime>>>,
+    /// Set while a periodic refresh runs so overlapping ones are skipped
+    refreshing_capabilities: Arc<AtomicBool>,
+    /// Until then an Error from the first connect is reported as
+    /// Connecting, since a retry is on its way (see `start_settling`)
+    settling_until: Arc<Mutex<Option<SystemTime>>>,
+    idle_disconnected: Arc<Mutex<bool>>,
+    /// Set once the user asks to connect; in safe mode only these are kept
+    /// up by the health loop and proxy requests
+    user_requested: Arc<AtomicBool>,
+    /// Serializes connect attempts so concurrent callers share one handshake
+    connect_lock: Arc<Mutex<()>>,
+    recent_stderr: Arc<Mutex<VecDeque<String>>>,
+    events: Arc<Mutex<VecDeque<ConnectionEvent>>>,
+    recorded_requests: Arc<Mutex<VecDeque<RecordedRequest>>>,
+    /// Requests currently being executed against the upstream
+    in_flight: Arc<AtomicUsize>,
+    /// Sizes of proxied messages, for `get_traffic_stats`
+    traffic: Arc<TrafficCounters>,
+    /// Bounds concurrent requests when `max_inflight_requests` is set
+    request_permits: Option<Arc<Semaphore>>,
+    /// Shared across all connections to bound concurrent handshakes
+    connect_permits: Arc<ConnectPermits>,
+    /// Shared sampling backend config (see `ProxyClientHandler`)
+    sampling: Arc<Mutex<Option<SamplingConfig>>>,
+    /// Roots served to the upstream; updated in place on config changes
+    roots: Arc<Mutex<Vec<String>>>,
+    /// Handle of the stdio child process while one is running, kept so
+    /// liveness can be checked directly and the process reaped on disconnect
+    child: Arc<Mutex<Option<Child>>>,
+    /// Command line the stdio child was last spawned with
+    child_command: Arc<Mutex<Option<String>>>,
+    /// Shared with the client handler to route upstream progress notifications
+    progress: ProgressDispatcher,
+    /// Shared global `inherited_env_allowlist`
+    env_allowlist: Arc<Mutex<Option<Vec<String>>>>,
 }
 
 impl McpConnection {
     /// Create a new connection (not yet connected)
-    pub fn new(config: McpServerConfig, connection_timeout_secs: u64) -> Self {
+    pub fn new(
+        config: McpServerConfig,
+        connection_timeout_secs: u64,
+        connect_permits: Arc<ConnectPermits>,
+        sampling: Arc<Mutex<Option<SamplingConfig>>>,
+        env_allowlist: Arc<Mutex<Option<Vec<String>>>>,
+    ) -> Self {
+        let roots = Arc::new(Mutex::new(config.roots.clone()));
+        let request_permits = config
+            .max_inflight_requests
+            .map(|max| Arc::new(Semaphore::new(max)));
         Self {
             config,
             state: Arc::new(Mutex::new(ConnectionState::Disconnected)),
@@ -138,8 +457,32 @@ impl McpConnection {
             connected_at: Arc::new(Mutex::new(None)),
             last_ping: Arc::new(Mutex::new(None)),
             error_message: Arc::new(Mutex::new(None)),
+            error_kind: Arc::new(Mutex::new(None)),
             reconnect_attempts: Arc::new(Mutex::new(0)),
             connection_timeout_secs: Arc::new(Mutex::new(connection_timeout_secs)),
+            active_upstream: Arc::new(Mutex::new(0)),
+            detected_transports: Arc::new(Mutex::new(HashMap::new())),
+            sse_alive: Arc::new(Mutex::new(None)),
+            last_activity: Arc::new(Mutex::new(None)),
+            capabilities_fetched_at: Arc::new(Mutex::new(None)),
+            refreshing_capabilities: Arc::new(AtomicBool::new(false)),
+            settling_until: Arc::new(Mutex::new(None)),
+            idle_disconnected: Arc::new(Mutex::new(false)),
+            user_requested: Arc::new(AtomicBool::new(false)),
+            connect_lock: Arc::new(Mutex::new(())),
+            recent_stderr: Arc::new(Mutex::new(VecDeque::with_capacity(STDERR_BUFFER_LINES))),
+            events: Arc::new(Mutex::new(VecDeque::with_capacity(CONNECTION_EVENT_LIMIT))),
+            recorded_requests: Arc::new(Mutex::new(VecDeque::new())),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            traffic: Arc::new(TrafficCounters::default()),
+            request_permits,
+            connect_permits,
+            sampling,
+            roots,
+            child: Arc::new(Mutex::new(None)),
+            child_command: Arc::new(Mutex::new(None)),
+            progress: ProgressDispatcher::new(),
+            env_allowlist,
         }
     }
 
@@ -148,9 +491,14 @@ impl McpConnection {
         *self.connection_timeout_secs.lock().await = secs;
     }
 
-    /// Get current connection state
+    /// Get current connection state.  A failed first connect still in its
+    /// grace period reads as Connecting (see `start_settling`).
     pub async fn get_state(&self) -> ConnectionState {
-        *self.state.lock().await
+        if self.is_settling().await {
+            ConnectionState::Connecting
+        } else {
+            *self.state.lock().await
+        }
     }
 
     /// Set connection state and update related fields
@@ -167,7 +515,9 @@ impl McpConnection {
         match new_state {
             ConnectionState::Connected => {
                 *self.connected_at.lock().await = Some(SystemTime::now());
+                *self.last_activity.lock().await = Some(SystemTime::now());
                 *self.error_message.lock().await = None;
+                *self.error_kind.lock().await = None;
                 *self.reconnect_attempts.lock().await = 0;
             }
             ConnectionState::Disconnected => {
@@ -177,9 +527,72 @@ impl McpConnection {
         }
     }
 
-    /// Set an error message
-    async fn set_error(&self, msg: String) {
+    /// Set an error message, with its kind when it came from a connect
+    async fn set_error(&self, msg: String, kind: Option<ConnectErrorKind>) {
         *self.error_message.lock().await = Some(msg);
+        *self.error_kind.lock().await = kind;
+    }
+
+    /// Put the connection into Error without trying to connect, e.g. when
+    /// a server it depends on is unavailable
+    pub async fn fail_without_connect(&self, msg: String) {
+        tracing::warn!("MCP '{}': not connecting: {}", self.config.name, msg);
+        self.record_event(ConnectionEventKind::Failed, None, Some(msg.clone()))
+            .await;
+        self.set_error(msg, None).await;
+        self.set_state(ConnectionState::Error).await;
+    }
+
+    /// Drop a stale error message. A connection in Error that still answers
+    /// a ping goes back to Connected; one that doesn't stays in Error with
+    /// the ping failure as its message. Returns the resulting state.
+    pub async fn clear_error(&self) -> ConnectionState {
+        *self.error_message.lock().await = None;
+        *self.error_kind.lock().await = None;
+        if self.get_state().await != ConnectionState::Error {
+            return self.get_state().await;
+        }
+
+        match self.ping().await {
+            Ok(()) => {
+                tracing::info!("MCP '{}': error cleared, connection is alive", self.config.name);
+                self.set_state(ConnectionState::Connected).await;
+            }
+            Err(e) => self.set_error(format!("{:#}", e), None).await,
+        }
+        self.get_state().await
+    }
+
+    /// Report a failed first connect as Connecting for up to `window`, or
+    /// until the next connect attempt settles it either way
+    pub async fn start_settling(&self, window: Duration) {
+        *self.settling_until.lock().await = Some(SystemTime::now() + window);
+    }
+
+    /// Whether a failed first connect is still within its settling window,
+    /// reported as Connecting although it needs a retry
+    pub async fn is_settling(&self) -> bool {
+        *self.state.lock().await == ConnectionState::Error
+            && self
+                .settling_until
+                .lock()
+                .await
+                .is_some_and(|until| SystemTime::now() < until)
+    }
+
+    /// Carry over the reconnect attempts recorded before a restart
+    pub async fn restore_reconnect_attempts(&self, attempts: u32) {
+        *self.reconnect_attempts.lock().await = attempts;
+    }
+
+    /// Give the health loop a fresh retry budget for this connection
+    pub async fn reset_reconnect_attempts(&self) {
+        *self.reconnect_attempts.lock().await = 0;
+    }
+
+    /// Last connection error, if any
+    pub async fn get_error(&self) -> Option<String> {
+        self.error_message.lock().await.clone()
     }
 
     /// Get current reconnect attempts count
@@ -188,36 +601,197 @@ impl McpConnection {
     }
 
     /// Increment reconnect attempts
-    pub async fn increment_reconnect_attempts(&self) {
+    async fn increment_reconnect_attempts(&self) {
         let mut attempts = self.reconnect_attempts.lock().await;
         *attempts += 1;
     }
 
-    /// Attempt to connect to the MCP server
-    pub async fn connect(&self) -> Result<()> {
-        self.set_state(ConnectionState::Connecting).await;
+    /// Whether the connection has seen no proxy requests for at least
+    /// `timeout_secs` while connected
+    pub async fn is_idle(&self, timeout_secs: u64) -> bool {
+        if self.get_state().await != ConnectionState::Connected {
+            return false;
+        }
+        let last_activity = *self.last_activity.lock().await;
+        last_activity
+            .and_then(|t| SystemTime::now().duration_since(t).ok())
+            .is_some_and(|idle| idle >= Duration::from_secs(timeout_secs))
+    }
 
-        // Wrap the connect in an overall timeout so we don't block forever
-        // if the server never completes the MCP handshake.
-        let timeout_secs = *self.connection_timeout_secs.lock().await;
-        let target = self.config.url.as_deref()
-            .or(self.config.command.as_deref())
-            .unwrap_or("unknown");
-        let result = tokio::time::timeout(Duration::from_secs(timeout_secs), async {
-            match self.config.transport_type {
-                TransportType::Stdio => self.connect_stdio().await,
-                TransportType::Sse => self.connect_sse().await,
-                TransportType::StreamableHttp => self.connect_http().await,
+    /// Whether the connection was torn down for inactivity (and should be
+    /// revived on demand rather than by the health loop)
+    pub async fn is_idle_disconnected(&self) -> bool {
+        *self.idle_disconnected.lock().await
+    }
+
+    /// Disconnect because of inactivity, keeping the MCP enabled
+    pub async fn disconnect_idle(&self) {
+        self.disconnect().await;
+        *self.idle_disconnected.lock().await = true;
+    }
+
+    /// Whether the user has asked to connect this MCP since it was created
+    pub fn is_user_requested(&self) -> bool {
+        self.user_requested.load(Ordering::SeqCst)
+    }
+
+    /// Number of requests currently in flight
+    pub fn in_flight_requests(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Count one proxied message of `bytes_in` bytes answered with
+    /// `bytes_out` bytes (0 for notifications)
+    pub fn record_traffic(&self, bytes_in: u64, bytes_out: u64) {
+        self.traffic.bytes_in.fetch_add(bytes_in, Ordering::Relaxed);
+        self.traffic.bytes_out.fetch_add(bytes_out, Ordering::Relaxed);
+        self.traffic.request_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Traffic proxied so far
+    pub fn traffic_stats(&self) -> TrafficStats {
+        TrafficStats {
+            mcp_id: self.config.id.clone(),
+            bytes_in: self.traffic.bytes_in.load(Ordering::Relaxed),
+            bytes_out: self.traffic.bytes_out.load(Ordering::Relaxed),
+            request_count: self.traffic.request_count.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Whether a Connected connection has been up for at least `lifetime_secs`
+    pub async fn exceeded_lifetime(&self, lifetime_secs: u64) -> bool {
+        if self.get_state().await != ConnectionState::Connected {
+            return false;
+        }
+        let connected_at = *self.connected_at.lock().await;
+        connected_at
+            .and_then(|t| SystemTime::now().duration_since(t).ok())
+            .is_some_and(|uptime| uptime >= Duration::from_secs(lifetime_secs))
+    }
+
+    /// Planned reconnect after the max lifetime; unlike a failure-driven
+    /// reconnect this does not count towards `reconnect_attempts`
+    pub async fn recycle(&self) -> Result<()> {
+        self.disconnect().await;
+        self.connect(ReconnectReason::LifetimeRecycle).await
+    }
+
+    /// `initialize` params sent upstream, carrying the configured client
+    /// identity and pinned protocol version
+    fn client_info(&self) -> ClientInfo {
+        let identity = self
+            .config
+            .client_info
+            .clone()
+            .unwrap_or_else(default_client_identity);
+        let mut info = ClientInfo {
+            client_info: Implementation {
+                name: identity.name,
+                version: identity.version,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        if let Some(version) = &self.config.upstream_protocol_version {
+            match serde_json::from_value(serde_json::Value::String(version.clone())) {
+                Ok(version) => info.protocol_version = version,
+                Err(e) => tracing::warn!(
+                    "MCP '{}': ignoring protocol version '{}': {}",
+                    self.config.name,
+                    version,
+                    e
+                ),
             }
-        })
+        }
+        info
+    }
+
+    /// Handler for upstream -> client requests, served on every transport
+    async fn client_handler(&self) -> ProxyClientHandler {
+        ProxyClientHandler::new(
+            self.client_info(),
+            Arc::clone(&self.sampling),
+            Arc::clone(&self.roots),
+            self.progress.clone(),
+        )
         .await
-        .unwrap_or_else(|_| Err(anyhow!(
-            "Connection to {} timed out after {} seconds (server reachable but MCP handshake did not complete)",
-            target,
-            timeout_secs
-        )));
+    }
+
+    /// Replace the roots served to the upstream and tell it they changed
+    pub async fn set_roots(&self, roots: Vec<String>) {
+        *self.roots.lock().await = roots;
+        if let Some(service) = self.service.lock().await.as_ref() {
+            if let Err(e) = service.notify_roots_list_changed().await {
+                tracing::warn!(
+                    "MCP '{}': failed to send roots list_changed: {}",
+                    self.config.name,
+                    e
+                );
+            }
+        }
+    }
+
+    /// Primary upstream (command for stdio, URL otherwise) followed by the
+    /// configured fallbacks
+    fn upstreams(&self) -> Vec<String> {
+        let primary = match self.config.transport_type {
+            TransportType::Stdio => self.config.command.clone(),
+            TransportType::Sse | TransportType::StreamableHttp | TransportType::Auto => {
+                self.config.url.clone()
+            }
+        };
+        std::iter::once(primary.unwrap_or_default())
+            .chain(self.config.fallback_upstreams.iter().cloned())
+            .collect()
+    }
+
+    /// Attempt to connect to the MCP server
+    pub async fn connect(&self, reason: ReconnectReason) -> Result<()> {
+        let _guard = self.connect_lock.lock().await;
+        self.connect_locked(reason).await
+    }
+
+    /// Connect unless already connected.  Callers racing on a disconnected
+    /// MCP wait for a single in-flight handshake instead of starting their own.
+    pub async fn ensure_connected(&self) -> Result<()> {
+        let _guard = self.connect_lock.lock().await;
+        if self.get_state().await == ConnectionState::Connected {
+            return Ok(());
+        }
+        let reason = if self.is_idle_disconnected().await {
+            ReconnectReason::IdleRevival
+        } else {
+            ReconnectReason::OnDemand
+        };
+        self.connect_locked(reason).await
+    }
+
+    async fn connect_locked(&self, reason: ReconnectReason) -> Result<()> {
+        tracing::info!("MCP '{}': connecting ({:?})", self.config.name, reason);
+        self.record_event(ConnectionEventKind::ConnectAttempt, Some(reason), None)
+            .await;
+        if reason.counts_as_failure() {
+            self.increment_reconnect_attempts().await;
+        }
+        if reason == ReconnectReason::UserRequested {
+            self.user_requested.store(true, Ordering::SeqCst);
+        }
+        if reason != ReconnectReason::Initial {
+            // From here on the outcome of this attempt is shown as is
+            *self.settling_until.lock().await = None;
+        }
+        *self.idle_disconnected.lock().await = false;
+        self.set_state(ConnectionState::Connecting).await;
+        self.connect_from(0).await
+    }
+
+    /// Connect starting at the given upstream index, fetch capabilities and
+    /// update the state to reflect the outcome
+    async fn connect_from(&self, start: usize) -> Result<()> {
+        // Bound how many handshakes (child processes, HTTP clients) run at once
+        let _permit = self.connect_permits.acquire().await;
 
-        match result {
+        match self.connect_upstreams(start).await {
             Ok(()) => {
                 // Fetch capabilities after connecting
                 if let Err(e) = self.fetch_capabilities().await {
@@ -228,6 +802,8 @@ impl McpConnection {
                     );
                 }
                 self.set_state(ConnectionState::Connected).await;
+                self.record_event(ConnectionEventKind::Connected, None, None)
+                    .await;
                 Ok(())
             }
             Err(e) => {
@@ -237,22 +813,77 @@ impl McpConnection {
                     self.config.name,
                     detailed
                 );
-                self.set_error(detailed).await;
+                self.record_event(ConnectionEventKind::Failed, None, Some(detailed.clone()))
+                    .await;
+                self.set_error(detailed, Some(classify_connect_error(&e))).await;
                 self.set_state(ConnectionState::Error).await;
                 Err(e)
             }
         }
     }
 
-    /// Connect via stdio (child process)
-    async fn connect_stdio(&self) -> Result<()> {
-        let command_str = self
-            .config
-            .command
-            .as_ref()
-            .ok_or_else(|| anyhow!("No command specified for stdio transport"))?
-            .trim();
+    /// Try each upstream in turn, beginning at `start` and wrapping around,
+    /// and make the first one that completes the handshake active.
+    async fn connect_upstreams(&self, start: usize) -> Result<()> {
+        let upstreams = self.upstreams();
+        // Wrap each connect in an overall timeout so we don't block forever
+        // if the server never completes the MCP handshake.
+        let timeout_secs = *self.connection_timeout_secs.lock().await;
+        let mut last_error = None;
+
+        for offset in 0..upstreams.len() {
+            let index = (start + offset) % upstreams.len();
+            let target = upstreams[index].trim();
+            let display_target = if target.is_empty() { "unknown" } else { target };
+
+            let result = tokio::time::timeout(Duration::from_secs(timeout_secs), async {
+                match self.config.transport_type {
+                    TransportType::Stdio => self.connect_stdio(target).await,
+                    TransportType::Sse => self.connect_sse(target).await,
+                    TransportType::StreamableHttp => self.connect_http(target).await,
+                    TransportType::Auto => self.connect_auto(target).await,
+                }
+            })
+            .await
+            .unwrap_or_else(|elapsed| Err(anyhow::Error::new(elapsed).context(format!(
+                "Connection to {} timed out after {} seconds (server reachable but MCP handshake did not complete)",
+                display_target,
+                timeout_secs
+            ))));
+
+            match result {
+                Ok(()) => {
+                    if index > 0 {
+                        tracing::info!(
+                            "MCP '{}': using fallback upstream {} ({})",
+                            self.config.name,
+                            index,
+                            display_target
+                        );
+                    }
+                    *self.active_upstream.lock().await = index;
+                    return Ok(());
+                }
+                Err(e) => {
+                    if upstreams.len() > 1 {
+                        tracing::warn!(
+                            "MCP '{}': upstream {} ({}) failed: {:#}",
+                            self.config.name,
+                            index,
+                            display_target,
+                            e
+                        );
+                    }
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow!("No upstream configured")))
+    }
 
+    /// Connect via stdio (child process)
+    async fn connect_stdio(&self, command_str: &str) -> Result<()> {
         if command_str.is_empty() {
             return Err(anyhow!("No command specified for stdio transport"));
         }
@@ -270,7 +901,10 @@ impl McpConnection {
             (command_str.to_string(), Vec::new())
         };
 
-        let mut args = self.config.args.clone().unwrap_or_default();
+        let mut args = match &self.config.args_raw {
+            Some(raw) => split_shell_words(raw).map_err(|e| anyhow!("Invalid args_raw: {}", e))?,
+            None => self.config.args.clone().unwrap_or_default(),
+        };
         args.splice(0..0, extra_args); // prepend extra_args to existing args
 
         // Build the command
@@ -280,6 +914,16 @@ impl McpConnection {
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
 
+        // With an allowlist, only the listed variables are inherited from
+        // the app's environment
+        if let Some(allowlist) = self.env_allowlist.lock().await.as_ref() {
+            cmd.env_clear().envs(allowlisted_env(
+                allowlist,
+                &self.config.inherit_env,
+                |name| std::env::var_os(name),
+            ));
+        }
+
         // Set environment variables if provided
         if let Some(env) = &self.config.env {
             for (key, value) in env {
@@ -290,35 +934,143 @@ impl McpConnection {
         let full_cmd = format!("{} {}", executable, args.join(" "))
             .trim_end()
             .to_string();
-        let transport = TokioChildProcess::new(cmd)
-            .map_err(|e| {
-                anyhow!(
-                    "Failed to spawn MCP server process (command: {}): {}",
-                    full_cmd,
-                    e
-                )
-            })?;
+        self.recent_stderr.lock().await.clear();
+        cmd.kill_on_drop(true);
+        let mut child = cmd.spawn().map_err(|e| {
+            anyhow::Error::new(e).context(format!(
+                "Failed to spawn MCP server process (command: {})",
+                full_cmd
+            ))
+        })?;
 
-        let service = ().serve(transport)
-            .await
-            .context("Failed to initialize MCP client service")?;
+        let stdout = child.stdout.take().context("Child stdout was not piped")?;
+        let stdin = child.stdin.take().context("Child stdin was not piped")?;
+        if let Some(stderr) = child.stderr.take() {
+            self.spawn_stderr_reader(stderr);
+        }
+        *self.child.lock().await = Some(child);
+        *self.child_command.lock().await = Some(full_cmd);
+
+        let service = match self.client_handler().await.serve((stdout, stdin)).await {
+            Ok(service) => service,
+            Err(e) => {
+                self.stop_child().await;
+                return Err(e).context("Failed to initialize MCP client service");
+            }
+        };
 
         *self.service.lock().await = Some(service);
         Ok(())
     }
 
+    /// Drain the child's stderr into the bounded `recent_stderr` buffer
+    fn spawn_stderr_reader(&self, stderr: tokio::process::ChildStderr) {
+        let buffer = Arc::clone(&self.recent_stderr);
+        let name = self.config.name.clone();
+        let strip = self.config.strip_ansi.unwrap_or(true);
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(stderr);
+            let mut raw = Vec::new();
+            loop {
+                raw.clear();
+                match reader.read_until(b'\n', &mut raw).await {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        let mut line = String::from_utf8_lossy(&raw).trim_end().to_string();
+                        if strip {
+                            line = strip_ansi(&line);
+                        }
+                        tracing::debug!("MCP '{}' stderr: {}", name, line);
+                        let mut lines = buffer.lock().await;
+                        if lines.len() >= STDERR_BUFFER_LINES {
+                            lines.pop_front();
+                        }
+                        lines.push_back(line);
+                    }
+                    Err(e) => {
+                        tracing::debug!("MCP '{}': stderr read failed: {}", name, e);
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
+    /// Connect with the transport previously detected for `url`, or probe:
+    /// streamable HTTP first, then legacy SSE.  A remembered transport that
+    /// stops working is forgotten and the probe runs again.
+    async fn connect_auto(&self, url: &str) -> Result<()> {
+        let known = self.detected_transports.lock().await.get(url).cloned();
+        if let Some(transport) = known {
+            let result = match transport {
+                TransportType::Sse => self.connect_sse(url).await,
+                _ => self.connect_http(url).await,
+            };
+            if result.is_ok() {
+                return result;
+            }
+            tracing::info!(
+                "MCP '{}': detected transport for {} failed, probing again",
+                self.config.name,
+                url
+            );
+            self.detected_transports.lock().await.remove(url);
+        }
+
+        let (transport, result) = match self.connect_http(url).await {
+            Ok(()) => (TransportType::StreamableHttp, Ok(())),
+            Err(http_err) => {
+                tracing::debug!(
+                    "MCP '{}': streamable HTTP failed for {} ({:#}), trying legacy SSE",
+                    self.config.name,
+                    url,
+                    http_err
+                );
+                // The SSE error stays the source, with its causes intact
+                let result = self.connect_sse(url).await.map_err(|sse_err| {
+                    sse_err.context(format!(
+                        "Auto-detect failed: streamable HTTP: {:#}; legacy SSE",
+                        http_err
+                    ))
+                });
+                (TransportType::Sse, result)
+            }
+        };
+        if result.is_ok() {
+            tracing::info!(
+                "MCP '{}': detected {:?} transport for {}",
+                self.config.name,
+                transport,
+                url
+            );
+            self.detected_transports
+                .lock()
+                .await
+                .insert(url.to_string(), transport);
+        }
+        result
+    }
+
+    /// Transport auto-detection settled on for the active upstream
+    async fn resolved_transport(&self) -> Option<TransportType> {
+        if self.config.transport_type != TransportType::Auto {
+            return None;
+        }
+        let index = *self.active_upstream.lock().await;
+        let url = self.upstreams().get(index)?.trim().to_string();
+        self.detected_transports.lock().await.get(&url).cloned()
+    }
+
     /// Connect via legacy SSE transport (GET /sse + POST /messages)
-    async fn connect_sse(&self) -> Result<()> {
-        let url = self
-            .config
-            .url
-            .as_ref()
-            .ok_or_else(|| anyhow!("No URL specified for SSE transport"))?;
+    async fn connect_sse(&self, url: &str) -> Result<()> {
+        if url.is_empty() {
+            return Err(anyhow!("No URL specified for SSE transport"));
+        }
 
         // Quick reachability probe — a simple GET to the SSE endpoint.
         let client = self.build_http_client()?;
-        match client.get(url.as_str()).send().await {
-            Err(e) => return Err(anyhow!("Cannot reach {}: {}", url, e)),
+        match client.get(url).send().await {
+            Err(e) => return Err(anyhow::Error::new(e).context(format!("Cannot reach {}", url))),
             Ok(resp) if resp.status().is_server_error() => {
                 let status = resp.status();
                 return Err(anyhow!(
@@ -341,21 +1093,18 @@ impl McpConnection {
         use crate::mcp::legacy_sse::LegacySseWorker;
         use rmcp::transport::worker::WorkerTransport;
 
-        let mut worker = LegacySseWorker::from_url(url.as_str())
-            .map_err(|e| anyhow!("Invalid SSE URL: {}", e))?;
-
-        // Pass custom headers from config (e.g. Authorization)
-        if let Some(headers) = &self.config.headers {
-            let header_vec: Vec<(String, String)> = headers
-                .iter()
-                .map(|(k, v)| (k.clone(), v.clone()))
-                .collect();
-            worker = worker.with_headers(header_vec);
-        }
+        // Reuse the probe client so the SSE stream and message POSTs share
+        // one connection pool and carry the configured headers
+        let alive = Arc::new(AtomicBool::new(false));
+        let worker = LegacySseWorker::from_url(url)
+            .map_err(|e| anyhow!("Invalid SSE URL: {}", e))?
+            .with_client(client)
+            .with_liveness(Arc::clone(&alive));
+        *self.sse_alive.lock().await = Some(alive);
 
         let transport = WorkerTransport::spawn(worker);
 
-        let service = ().serve(transport)
+        let service = self.client_handler().await.serve(transport)
             .await
             .context(format!("MCP handshake failed with {}", url))?;
 
@@ -365,9 +1114,36 @@ impl McpConnection {
 
     /// Build a reqwest client with configured headers and timeouts
     fn build_http_client(&self) -> Result<reqwest::Client> {
+        let (tcp_keepalive, http2_keep_alive_interval) = keepalive_settings(&self.config);
+
+        let (connect_timeout, pool_idle_timeout) = http_timeouts(&self.config);
+
         let mut client_builder = reqwest::Client::builder()
-            .connect_timeout(Duration::from_secs(10))
-            .pool_idle_timeout(Duration::from_secs(90));
+            .connect_timeout(connect_timeout)
+            .pool_idle_timeout(pool_idle_timeout)
+            .tcp_keepalive(tcp_keepalive)
+            .http2_keep_alive_interval(http2_keep_alive_interval)
+            .http2_keep_alive_while_idle(http2_keep_alive_interval.is_some())
+            .http2_adaptive_window(self.config.http2_adaptive_window.unwrap_or(true));
+
+        // Covers both streamable HTTP and the legacy SSE worker, which
+        // reuses this client
+        let decompression = self.config.http_decompression.unwrap_or(true);
+        client_builder = client_builder
+            .gzip(decompression)
+            .brotli(decompression);
+
+        if let Some(max_idle) = self.config.http_pool_max_idle_per_host {
+            client_builder = client_builder.pool_max_idle_per_host(max_idle);
+        }
+
+        // The port is taken from the URL; reqwest ignores the one given here
+        if let Some(host_override) = &self.config.host_override {
+            client_builder = client_builder.resolve(
+                &host_override.host,
+                std::net::SocketAddr::new(host_override.ip, 0),
+            );
+        }
 
         // Apply custom headers from config (e.g. Authorization, cookies, etc.)
         if let Some(headers) = &self.config.headers {
@@ -391,12 +1167,10 @@ impl McpConnection {
     }
 
     /// Connect via Streamable HTTP
-    async fn connect_http(&self) -> Result<()> {
-        let url = self
-            .config
-            .url
-            .as_ref()
-            .ok_or_else(|| anyhow!("No URL specified for HTTP transport"))?;
+    async fn connect_http(&self, url: &str) -> Result<()> {
+        if url.is_empty() {
+            return Err(anyhow!("No URL specified for HTTP transport"));
+        }
 
         let client = self.build_http_client()?;
 
@@ -405,17 +1179,18 @@ impl McpConnection {
         // ("connection refused", "404 Not Found", etc.) instead of a vague
         // timeout 30 seconds later.
         let probe = client
-            .post(url.as_str())
+            .post(url)
             .header("Content-Type", "application/json")
             .header("Accept", "application/json, text/event-stream")
             .body("{\"jsonrpc\":\"2.0\",\"method\":\"ping\",\"id\":0}")
             .send()
             .await;
 
-        match &probe {
+        match probe {
             Err(e) => {
-                // Connection-level failure (refused, DNS, TLS, etc.)
-                return Err(anyhow!("Cannot reach {}: {}", url, e));
+                // Connection-level failure (refused, DNS, TLS, etc.); the
+                // source chain is kept for `classify_connect_error`
+                return Err(anyhow::Error::new(e).context(format!("Cannot reach {}", url)));
             }
             Ok(resp) => {
                 let status = resp.status();
@@ -445,10 +1220,12 @@ impl McpConnection {
         // consumed its connection pool state).
         let client = self.build_http_client()?;
 
-        let config = StreamableHttpClientTransportConfig::with_uri(url.as_str());
-        let transport = StreamableHttpClientTransport::with_client(GracefulHttpClient(client), config);
+        let config = StreamableHttpClientTransportConfig::with_uri(url);
+        let signer = self.config.request_signing.as_ref().map(|s| Arc::new(RequestSigner::new(s)));
+        let transport =
+            StreamableHttpClientTransport::with_client(GracefulHttpClient(client, signer), config);
 
-        let service = ().serve(transport)
+        let service = self.client_handler().await.serve(transport)
             .await
             .context(format!("MCP handshake failed with {}", url))?;
 
@@ -469,11 +1246,20 @@ impl McpConnection {
                 let tools: Vec<Tool> = result
                     .tools
                     .into_iter()
-                    .map(|t| Tool {
-                        name: t.name.to_string(),
-                        description: t.description.map(|d| d.to_string()),
-                        input_schema: serde_json::to_value(&t.input_schema)
-                            .unwrap_or(serde_json::Value::Object(Default::default())),
+                    .map(|t| {
+                        Tool::new(
+                            t.name.to_string(),
+                            t.description.map(|d| d.to_string()),
+                            serde_json::to_value(&t.input_schema)
+                                .unwrap_or(serde_json::Value::Object(Default::default())),
+                            t.annotations.map(|a| ToolAnnotations {
+                                title: a.title,
+                                read_only_hint: a.read_only_hint,
+                                destructive_hint: a.destructive_hint,
+                                idempotent_hint: a.idempotent_hint,
+                                open_world_hint: a.open_world_hint,
+                            }),
+                        )
                     })
                     .collect();
 
@@ -523,21 +1309,84 @@ impl McpConnection {
             }
         }
 
+        *self.capabilities_fetched_at.lock().await = Some(SystemTime::now());
         Ok(())
     }
 
-    /// Ping the server for health check
+    /// Whether `capability_refresh_interval_secs` has passed since tools
+    /// and resources were last listed
+    pub async fn capability_refresh_due(&self) -> bool {
+        let interval = match self.config.capability_refresh_interval_secs {
+            Some(secs) if secs > 0 => Duration::from_secs(secs),
+            _ => return false,
+        };
+        if self.get_state().await != ConnectionState::Connected {
+            return false;
+        }
+        let fetched_at = *self.capabilities_fetched_at.lock().await;
+        fetched_at
+            .and_then(|t| SystemTime::now().duration_since(t).ok())
+            .is_none_or(|elapsed| elapsed >= interval)
+    }
+
+    /// Periodic re-list of tools and resources.  Skipped when another
+    /// refresh is running or a fetch happened within the interval anyway
+    /// (e.g. on connect or a `find_tool` miss).
+    pub async fn refresh_capabilities(&self) -> Result<()> {
+        if self.refreshing_capabilities.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let result = if self.capability_refresh_due().await {
+            tracing::debug!("MCP '{}': periodic capability refresh", self.config.name);
+            self.fetch_capabilities().await
+        } else {
+            Ok(())
+        };
+        self.refreshing_capabilities.store(false, Ordering::SeqCst);
+        result
+    }
+
+    /// Ping the server for health check.  Legacy SSE connections are
+    /// judged by their stream alone; everything else gets a protocol `ping`.
     pub async fn ping(&self) -> Result<()> {
+        // A crashed stdio child can leave the transport hanging rather than
+        // erroring, so check the process itself first
+        if let Some(status) = self.child_exit_status().await {
+            let msg = format!("MCP server process exited ({})", status);
+            if let Some(service) = self.service.lock().await.take() {
+                let _ = service.cancel().await;
+            }
+            self.record_event(ConnectionEventKind::Failed, None, Some(msg.clone()))
+                .await;
+            self.set_error(msg.clone(), None).await;
+            self.set_state(ConnectionState::Error).await;
+            return Err(anyhow!(msg));
+        }
+
         let service_lock = self.service.lock().await;
         let service = service_lock
             .as_ref()
             .ok_or_else(|| anyhow!("Not connected"))?;
 
-        // Use list_tools as a lightweight health check (no dedicated ping in rmcp)
-        let _ = service
-            .list_tools(Default::default())
+        let transport = self
+            .resolved_transport()
             .await
-            .context("Health check failed")?;
+            .unwrap_or_else(|| self.config.transport_type.clone());
+        if transport == TransportType::Sse {
+            // Every response arrives on the SSE stream, so while its reader
+            // runs the transport is up
+            let alive = self
+                .sse_alive
+                .lock()
+                .await
+                .as_ref()
+                .is_some_and(|alive| alive.load(Ordering::SeqCst));
+            if !alive {
+                return Err(anyhow!("Health check failed: SSE stream closed"));
+            }
+        } else {
+            send_ping(service).await.context("Health check failed")?;
+        }
 
         *self.last_ping.lock().await = Some(SystemTime::now());
         Ok(())
@@ -550,17 +1399,102 @@ impl McpConnection {
         }
         *self.tools.lock().await = Vec::new();
         *self.resources.lock().await = Vec::new();
+        *self.idle_disconnected.lock().await = false;
+        self.stop_child().await;
+        self.set_state(ConnectionState::Disconnected).await;
+        self.record_event(ConnectionEventKind::Disconnected, None, None)
+            .await;
+    }
+
+    /// Kill the stdio child outright and mark the connection Disconnected.
+    /// Used when a graceful disconnect does not finish in time, or to get
+    /// rid of a stuck child; `detail` goes into the event log.
+    pub async fn force_kill(&self, detail: &str) {
+        if let Some(mut child) = self.child.lock().await.take() {
+            tracing::warn!(
+                "MCP '{}': force-killing process {:?}",
+                self.config.name,
+                child.id()
+            );
+            let _ = child.start_kill();
+        }
         self.set_state(ConnectionState::Disconnected).await;
+        self.record_event(
+            ConnectionEventKind::Disconnected,
+            None,
+            Some(detail.to_string()),
+        )
+        .await;
+    }
+
+    /// PID and command line of the stdio child; `None` for other transports
+    pub async fn spawned_process(&self) -> Option<SpawnedProcess> {
+        if self.config.transport_type != TransportType::Stdio {
+            return None;
+        }
+        let mut child = self.child.lock().await;
+        let (pid, alive) = match child.as_mut() {
+            Some(child) => (child.id(), matches!(child.try_wait(), Ok(None))),
+            None => (None, false),
+        };
+        Some(SpawnedProcess {
+            mcp_id: self.config.id.clone(),
+            pid,
+            command: self.child_command.lock().await.clone(),
+            alive,
+        })
+    }
+
+    /// Exit status of the stdio child, if it has exited
+    async fn child_exit_status(&self) -> Option<std::process::ExitStatus> {
+        let mut child = self.child.lock().await;
+        match child.as_mut()?.try_wait() {
+            Ok(Some(status)) => {
+                *child = None;
+                Some(status)
+            }
+            _ => None,
+        }
+    }
+
+    /// Give the stdio child a moment to exit on its own (its stdin is closed
+    /// once the service stops), then kill it
+    async fn stop_child(&self) {
+        let Some(mut child) = self.child.lock().await.take() else {
+            return;
+        };
+        let grace = Duration::from_secs(CHILD_EXIT_GRACE_SECS);
+        if tokio::time::timeout(grace, child.wait()).await.is_err() {
+            if let Err(e) = child.kill().await {
+                tracing::warn!("MCP '{}': failed to kill process: {}", self.config.name, e);
+            }
+        }
     }
 
-    /// Get current status snapshot
-    pub async fn status(&self, proxy_port: u16) -> McpStatus {
-        let state = *self.state.lock().await;
-        let tools_count = self.tools.lock().await.len();
-        let resources_count = self.resources.lock().await.len();
+    /// Current status.  Disabled counts only include cached tools/resources,
+    /// so stale entries for items the server no longer reports are ignored.
+    /// `proxy_url` is left unset; the manager fills it in (see `proxy_url`).
+    pub async fn status(&self, disabled: &DisabledItems) -> McpStatus {
+        let settling = self.is_settling().await;
+        let state = self.get_state().await;
+        let (tools_count, disabled_tools_count) = {
+            let tools = self.tools.lock().await;
+            let is_enabled = disabled.tool_filter();
+            let disabled_count = tools.iter().filter(|t| !is_enabled(&t.name)).count();
+            (tools.len(), disabled_count)
+        };
+        let (resources_count, disabled_resources_count) = {
+            let resources = self.resources.lock().await;
+            let is_enabled = disabled.resource_filter();
+            let disabled_count = resources.iter().filter(|r| !is_enabled(&r.uri)).count();
+            (resources.len(), disabled_count)
+        };
         let connected_at = *self.connected_at.lock().await;
         let last_ping = *self.last_ping.lock().await;
-        let error_message = self.error_message.lock().await.clone();
+        let error_message = self.error_message.lock().await.clone().filter(|_| !settling);
+        let error_kind = self.error_kind.lock().await.filter(|_| !settling);
+        let active_upstream = *self.active_upstream.lock().await;
+        let resolved_transport = self.resolved_transport().await;
 
         let uptime_seconds = connected_at.and_then(|t| {
             SystemTime::now()
@@ -569,27 +1503,26 @@ impl McpConnection {
                 .map(|d| d.as_secs())
         });
 
-        let proxy_url = if state == ConnectionState::Connected {
-            Some(format!(
-                "http://127.0.0.1:{}/mcp/{}",
-                proxy_port, self.config.id
-            ))
-        } else {
-            None
-        };
-
         McpStatus {
             id: self.config.id.clone(),
             name: self.config.name.clone(),
             state,
             transport_type: self.config.transport_type.clone(),
+            color: self.config.color.clone(),
+            short_label: self.config.short_label.clone(),
+            resolved_transport,
             connected_at: connected_at.map(format_system_time),
             last_ping: last_ping.map(format_system_time),
             error_message,
+            error_kind,
             tools_count,
             resources_count,
+            disabled_tools_count,
+            disabled_resources_count,
             uptime_seconds,
-            proxy_url,
+            proxy_url: None,
+            active_upstream,
+            in_flight_requests: self.in_flight_requests(),
         }
     }
 
@@ -598,112 +1531,1583 @@ impl McpConnection {
         self.tools.lock().await.clone()
     }
 
-    /// Get cached resources
-    pub async fn get_resources(&self) -> Vec<Resource> {
-        self.resources.lock().await.clone()
+    /// Look up a tool by its upstream name, re-listing tools once if it is
+    /// not in the cache
+    pub async fn find_tool(&self, name: &str) -> Result<Option<Tool>> {
+        if let Some(tool) = self.tools.lock().await.iter().find(|t| t.name == name) {
+            return Ok(Some(tool.clone()));
+        }
+        self.fetch_capabilities().await?;
+        Ok(self.tools.lock().await.iter().find(|t| t.name == name).cloned())
+    }
+
+    /// The upstream's raw `initialize` result (protocolVersion,
+    /// capabilities, serverInfo), if connected
+    pub async fn server_info(&self) -> Option<serde_json::Value> {
+        let service = self.service.lock().await;
+        let info = service.as_ref()?.peer_info()?;
+        serde_json::to_value(info).ok()
+    }
+
+    /// Configured tool aliases, resolved against the cached tool list
+    pub async fn tool_aliases(&self) -> ToolAliases {
+        let tools = self.tools.lock().await;
+        ToolAliases::new(
+            &self.config.tool_aliases,
+            tools.iter().map(|t| t.name.as_str()),
+        )
+    }
+
+    /// Get cached resources
+    pub async fn get_resources(&self) -> Vec<Resource> {
+        self.resources.lock().await.clone()
+    }
+
+    /// Get the most recent stderr lines of a stdio server, oldest first
+    pub async fn get_recent_stderr(&self) -> Vec<String> {
+        self.recent_stderr.lock().await.iter().cloned().collect()
+    }
+
+    /// Get the recorded connection lifecycle events, oldest first
+    pub async fn get_events(&self) -> Vec<ConnectionEvent> {
+        self.events.lock().await.iter().cloned().collect()
+    }
+
+    /// Append a lifecycle event, dropping the oldest beyond the limit
+    async fn record_event(
+        &self,
+        kind: ConnectionEventKind,
+        reason: Option<ReconnectReason>,
+        detail: Option<String>,
+    ) {
+        let mut events = self.events.lock().await;
+        if events.len() >= CONNECTION_EVENT_LIMIT {
+            events.pop_front();
+        }
+        events.push_back(ConnectionEvent {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            kind,
+            reason,
+            detail,
+        });
+    }
+
+    /// Whether proxied requests are recorded for debugging
+    pub fn records_requests(&self) -> bool {
+        self.config.record_requests.unwrap_or(false)
+    }
+
+    /// Recorded requests, oldest first
+    pub async fn get_recorded_requests(&self) -> Vec<RecordedRequest> {
+        self.recorded_requests.lock().await.iter().cloned().collect()
+    }
+
+    /// Append a proxied exchange with secrets redacted, dropping the oldest
+    /// beyond the limit
+    pub async fn record_request(
+        &self,
+        method: &str,
+        request: &serde_json::Value,
+        response: Option<&serde_json::Value>,
+        duration: Duration,
+    ) {
+        let mut request = request.clone();
+        redact_json(&mut request);
+        let response = response.cloned().map(|mut r| {
+            redact_json(&mut r);
+            r
+        });
+
+        let mut recorded = self.recorded_requests.lock().await;
+        if recorded.len() >= RECORDED_REQUEST_LIMIT {
+            recorded.pop_front();
+        }
+        recorded.push_back(RecordedRequest {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            method: method.to_string(),
+            request,
+            response,
+            duration_ms: duration.as_millis() as u64,
+        });
     }
 
     /// Execute a JSON-RPC method against the underlying MCP server.
     /// Returns the `result` value on success (not the full JSON-RPC envelope).
+    /// Transient failures are retried up to `max_retries` times for methods
+    /// that are safe to repeat.
     pub async fn execute_request(
         &self,
         method: &str,
         params: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        self.execute_request_with_progress(method, params, None, &ForwardedHeaders::default())
+            .await
+    }
+
+    /// Like `execute_request`, but progress notifications the upstream sends
+    /// for a `tools/call` are forwarded to `progress`, and `forwarded`
+    /// headers are added to the upstream HTTP request
+    pub async fn execute_request_with_progress(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+        progress: Option<&ProgressSender>,
+        forwarded: &ForwardedHeaders,
+    ) -> Result<serde_json::Value> {
+        let _permit = match &self.request_permits {
+            Some(permits) => Some(
+                permits
+                    .try_acquire()
+                    .map_err(|_| TooManyRequests(self.in_flight_requests()))?,
+            ),
+            None => None,
+        };
+        *self.last_activity.lock().await = Some(SystemTime::now());
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+
+        let max_retries = if self.is_retry_safe(method) {
+            self.config.max_retries.unwrap_or(0)
+        } else {
+            0
+        };
+        let mut attempt = 0;
+        loop {
+            match self
+                .execute_with_failover(method, params.clone(), progress, forwarded)
+                .await
+            {
+                Err(e) if attempt < max_retries && is_transient_error(&e) => {
+                    attempt += 1;
+                    tracing::warn!(
+                        "MCP '{}': {} failed ({:#}), retry {}/{}",
+                        self.config.name,
+                        method,
+                        e,
+                        attempt,
+                        max_retries
+                    );
+                    let backoff = RETRY_BACKOFF_MS << (attempt - 1).min(10);
+                    tokio::time::sleep(Duration::from_millis(backoff)).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Whether `method` may be sent again after a failure
+    fn is_retry_safe(&self, method: &str) -> bool {
+        RETRY_SAFE_METHODS.contains(&method)
+            || (method == "tools/call" && self.config.idempotent.unwrap_or(false))
+    }
+
+    /// On a transport error the next upstream (if any) is made active.  The
+    /// request is retried once against it only when it is safe to repeat;
+    /// otherwise it may already have run, so the error is returned.
+    async fn execute_with_failover(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+        progress: Option<&ProgressSender>,
+        forwarded: &ForwardedHeaders,
+    ) -> Result<serde_json::Value> {
+        let upstream_count = self.upstreams().len();
+        let used = *self.active_upstream.lock().await;
+        match self
+            .execute_on_active(method, params.clone(), progress, forwarded)
+            .await
+        {
+            Err(e) if upstream_count > 1 && is_transport_error(&e) => {
+                let reconnected = self.fail_over_from(used, upstream_count, &e).await;
+                if !self.is_retry_safe(method) {
+                    return Err(e);
+                }
+                reconnected?;
+                self.execute_on_active(method, params, progress, forwarded)
+                    .await
+            }
+            result => result,
+        }
+    }
+
+    /// Make the upstream after `failed` active, unless a concurrent caller
+    /// already moved off it while we waited for the connect lock
+    async fn fail_over_from(
+        &self,
+        failed: usize,
+        upstream_count: usize,
+        error: &anyhow::Error,
+    ) -> Result<()> {
+        let _guard = self.connect_lock.lock().await;
+        if *self.active_upstream.lock().await != failed
+            && self.get_state().await == ConnectionState::Connected
+        {
+            return Ok(());
+        }
+        let next = (failed + 1) % upstream_count;
+        tracing::warn!(
+            "MCP '{}': transport error ({:#}), failing over to upstream {}",
+            self.config.name,
+            error,
+            next
+        );
+        if let Some(service) = self.service.lock().await.take() {
+            let _ = service.cancel().await;
+        }
+        self.set_state(ConnectionState::Reconnecting).await;
+        self.connect_from(next).await
+    }
+
+    /// Execute a JSON-RPC method against the currently active upstream
+    async fn execute_on_active(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+        progress: Option<&ProgressSender>,
+        forwarded: &ForwardedHeaders,
     ) -> Result<serde_json::Value> {
         let service_lock = self.service.lock().await;
         let service = service_lock
             .as_ref()
             .ok_or_else(|| anyhow!("Not connected"))?;
 
-        let result = match method {
-            "ping" => {
-                // rmcp doesn't expose a dedicated ping; use list_tools as a lightweight check
-                let _ = service.list_tools(Default::default()).await.context("ping failed")?;
-                serde_json::json!({})
-            }
-            "tools/list" => {
-                let result = service
-                    .list_tools(Default::default())
-                    .await
-                    .context("tools/list failed")?;
-                serde_json::to_value(&result)?
-            }
-            "tools/call" => {
-                let tool_params: CallToolRequestParams = serde_json::from_value(params)
-                    .context("Invalid tools/call params")?;
-                let result = service
-                    .call_tool(tool_params)
+        if method == "ping" {
+            send_ping(service).await.context("ping failed")?;
+            return Ok(serde_json::json!({}));
+        }
+
+        let result = match (client_request(method, params)?, progress) {
+            (ClientRequest::CallToolRequest(call), Some(progress)) => {
+                let result = self
+                    .call_tool_with_progress(service, call.params, progress, forwarded)
                     .await
                     .context("tools/call failed")?;
                 serde_json::to_value(&result)?
             }
-            "resources/list" => {
+            (mut request, _) => {
+                *request.extensions_mut() = forwarded.to_extensions();
                 let result = service
-                    .list_resources(Default::default())
+                    .send_request(request)
                     .await
-                    .context("resources/list failed")?;
+                    .context(format!("{} failed", method))?;
                 serde_json::to_value(&result)?
             }
-            "resources/read" => {
-                let read_params = serde_json::from_value(params)
-                    .context("Invalid resources/read params")?;
-                let result = service
-                    .read_resource(read_params)
-                    .await
-                    .context("resources/read failed")?;
-                serde_json::to_value(&result)?
+        };
+
+        Ok(result)
+    }
+
+    /// Call a tool, forwarding the upstream's progress notifications for
+    /// this request until the result arrives
+    async fn call_tool_with_progress(
+        &self,
+        service: &RunningService<RoleClient, ProxyClientHandler>,
+        mut params: CallToolRequestParams,
+        progress: &ProgressSender,
+        forwarded: &ForwardedHeaders,
+    ) -> Result<CallToolResult> {
+        use futures::StreamExt;
+
+        // The upstream must report progress against the token rmcp assigns,
+        // or it can't be routed back here.  rmcp only adds its token when the
+        // params carry no `_meta` of their own, so the client's metadata is
+        // passed as request options instead, minus the client's token (the
+        // proxy re-attaches that downstream).
+        let mut meta = params.meta.take().unwrap_or_default();
+        meta.0.remove("progressToken");
+        let options = PeerRequestOptions {
+            meta: Some(meta),
+            ..PeerRequestOptions::no_options()
+        };
+        let request = ClientRequest::CallToolRequest(CallToolRequest {
+            method: Default::default(),
+            params,
+            extensions: forwarded.to_extensions(),
+        });
+        let handle = service
+            .send_cancellable_request(request, options)
+            .await?;
+        let mut updates = self.progress.subscribe(handle.progress_token.clone()).await;
+
+        let response = handle.await_response();
+        tokio::pin!(response);
+        let response = loop {
+            tokio::select! {
+                Some(update) = updates.next() => {
+                    let _ = progress.send(update);
+                }
+                response = &mut response => break response?,
             }
-            "resources/templates/list" => {
-                let result = service
-                    .list_resource_templates(Default::default())
-                    .await
-                    .context("resources/templates/list failed")?;
-                serde_json::to_value(&result)?
+        };
+        match response {
+            ServerResult::CallToolResult(result) => Ok(result),
+            _ => Err(anyhow!("Unexpected response to tools/call")),
+        }
+    }
+}
+
+/// The typed request for `method`, covering every method the proxy sends
+/// upstream on a client's behalf.  List requests always fetch from the start.
+fn client_request(method: &str, params: serde_json::Value) -> Result<ClientRequest> {
+    fn parse<P: serde::de::DeserializeOwned>(
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<P, InvalidParams> {
+        serde_json::from_value(params).map_err(|source| InvalidParams {
+            method: method.to_string(),
+            source,
+        })
+    }
+
+    let request = match method {
+        "tools/list" => ClientRequest::ListToolsRequest(Default::default()),
+        "tools/call" => ClientRequest::CallToolRequest(Request::new(parse(method, params)?)),
+        "resources/list" => ClientRequest::ListResourcesRequest(Default::default()),
+        "resources/read" => {
+            ClientRequest::ReadResourceRequest(Request::new(parse(method, params)?))
+        }
+        "resources/templates/list" => {
+            ClientRequest::ListResourceTemplatesRequest(Default::default())
+        }
+        "prompts/list" => ClientRequest::ListPromptsRequest(Default::default()),
+        "prompts/get" => ClientRequest::GetPromptRequest(Request::new(parse(method, params)?)),
+        "completion/complete" => {
+            ClientRequest::CompleteRequest(Request::new(parse(method, params)?))
+        }
+        "logging/setLevel" => ClientRequest::SetLevelRequest(Request::new(parse(method, params)?)),
+        other => return Err(anyhow!("Method not found: {}", other)),
+    };
+    Ok(request)
+}
+
+/// Send a protocol `ping`, falling back to `tools/list` for servers that
+/// don't implement it
+async fn send_ping(service: &RunningService<RoleClient, ProxyClientHandler>) -> Result<()> {
+    let request = ClientRequest::PingRequest(PingRequest {
+        method: Default::default(),
+        extensions: Default::default(),
+    });
+    match service.send_request(request).await {
+        Ok(_) => Ok(()),
+        Err(rmcp::ServiceError::McpError(e)) if e.code == ErrorCode::METHOD_NOT_FOUND => {
+            service.list_tools(Default::default()).await?;
+            Ok(())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Connect timeout and pooled-connection idle timeout for HTTP upstreams
+fn http_timeouts(config: &McpServerConfig) -> (Duration, Duration) {
+    (
+        Duration::from_secs(
+            config
+                .http_connect_timeout_secs
+                .unwrap_or(DEFAULT_HTTP_CONNECT_TIMEOUT_SECS),
+        ),
+        Duration::from_secs(
+            config
+                .http_pool_idle_timeout_secs
+                .unwrap_or(DEFAULT_HTTP_POOL_IDLE_TIMEOUT_SECS),
+        ),
+    )
+}
+
+/// TCP keepalive and HTTP/2 keepalive ping interval for HTTP upstreams,
+/// `None` where configured as 0 (disabled)
+fn keepalive_settings(config: &McpServerConfig) -> (Option<Duration>, Option<Duration>) {
+    let enabled = |secs: u64| (secs > 0).then(|| Duration::from_secs(secs));
+    (
+        enabled(config.tcp_keepalive_secs.unwrap_or(DEFAULT_TCP_KEEPALIVE_SECS)),
+        enabled(
+            config
+                .http2_keep_alive_interval_secs
+                .unwrap_or(DEFAULT_HTTP2_KEEP_ALIVE_INTERVAL_SECS),
+        ),
+    )
+}
+
+/// Variables a stdio child inherits under an allowlist: the listed names,
+/// then the MCP's `inherit_env`, that `lookup` finds in the environment
+fn allowlisted_env<'a>(
+    allowlist: &'a [String],
+    inherit_env: &'a [String],
+    lookup: impl Fn(&str) -> Option<std::ffi::OsString>,
+) -> Vec<(&'a str, std::ffi::OsString)> {
+    allowlist
+        .iter()
+        .chain(inherit_env)
+        .filter_map(|name| Some((name.as_str(), lookup(name)?)))
+        .collect()
+}
+
+/// Whether an error was caused by the transport (as opposed to the server
+/// answering with an MCP error)
+fn is_transport_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<rmcp::ServiceError>(),
+            Some(rmcp::ServiceError::TransportSend(_) | rmcp::ServiceError::TransportClosed)
+        )
+    })
+}
+
+/// `ConnectErrorKind` for a failed connect.  Typed causes decide first,
+/// including the transport error rmcp keeps inside a failed handshake; the
+/// message of each cause is only matched, from its start, for errors that
+/// carry nothing more specific than text.
+fn classify_connect_error(error: &anyhow::Error) -> ConnectErrorKind {
+    let mut causes = Vec::new();
+    collect_causes(error.as_ref(), &mut causes);
+    if let Some(kind) = causes.iter().find_map(|cause| classify_cause(*cause)) {
+        return kind;
+    }
+
+    let messages: Vec<String> = causes
+        .iter()
+        .map(|cause| cause.to_string().to_ascii_lowercase())
+        .collect();
+    let starts_with = |prefixes: &[&str]| {
+        messages
+            .iter()
+            .any(|m| prefixes.iter().any(|p| m.starts_with(p)))
+    };
+    if starts_with(&["failed to spawn mcp server process", "no command specified"]) {
+        ConnectErrorKind::SpawnFailed
+    } else if starts_with(&["dns error", "failed to lookup address information"]) {
+        ConnectErrorKind::DnsError
+    } else {
+        ConnectErrorKind::TransportError
+    }
+}
+
+/// `error` and its sources, descending into the transport errors that rmcp
+/// holds as plain fields rather than as `source()`
+fn collect_causes<'a>(
+    error: &'a (dyn std::error::Error + 'static),
+    causes: &mut Vec<&'a (dyn std::error::Error + 'static)>,
+) {
+    use rmcp::service::ClientInitializeError;
+    use rmcp::transport::streamable_http_client::StreamableHttpError;
+
+    let mut next = Some(error);
+    while let Some(cause) = next {
+        causes.push(cause);
+        if let Some(ClientInitializeError::TransportError { error, .. }) =
+            cause.downcast_ref::<ClientInitializeError>()
+        {
+            collect_causes(error, causes);
+        }
+        if let Some(StreamableHttpError::Client(e)) =
+            cause.downcast_ref::<StreamableHttpError<reqwest::Error>>()
+        {
+            collect_causes(e, causes);
+        }
+        next = cause.source();
+    }
+}
+
+/// The kind a single typed cause implies, if any
+fn classify_cause(cause: &(dyn std::error::Error + 'static)) -> Option<ConnectErrorKind> {
+    use crate::mcp::legacy_sse::LegacySseError;
+    use rmcp::service::ClientInitializeError;
+    use rmcp::transport::streamable_http_client::StreamableHttpError;
+
+    if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+        return match e.kind() {
+            std::io::ErrorKind::ConnectionRefused => Some(ConnectErrorKind::Refused),
+            std::io::ErrorKind::TimedOut => Some(ConnectErrorKind::Timeout),
+            _ => None,
+        };
+    }
+    if cause.is::<tokio::time::error::Elapsed>() {
+        return Some(ConnectErrorKind::Timeout);
+    }
+    if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+        if e.is_timeout() {
+            return Some(ConnectErrorKind::Timeout);
+        }
+        return match e.status() {
+            Some(reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN) => {
+                Some(ConnectErrorKind::AuthFailed)
             }
-            "prompts/list" => {
-                let result = service
-                    .list_prompts(Default::default())
-                    .await
-                    .context("prompts/list failed")?;
-                serde_json::to_value(&result)?
+            _ => None,
+        };
+    }
+    if let Some(rmcp::ServiceError::Timeout { .. }) = cause.downcast_ref::<rmcp::ServiceError>() {
+        return Some(ConnectErrorKind::Timeout);
+    }
+    if let Some(e) = cause.downcast_ref::<ClientInitializeError>() {
+        return match e {
+            ClientInitializeError::ExpectedInitResponse(_)
+            | ClientInitializeError::ExpectedInitResult(_)
+            | ClientInitializeError::ConflictInitResponseId(..)
+            | ClientInitializeError::JsonRpcError(_) => Some(ConnectErrorKind::ProtocolError),
+            // The transport error itself is among the causes collected
+            _ => None,
+        };
+    }
+    if let Some(e) = cause.downcast_ref::<StreamableHttpError<reqwest::Error>>() {
+        return match e {
+            StreamableHttpError::AuthRequired(_) => Some(ConnectErrorKind::AuthFailed),
+            StreamableHttpError::UnexpectedContentType(_)
+            | StreamableHttpError::MissingSessionIdInResponse => {
+                Some(ConnectErrorKind::ProtocolError)
             }
-            "prompts/get" => {
-                let prompt_params = serde_json::from_value(params)
-                    .context("Invalid prompts/get params")?;
-                let result = service
-                    .get_prompt(prompt_params)
-                    .await
-                    .context("prompts/get failed")?;
-                serde_json::to_value(&result)?
+            _ => None,
+        };
+    }
+    if let Some(LegacySseError::NoEndpoint) = cause.downcast_ref::<LegacySseError>() {
+        return Some(ConnectErrorKind::ProtocolError);
+    }
+    if cause.is::<serde_json::Error>() {
+        return Some(ConnectErrorKind::ProtocolError);
+    }
+    None
+}
+
+/// Failures worth retrying: transport errors and request timeouts
+fn is_transient_error(error: &anyhow::Error) -> bool {
+    is_transport_error(error)
+        || error.chain().any(|cause| {
+            matches!(
+                cause.downcast_ref::<rmcp::ServiceError>(),
+                Some(rmcp::ServiceError::Timeout { .. })
+            )
+        })
+}
+
+fn format_system_time(time: SystemTime) -> String {
+    let datetime: chrono::DateTime<chrono::Utc> = time.into();
+    datetime.to_rfc3339()
+}
+
+/// Whether an object key names a secret (see `SENSITIVE_KEYS`)
+fn is_sensitive_key(key: &str) -> bool {
+    if PROTOCOL_KEYS.contains(&key) {
+        return false;
+    }
+    // Lowercase words joined by `_`, split at separators and camelCase humps
+    let mut words = String::with_capacity(key.len() + 4);
+    let mut after_lower = false;
+    for c in key.chars() {
+        if !c.is_alphanumeric() {
+            if !words.is_empty() && !words.ends_with('_') {
+                words.push('_');
             }
-            "completion/complete" => {
-                let complete_params = serde_json::from_value(params)
-                    .context("Invalid completion/complete params")?;
-                let result = service
-                    .complete(complete_params)
-                    .await
-                    .context("completion/complete failed")?;
-                serde_json::to_value(&result)?
+            after_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && after_lower {
+            words.push('_');
+        }
+        after_lower = c.is_lowercase() || c.is_numeric();
+        words.extend(c.to_lowercase());
+    }
+    let words = words.trim_end_matches('_');
+    SENSITIVE_KEYS.iter().any(|sensitive| {
+        words
+            .strip_suffix(sensitive)
+            .is_some_and(|rest| rest.is_empty() || rest.ends_with('_'))
+    })
+}
+
+/// Replace the values of sensitive-looking keys, at any depth
+fn redact_json(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if is_sensitive_key(key) {
+                    *value = serde_json::Value::String(REDACTED.to_string());
+                } else {
+                    redact_json(value);
+                }
             }
-            "logging/setLevel" => {
-                let level_params = serde_json::from_value(params)
-                    .context("Invalid logging/setLevel params")?;
-                service
-                    .set_level(level_params)
-                    .await
-                    .context("logging/setLevel failed")?;
-                serde_json::json!({})
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_json),
+        _ => {}
+    }
+}
+
+/// Remove ANSI escape sequences: CSI (`ESC [ ... final`), OSC (`ESC ] ...`
+/// ended by BEL or `ESC \`) and other short escapes
+fn strip_ansi(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('[') => {
+                // Parameters and intermediates, then one final byte in @..~
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
             }
-            other => {
-                return Err(anyhow!("Method not found: {}", other));
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // `ESC ( B` and similar: intermediates, then one final character
+            Some(' '..='/') => {
+                for c in chars.by_ref() {
+                    if !(' '..='/').contains(&c) {
+                        break;
+                    }
+                }
             }
+            _ => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{
+        http_mcp_config, mcp_config, shell_mcp_config, unreachable_url, FakeUpstream,
+    };
+    use std::collections::HashMap;
+
+    fn connection(config: McpServerConfig) -> McpConnection {
+        McpConnection::new(
+            config,
+            5,
+            Arc::new(ConnectPermits::new(4)),
+            Arc::new(Mutex::new(None)),
+            Arc::new(Mutex::new(None)),
+        )
+    }
+
+    fn call_text(result: &serde_json::Value) -> &str {
+        result["content"][0]["text"].as_str().unwrap_or_default()
+    }
+
+    fn count(methods: &[String], method: &str) -> usize {
+        methods.iter().filter(|m| m.as_str() == method).count()
+    }
+
+    #[tokio::test]
+    async fn connect_errors_are_classified_by_cause() {
+        use rmcp::service::ClientInitializeError;
+        use rmcp::transport::streamable_http_client::{AuthRequiredError, StreamableHttpError};
+        use std::io::{Error as IoError, ErrorKind};
+
+        let handshake = |error: StreamableHttpError<reqwest::Error>| {
+            anyhow::Error::new(ClientInitializeError::TransportError {
+                error: rmcp::transport::DynamicTransportError {
+                    transport_name: "streamable-http".into(),
+                    transport_type_id: std::any::TypeId::of::<()>(),
+                    error: Box::new(error),
+                },
+                context: "send initialize request".into(),
+            })
+            .context("MCP handshake failed with http://a/mcp")
         };
+        let elapsed = tokio::time::timeout(Duration::ZERO, std::future::pending::<()>())
+            .await
+            .unwrap_err();
+
+        let cases = [
+            (
+                anyhow::Error::new(IoError::from(ErrorKind::NotFound))
+                    .context("Failed to spawn MCP server process (command: nope)"),
+                ConnectErrorKind::SpawnFailed,
+            ),
+            (
+                anyhow::Error::new(IoError::from(ErrorKind::ConnectionRefused))
+                    .context("Cannot reach http://127.0.0.1:9/mcp"),
+                ConnectErrorKind::Refused,
+            ),
+            (
+                anyhow::Error::new(IoError::new(
+                    ErrorKind::Other,
+                    "failed to lookup address information: Name or service not known",
+                ))
+                .context("Cannot reach http://nope.invalid/mcp"),
+                ConnectErrorKind::DnsError,
+            ),
+            (
+                anyhow::Error::new(elapsed)
+                    .context("Connection to http://a/mcp timed out after 5 seconds"),
+                ConnectErrorKind::Timeout,
+            ),
+            (
+                handshake(StreamableHttpError::AuthRequired(AuthRequiredError {
+                    www_authenticate_header: "Bearer".to_string(),
+                })),
+                ConnectErrorKind::AuthFailed,
+            ),
+            (
+                handshake(StreamableHttpError::UnexpectedContentType(None)),
+                ConnectErrorKind::ProtocolError,
+            ),
+            (
+                anyhow::Error::new(ClientInitializeError::ExpectedInitResult(None))
+                    .context("Failed to initialize MCP client service"),
+                ConnectErrorKind::ProtocolError,
+            ),
+            (
+                anyhow::Error::new(ClientInitializeError::ConnectionClosed(
+                    "initialize response".to_string(),
+                ))
+                .context("Failed to initialize MCP client service"),
+                ConnectErrorKind::TransportError,
+            ),
+            (
+                handshake(StreamableHttpError::UnexpectedServerResponse(
+                    "HTTP 503 Service Unavailable".into(),
+                )),
+                ConnectErrorKind::TransportError,
+            ),
+            (
+                anyhow!("Server error from http://a/sse — HTTP 502 Bad Gateway"),
+                ConnectErrorKind::TransportError,
+            ),
+        ];
+        for (error, kind) in cases {
+            assert_eq!(classify_connect_error(&error), kind, "{:#}", error);
+        }
+    }
 
-        Ok(result)
+    #[tokio::test]
+    async fn failed_connects_report_their_error_kind() {
+        let refused = connection(http_mcp_config("fs", &unreachable_url().await));
+        refused.connect(ReconnectReason::Initial).await.unwrap_err();
+        let status = refused.status(&DisabledItems::default()).await;
+        assert_eq!(status.error_kind, Some(ConnectErrorKind::Refused));
+
+        let mut config = mcp_config("bin");
+        config.command = Some("/nonexistent/mcp-server".to_string());
+        let missing = connection(config);
+        missing.connect(ReconnectReason::Initial).await.unwrap_err();
+        let status = missing.status(&DisabledItems::default()).await;
+        assert_eq!(status.error_kind, Some(ConnectErrorKind::SpawnFailed));
+
+        let app = axum::Router::new().route(
+            "/mcp",
+            axum::routing::post(|| async { axum::http::StatusCode::FORBIDDEN }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let forbidden = connection(http_mcp_config("api", &url));
+        forbidden.connect(ReconnectReason::Initial).await.unwrap_err();
+        let status = forbidden.status(&DisabledItems::default()).await;
+        assert_eq!(status.error_kind, Some(ConnectErrorKind::AuthFailed));
     }
-}
 
-fn format_system_time(time: SystemTime) -> String {
-    let datetime: chrono::DateTime<chrono::Utc> = time.into();
-    datetime.to_rfc3339()
+    #[tokio::test]
+    async fn clearing_the_error_of_a_live_connection_marks_it_connected() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let conn = connection(http_mcp_config("fs", &upstream.url));
+        conn.connect(ReconnectReason::Initial).await.unwrap();
+        conn.set_error("stale failure".to_string(), None).await;
+        conn.set_state(ConnectionState::Error).await;
+
+        assert_eq!(conn.clear_error().await, ConnectionState::Connected);
+        assert_eq!(conn.get_error().await, None);
+    }
+
+    #[tokio::test]
+    async fn clearing_the_error_of_a_broken_connection_keeps_it_in_error() {
+        let conn = connection(http_mcp_config("fs", &unreachable_url().await));
+        conn.connect(ReconnectReason::Initial).await.unwrap_err();
+        let stale = conn.get_error().await.unwrap();
+
+        assert_eq!(conn.clear_error().await, ConnectionState::Error);
+        let fresh = conn.get_error().await.unwrap();
+        assert_ne!(fresh, stale);
+        assert_eq!(fresh, "Not connected");
+        let status = conn.status(&DisabledItems::default()).await;
+        assert_eq!(status.error_kind, None);
+    }
+
+    #[tokio::test]
+    async fn connect_falls_back_when_the_primary_is_down() {
+        let secondary = FakeUpstream::spawn("secondary", &["echo"]).await;
+        let mut config = http_mcp_config("fs", &unreachable_url().await);
+        config.fallback_upstreams = vec![secondary.url.clone()];
+        let conn = connection(config);
+
+        conn.connect(ReconnectReason::Initial).await.unwrap();
+
+        let status = conn.status(&DisabledItems::default()).await;
+        assert_eq!(status.state, ConnectionState::Connected);
+        assert_eq!(status.active_upstream, 1);
+        let result = conn
+            .execute_request(
+                "tools/call",
+                serde_json::json!({"name": "echo", "arguments": {}}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(call_text(&result), "echo from secondary");
+    }
+
+    #[tokio::test]
+    async fn requests_fail_over_when_the_active_upstream_dies() {
+        let mut primary = FakeUpstream::spawn("primary", &["echo"]).await;
+        let secondary = FakeUpstream::spawn("secondary", &["echo"]).await;
+        let mut config = http_mcp_config("fs", &primary.url);
+        config.fallback_upstreams = vec![secondary.url.clone()];
+        let conn = connection(config);
+        conn.connect(ReconnectReason::Initial).await.unwrap();
+        assert_eq!(
+            conn.status(&DisabledItems::default()).await.active_upstream,
+            0
+        );
+
+        primary.stop().await;
+        let listed_before = count(&secondary.methods(), "tools/list");
+        let result = conn
+            .execute_request("tools/list", serde_json::json!({}))
+            .await
+            .unwrap();
+
+        assert_eq!(result["tools"][0]["name"], "echo");
+        assert!(count(&secondary.methods(), "tools/list") > listed_before);
+        let status = conn.status(&DisabledItems::default()).await;
+        assert_eq!(status.state, ConnectionState::Connected);
+        assert_eq!(status.active_upstream, 1);
+    }
+
+    #[tokio::test]
+    async fn failover_does_not_replay_a_non_idempotent_tool_call() {
+        let mut primary = FakeUpstream::spawn("primary", &["echo"]).await;
+        let secondary = FakeUpstream::spawn("secondary", &["echo"]).await;
+        let mut config = http_mcp_config("fs", &primary.url);
+        config.fallback_upstreams = vec![secondary.url.clone()];
+        let conn = connection(config);
+        conn.connect(ReconnectReason::Initial).await.unwrap();
+
+        primary.stop().await;
+        let call = serde_json::json!({"name": "echo", "arguments": {}});
+        assert!(conn.execute_request("tools/call", call.clone()).await.is_err());
+
+        assert_eq!(count(&secondary.methods(), "tools/call"), 0);
+        let status = conn.status(&DisabledItems::default()).await;
+        assert_eq!(status.state, ConnectionState::Connected);
+        assert_eq!(status.active_upstream, 1);
+        let result = conn.execute_request("tools/call", call).await.unwrap();
+        assert_eq!(call_text(&result), "echo from secondary");
+    }
+
+    #[tokio::test]
+    async fn concurrent_failures_fail_over_only_once() {
+        let mut primary = FakeUpstream::spawn("primary", &["echo"]).await;
+        let secondary = FakeUpstream::spawn("secondary", &["echo"]).await;
+        let mut config = http_mcp_config("fs", &primary.url);
+        config.fallback_upstreams = vec![secondary.url.clone()];
+        let conn = Arc::new(connection(config));
+        conn.connect(ReconnectReason::Initial).await.unwrap();
+
+        primary.stop().await;
+        let requests: Vec<_> = (0..4)
+            .map(|_| {
+                let conn = Arc::clone(&conn);
+                tokio::spawn(async move {
+                    conn.execute_request("tools/list", serde_json::json!({}))
+                        .await
+                })
+            })
+            .collect();
+        for request in requests {
+            request.await.unwrap().unwrap();
+        }
+
+        assert_eq!(count(&secondary.methods(), "initialize"), 1);
+        assert_eq!(conn.status(&DisabledItems::default()).await.active_upstream, 1);
+    }
+
+    #[tokio::test]
+    async fn connect_reports_the_last_error_when_every_upstream_is_down() {
+        let mut config = http_mcp_config("fs", &unreachable_url().await);
+        config.fallback_upstreams = vec![unreachable_url().await];
+        let conn = connection(config);
+
+        assert!(conn.connect(ReconnectReason::Initial).await.is_err());
+        assert_eq!(conn.get_state().await, ConnectionState::Error);
+    }
+
+    #[tokio::test]
+    async fn initialize_presents_the_proxy_as_client_by_default() {
+        let upstream = FakeUpstream::spawn("up", &[]).await;
+        let conn = connection(http_mcp_config("fs", &upstream.url));
+        conn.connect(ReconnectReason::Initial).await.unwrap();
+
+        let params = upstream.params_of("initialize");
+        assert_eq!(params.len(), 1);
+        assert_eq!(params[0]["clientInfo"]["name"], DEFAULT_CLIENT_NAME);
+        assert_eq!(
+            params[0]["clientInfo"]["version"],
+            env!("CARGO_PKG_VERSION")
+        );
+    }
+
+    #[tokio::test]
+    async fn initialize_sends_the_configured_client_info() {
+        let upstream = FakeUpstream::spawn("up", &[]).await;
+        let mut config = http_mcp_config("fs", &upstream.url);
+        config.client_info = Some(ClientIdentity {
+            name: "claude-code".to_string(),
+            version: "2.1.0".to_string(),
+        });
+        let conn = connection(config);
+        conn.connect(ReconnectReason::Initial).await.unwrap();
+
+        let params = upstream.params_of("initialize");
+        assert_eq!(params[0]["clientInfo"]["name"], "claude-code");
+        assert_eq!(params[0]["clientInfo"]["version"], "2.1.0");
+    }
+
+    #[tokio::test]
+    async fn initialize_requests_the_pinned_protocol_version() {
+        let upstream = FakeUpstream::spawn("up", &[]).await;
+        let mut config = http_mcp_config("fs", &upstream.url);
+        config.upstream_protocol_version = Some("2024-11-05".to_string());
+        let conn = connection(config);
+        conn.connect(ReconnectReason::Initial).await.unwrap();
+
+        let params = upstream.params_of("initialize");
+        assert_eq!(params[0]["protocolVersion"], "2024-11-05");
+    }
+
+    #[tokio::test]
+    async fn legacy_sse_sends_configured_headers_on_stream_and_posts() {
+        let upstream = FakeUpstream::spawn("sse", &["echo"]).await;
+        let mut config = http_mcp_config("legacy", &upstream.sse_url);
+        config.transport_type = TransportType::Sse;
+        config.headers = Some(HashMap::from([("X-Team".to_string(), "tools".to_string())]));
+        let conn = connection(config);
+        conn.connect(ReconnectReason::Initial).await.unwrap();
+
+        let result = conn
+            .execute_request(
+                "tools/call",
+                serde_json::json!({"name": "echo", "arguments": {}}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(call_text(&result), "echo from sse");
+
+        let seen = upstream.header_values("x-team");
+        assert!(seen.iter().any(|(method, _)| method == "GET"));
+        assert!(seen.iter().any(|(method, _)| method == "POST"));
+        for (method, value) in seen {
+            assert_eq!(value.as_deref(), Some("tools"), "{} without header", method);
+        }
+    }
+
+    #[tokio::test]
+    async fn http_health_check_sends_a_ping_rather_than_listing_tools() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let conn = connection(http_mcp_config("fs", &upstream.url));
+        conn.connect(ReconnectReason::Initial).await.unwrap();
+        let listed = upstream.requests_for("tools/list").len();
+
+        conn.ping().await.unwrap();
+
+        assert_eq!(upstream.requests_for("ping").len(), 1);
+        assert_eq!(upstream.requests_for("tools/list").len(), listed);
+        let status = conn.status(&DisabledItems::default()).await;
+        assert!(status.last_ping.is_some());
+    }
+
+    #[tokio::test]
+    async fn legacy_sse_health_check_only_checks_the_stream() {
+        let upstream = FakeUpstream::spawn("sse", &["echo"]).await;
+        let mut config = http_mcp_config("legacy", &upstream.sse_url);
+        config.transport_type = TransportType::Sse;
+        let conn = connection(config);
+        conn.connect(ReconnectReason::Initial).await.unwrap();
+        let sent = upstream.methods().len();
+
+        conn.ping().await.unwrap();
+
+        assert_eq!(upstream.methods().len(), sent);
+    }
+
+    #[tokio::test]
+    async fn auto_transport_detects_streamable_http_and_legacy_sse() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        for (url, expected) in [
+            (&upstream.url, TransportType::StreamableHttp),
+            (&upstream.sse_url, TransportType::Sse),
+        ] {
+            let mut config = http_mcp_config("fs", url);
+            config.transport_type = TransportType::Auto;
+            let conn = connection(config);
+
+            conn.connect(ReconnectReason::Initial).await.unwrap();
+
+            let status = conn.status(&DisabledItems::default()).await;
+            assert_eq!(status.resolved_transport, Some(expected));
+            let result = conn
+                .execute_request(
+                    "tools/call",
+                    serde_json::json!({"name": "echo", "arguments": {}}),
+                )
+                .await
+                .unwrap();
+            assert_eq!(call_text(&result), "echo from up");
+        }
+    }
+
+    #[tokio::test]
+    async fn auto_transport_reports_both_failures() {
+        let mut config = http_mcp_config("fs", &unreachable_url().await);
+        config.transport_type = TransportType::Auto;
+        let conn = connection(config);
+
+        let err = conn
+            .connect(ReconnectReason::Initial)
+            .await
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.contains("streamable HTTP"), "{}", err);
+        assert!(err.contains("legacy SSE"), "{}", err);
+        assert_eq!(conn.status(&DisabledItems::default()).await.resolved_transport, None);
+    }
+
+    #[tokio::test]
+    async fn recorded_requests_are_redacted_and_capped() {
+        let conn = connection(http_mcp_config("fs", "http://127.0.0.1:1/mcp"));
+        let request = |n: usize| {
+            serde_json::json!({
+                "method": "tools/call",
+                "params": {
+                    "arguments": {
+                        "path": format!("/tmp/{}", n),
+                        "nested": [{"API_KEY": "k"}],
+                        "password": "p"
+                    },
+                    "_meta": {"progressToken": n}
+                }
+            })
+        };
+
+        for n in 0..RECORDED_REQUEST_LIMIT + 1 {
+            conn.record_request("tools/call", &request(n), None, Duration::from_millis(3))
+                .await;
+        }
+
+        let recorded = conn.get_recorded_requests().await;
+        assert_eq!(recorded.len(), RECORDED_REQUEST_LIMIT);
+        // The first request was evicted; the rest are kept oldest first
+        assert_eq!(recorded[0].request["params"]["arguments"]["path"], "/tmp/1");
+        assert_eq!(
+            recorded[RECORDED_REQUEST_LIMIT - 1].request["params"]["arguments"]["path"],
+            format!("/tmp/{}", RECORDED_REQUEST_LIMIT)
+        );
+        let arguments = &recorded[0].request["params"]["arguments"];
+        assert_eq!(arguments["password"], REDACTED);
+        assert_eq!(arguments["nested"][0]["API_KEY"], REDACTED);
+        assert_eq!(recorded[0].request["params"]["_meta"]["progressToken"], 1);
+        assert_eq!(recorded[0].duration_ms, 3);
+    }
+
+    #[test]
+    fn sensitive_keys_match_whole_trailing_words() {
+        let sensitive = [
+            "token",
+            "accessToken",
+            "refresh_token",
+            "X-Api-Key",
+            "apiKey",
+            "APIKEY",
+            "client_secret",
+            "Authorization",
+            "db.password",
+        ];
+        for key in sensitive {
+            assert!(is_sensitive_key(key), "{}", key);
+        }
+        let plain = [
+            "progressToken",
+            "max_tokens",
+            "maxTokens",
+            "token_count",
+            "tokenizer",
+            "path",
+            "secretary",
+        ];
+        for key in plain {
+            assert!(!is_sensitive_key(key), "{}", key);
+        }
+    }
+
+    #[test]
+    fn strip_ansi_removes_escape_sequences_only() {
+        assert_eq!(strip_ansi("\x1b[1;31merror\x1b[0m: failed"), "error: failed");
+        assert_eq!(strip_ansi("\x1b]0;title\x07ready"), "ready");
+        assert_eq!(
+            strip_ansi("see \x1b]8;;http://x\x1b\\docs\x1b]8;;\x1b\\ here"),
+            "see docs here"
+        );
+        assert_eq!(strip_ansi("\x1b(Bplain"), "plain");
+        assert_eq!(strip_ansi("no escapes [here]"), "no escapes [here]");
+    }
+
+    #[tokio::test]
+    async fn stderr_is_stripped_of_ansi_unless_disabled() {
+        let script = "printf '\\033[32mready\\033[0m\\n' >&2; exit 1";
+        for (strip, expected) in [(None, "ready"), (Some(false), "\x1b[32mready\x1b[0m")] {
+            let mut config = shell_mcp_config("fs", script);
+            config.strip_ansi = strip;
+            let conn = connection(config);
+            let _ = conn.connect(ReconnectReason::Initial).await;
+
+            let mut lines = Vec::new();
+            for _ in 0..100 {
+                lines = conn.get_recent_stderr().await;
+                if !lines.is_empty() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            assert_eq!(lines, [expected]);
+        }
+    }
+
+    #[tokio::test]
+    async fn stdio_servers_receive_args_raw_split_into_words() {
+        let mut config = shell_mcp_config("fs", "");
+        config.args = None;
+        config.args_raw =
+            Some(r#"-c 'echo "$0|$1" >&2; exit 1' 'a b' "c\"d""#.to_string());
+        let conn = connection(config);
+
+        assert!(conn.connect(ReconnectReason::Initial).await.is_err());
+
+        for _ in 0..100 {
+            if let Some(line) = conn.get_recent_stderr().await.first() {
+                assert_eq!(line, r#"a b|c"d"#);
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("no stderr from the stdio server");
+    }
+
+    #[tokio::test]
+    async fn host_override_connects_to_the_given_ip_keeping_the_host_name() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let url = upstream.url.replace("127.0.0.1", "internal-mcp.invalid");
+        let mut config = http_mcp_config("fs", &url);
+        config.host_override = Some(HostOverride {
+            host: "internal-mcp.invalid".to_string(),
+            ip: "127.0.0.1".parse().unwrap(),
+        });
+        let conn = connection(config);
+
+        conn.connect(ReconnectReason::Initial).await.unwrap();
+
+        let result = conn
+            .execute_request(
+                "tools/call",
+                serde_json::json!({"name": "echo", "arguments": {}}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(call_text(&result), "echo from up");
+        let hosts = upstream.header_values("host");
+        assert!(!hosts.is_empty());
+        for (_, host) in hosts {
+            assert!(host.unwrap().starts_with("internal-mcp.invalid:"));
+        }
+
+        // Without the override the name doesn't resolve
+        let plain = connection(http_mcp_config("plain", &url));
+        assert!(plain.connect(ReconnectReason::Initial).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn http_clients_decode_gzip_replies_unless_disabled() {
+        let compressed = FakeUpstream::spawn("compressed", &["echo"]).await;
+        compressed.gzip_responses();
+        let conn = connection(http_mcp_config("fs", &compressed.url));
+        conn.connect(ReconnectReason::Initial).await.unwrap();
+
+        let result = conn
+            .execute_request(
+                "tools/call",
+                serde_json::json!({"name": "echo", "arguments": {}}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(call_text(&result), "echo from compressed");
+        assert!(compressed.gzipped_replies() >= 2);
+
+        let plain = FakeUpstream::spawn("plain", &["echo"]).await;
+        let mut config = http_mcp_config("legacy", &plain.sse_url);
+        config.transport_type = TransportType::Sse;
+        config.http_decompression = Some(false);
+        let legacy = connection(config);
+        legacy.connect(ReconnectReason::Initial).await.unwrap();
+
+        let seen = compressed.header_values("accept-encoding");
+        assert!(!seen.is_empty());
+        for (method, value) in seen {
+            let value = value.unwrap_or_default();
+            assert!(value.contains("gzip") && value.contains("br"), "{}: {}", method, value);
+        }
+        let seen = plain.header_values("accept-encoding");
+        assert!(!seen.is_empty());
+        for (method, value) in seen {
+            assert_eq!(value, None, "{} asked for compression", method);
+        }
+    }
+
+    #[tokio::test]
+    async fn only_health_failure_reconnects_use_the_failure_budget() {
+        let conn = connection(http_mcp_config("fs", &unreachable_url().await));
+
+        for reason in [ReconnectReason::UserRequested, ReconnectReason::OnDemand] {
+            assert!(conn.connect(reason).await.is_err());
+        }
+        assert_eq!(conn.get_reconnect_attempts().await, 0);
+
+        assert!(conn.connect(ReconnectReason::HealthFailure).await.is_err());
+        assert_eq!(conn.get_reconnect_attempts().await, 1);
+    }
+
+    #[tokio::test]
+    async fn events_record_a_failed_connect_then_a_reconnect_in_order() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let conn = connection(http_mcp_config("fs", &upstream.url));
+        upstream.fail_next(1);
+
+        assert!(conn.connect(ReconnectReason::Initial).await.is_err());
+        conn.connect(ReconnectReason::UserRequested).await.unwrap();
+        conn.disconnect().await;
+
+        let events = conn.get_events().await;
+        let kinds: Vec<_> = events.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                ConnectionEventKind::ConnectAttempt,
+                ConnectionEventKind::Failed,
+                ConnectionEventKind::ConnectAttempt,
+                ConnectionEventKind::Connected,
+                ConnectionEventKind::Disconnected,
+            ]
+        );
+        assert_eq!(events[0].reason, Some(ReconnectReason::Initial));
+        assert_eq!(events[2].reason, Some(ReconnectReason::UserRequested));
+        assert!(events[1].detail.is_some());
+        assert!(events.iter().skip(3).all(|e| e.reason.is_none()));
+    }
+
+    #[tokio::test]
+    async fn events_keep_only_the_most_recent_entries() {
+        let conn = connection(http_mcp_config("fs", "http://localhost/mcp"));
+        for i in 0..CONNECTION_EVENT_LIMIT + 5 {
+            conn.record_event(ConnectionEventKind::Failed, None, Some(i.to_string()))
+                .await;
+        }
+
+        let events = conn.get_events().await;
+        assert_eq!(events.len(), CONNECTION_EVENT_LIMIT);
+        assert_eq!(events[0].detail.as_deref(), Some("5"));
+        assert_eq!(
+            events.last().unwrap().detail,
+            Some((CONNECTION_EVENT_LIMIT + 4).to_string())
+        );
+    }
+
+    #[test]
+    fn keepalive_defaults_apply_when_unset() {
+        let config = http_mcp_config("fs", "http://localhost/mcp");
+        assert_eq!(
+            keepalive_settings(&config),
+            (
+                Some(Duration::from_secs(DEFAULT_TCP_KEEPALIVE_SECS)),
+                Some(Duration::from_secs(DEFAULT_HTTP2_KEEP_ALIVE_INTERVAL_SECS))
+            )
+        );
+    }
+
+    #[test]
+    fn keepalive_settings_honor_overrides_and_zero_disables() {
+        let mut config = http_mcp_config("fs", "http://localhost/mcp");
+        config.tcp_keepalive_secs = Some(15);
+        config.http2_keep_alive_interval_secs = Some(0);
+        assert_eq!(
+            keepalive_settings(&config),
+            (Some(Duration::from_secs(15)), None)
+        );
+    }
+
+    #[test]
+    fn http_timeouts_default_and_override() {
+        let mut config = http_mcp_config("fs", "http://localhost/mcp");
+        assert_eq!(
+            http_timeouts(&config),
+            (
+                Duration::from_secs(DEFAULT_HTTP_CONNECT_TIMEOUT_SECS),
+                Duration::from_secs(DEFAULT_HTTP_POOL_IDLE_TIMEOUT_SECS)
+            )
+        );
+
+        config.http_connect_timeout_secs = Some(3);
+        config.http_pool_idle_timeout_secs = Some(5);
+        assert_eq!(
+            http_timeouts(&config),
+            (Duration::from_secs(3), Duration::from_secs(5))
+        );
+    }
+
+    #[test]
+    fn allowlisted_env_passes_only_listed_variables_that_are_set() {
+        let environment = HashMap::from([("ALLOWED", "a"), ("EXTRA", "e"), ("OTHER", "o")]);
+        let lookup = |name: &str| environment.get(name).map(std::ffi::OsString::from);
+        let names = |list: &[&str]| list.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+
+        let allowlist = names(&["ALLOWED", "MISSING"]);
+        assert_eq!(
+            allowlisted_env(&allowlist, &[], lookup),
+            vec![("ALLOWED", "a".into())]
+        );
+        let inherit_env = names(&["EXTRA"]);
+        assert_eq!(
+            allowlisted_env(&allowlist, &inherit_env, lookup),
+            vec![("ALLOWED", "a".into()), ("EXTRA", "e".into())]
+        );
+        assert!(allowlisted_env(&[], &[], lookup).is_empty());
+    }
+
+    #[tokio::test]
+    async fn calls_work_without_idle_pooled_connections() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let mut config = http_mcp_config("fs", &upstream.url);
+        config.http_pool_max_idle_per_host = Some(0);
+        config.http_pool_idle_timeout_secs = Some(1);
+        let conn = connection(config);
+        conn.connect(ReconnectReason::Initial).await.unwrap();
+
+        for _ in 0..3 {
+            let result = conn
+                .execute_request(
+                    "tools/call",
+                    serde_json::json!({"name": "echo", "arguments": {}}),
+                )
+                .await
+                .unwrap();
+            assert_eq!(call_text(&result), "echo from up");
+        }
+    }
+
+    #[tokio::test]
+    async fn connects_with_custom_keepalive_settings() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let mut config = http_mcp_config("fs", &upstream.url);
+        config.tcp_keepalive_secs = Some(0);
+        config.http2_keep_alive_interval_secs = Some(5);
+        config.http2_adaptive_window = Some(false);
+        let conn = connection(config);
+
+        conn.connect(ReconnectReason::Initial).await.unwrap();
+        assert_eq!(conn.get_state().await, ConnectionState::Connected);
+    }
+
+    /// How many `method` requests `upstream` has received
+    fn count(upstream: &FakeUpstream, method: &str) -> usize {
+        upstream.methods().iter().filter(|m| *m == method).count()
+    }
+
+    async fn retrying_connection(upstream: &FakeUpstream, idempotent: bool) -> McpConnection {
+        let mut config = http_mcp_config("fs", &upstream.url);
+        config.max_retries = Some(2);
+        config.idempotent = Some(idempotent);
+        let conn = connection(config);
+        conn.connect(ReconnectReason::Initial).await.unwrap();
+        conn
+    }
+
+    #[tokio::test]
+    async fn read_only_methods_are_retried_on_transient_failures() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let conn = retrying_connection(&upstream, false).await;
+        let before = count(&upstream, "tools/list");
+        upstream.fail_next(1);
+
+        let result = conn
+            .execute_request("tools/list", serde_json::json!({}))
+            .await
+            .unwrap();
+
+        assert_eq!(result["tools"][0]["name"], "echo");
+        assert_eq!(count(&upstream, "tools/list") - before, 2);
+    }
+
+    #[tokio::test]
+    async fn tool_calls_are_not_retried_by_default() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let conn = retrying_connection(&upstream, false).await;
+        upstream.fail_next(1);
+
+        let result = conn
+            .execute_request(
+                "tools/call",
+                serde_json::json!({"name": "echo", "arguments": {}}),
+            )
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(count(&upstream, "tools/call"), 1);
+    }
+
+    #[tokio::test]
+    async fn tool_calls_are_retried_when_the_mcp_is_idempotent() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let conn = retrying_connection(&upstream, true).await;
+        upstream.fail_next(1);
+
+        let result = conn
+            .execute_request(
+                "tools/call",
+                serde_json::json!({"name": "echo", "arguments": {}}),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(call_text(&result), "echo from up");
+        assert_eq!(count(&upstream, "tools/call"), 2);
+    }
+
+    /// A stdio MCP server in shell that answers the handshake and capability
+    /// listing from a background loop, while the process itself exits with
+    /// status 3 after a second, leaving the pipes open as a hung child would.
+    /// (Background jobs get /dev/null as stdin unless given an explicit fd.)
+    const EXITING_STDIO_SERVER: &str = r#"
+        exec 3<&0
+        (
+            while read -r line; do
+                id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+                [ -z "$id" ] && continue
+                case "$line" in
+                    *'"initialize"'*) result='{"protocolVersion":"2025-03-26","capabilities":{"tools":{}},"serverInfo":{"name":"sh","version":"1"}}' ;;
+                    *'"tools/list"'*) result='{"tools":[]}' ;;
+                    *'"resources/list"'*) result='{"resources":[]}' ;;
+                    *) result='{}' ;;
+                esac
+                printf '{"jsonrpc":"2.0","id":%s,"result":%s}\n' "$id" "$result"
+            done
+        ) <&3 &
+        sleep 1
+        exit 3
+    "#;
+
+    #[tokio::test]
+    async fn health_check_notices_an_exited_stdio_child() {
+        let conn = connection(shell_mcp_config("fs", EXITING_STDIO_SERVER));
+        conn.connect(ReconnectReason::Initial).await.unwrap();
+        assert_eq!(conn.get_state().await, ConnectionState::Connected);
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        let error = conn.ping().await.unwrap_err().to_string();
+
+        assert!(error.contains("exited"), "{}", error);
+        assert!(error.contains('3'), "{}", error);
+        assert_eq!(conn.get_state().await, ConnectionState::Error);
+        let status = conn.status(&DisabledItems::default()).await;
+        assert_eq!(status.error_message.as_deref(), Some(error.as_str()));
+    }
+
+    #[tokio::test]
+    async fn spawned_process_reports_the_pid_only_while_the_child_runs() {
+        let conn = connection(shell_mcp_config("fs", EXITING_STDIO_SERVER));
+        let process = conn.spawned_process().await.unwrap();
+        assert_eq!((process.pid, process.alive), (None, false));
+
+        conn.connect(ReconnectReason::Initial).await.unwrap();
+        let process = conn.spawned_process().await.unwrap();
+        assert!(process.pid.is_some());
+        assert!(process.alive);
+        assert!(process.command.unwrap().starts_with("sh -c"));
+
+        conn.force_kill("Killed by user").await;
+        let process = conn.spawned_process().await.unwrap();
+        assert_eq!((process.pid, process.alive), (None, false));
+        assert_eq!(conn.get_state().await, ConnectionState::Disconnected);
+
+        let http = connection(http_mcp_config("web", "http://127.0.0.1:1/mcp"));
+        assert!(http.spawned_process().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn signed_upstreams_get_a_signature_on_every_mcp_post() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let mut config = http_mcp_config("fs", &upstream.url);
+        config.request_signing = Some(RequestSigning {
+            secret: "s3cret".to_string(),
+            header: "X-Signature".to_string(),
+            payload: SigningPayload::Body,
+            timestamp_header: "X-Timestamp".to_string(),
+        });
+        let conn = connection(config);
+        conn.connect(ReconnectReason::Initial).await.unwrap();
+
+        let signatures: Vec<String> = upstream
+            .header_values("x-signature")
+            .into_iter()
+            .filter_map(|(_, value)| value)
+            .collect();
+        // initialize, notifications/initialized and the capability listing
+        assert!(signatures.len() >= 3, "{:?}", signatures);
+        for signature in signatures {
+            assert_eq!(signature.len(), 64);
+            assert!(signature.bytes().all(|b| b.is_ascii_hexdigit()));
+        }
+    }
+
+    #[tokio::test]
+    async fn signed_posts_report_a_401_challenge_as_auth_required() {
+        use rmcp::transport::streamable_http_client::{StreamableHttpClient, StreamableHttpError};
+
+        let app = axum::Router::new().route(
+            "/mcp",
+            axum::routing::post(|| async {
+                (
+                    axum::http::StatusCode::UNAUTHORIZED,
+                    [(axum::http::header::WWW_AUTHENTICATE, r#"Bearer realm="mcp""#)],
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let signer = RequestSigner::new(&RequestSigning {
+            secret: "s3cret".to_string(),
+            header: "X-Signature".to_string(),
+            payload: SigningPayload::Body,
+            timestamp_header: "X-Timestamp".to_string(),
+        });
+        let client = GracefulHttpClient(reqwest::Client::new(), Some(Arc::new(signer)));
+        let message = ClientJsonRpcMessage::request(
+            client_request("ping", serde_json::json!({})).unwrap(),
+            rmcp::model::NumberOrString::Number(1),
+        );
+
+        match client.post_message(url.into(), message, None, None).await {
+            Err(StreamableHttpError::AuthRequired(e)) => {
+                assert_eq!(e.www_authenticate_header, r#"Bearer realm="mcp""#)
+            }
+            other => panic!("expected AuthRequired, got {:?}", other.map(|_| ())),
+        }
+    }
 }
@@ -3,10 +3,13 @@
 //! Old-style SSE MCP servers work like this:
 //!   1. Client opens `GET <base_url>/sse` → server sends SSE events
 //!   2. Server sends an `endpoint` event with a relative URL like `/messages?sessionId=xxx`
-//!   3. Client sends JSON-RPC requests via `POST <base_url><endpoint>`
+//!   3. Client sends JSON-RPC requests via `POST <base_url><endpoint>` (a
+//!      query-only endpoint like `?sessionId=xxx` is appended to the SSE path)
 //!   4. Server sends JSON-RPC responses/notifications via the SSE stream
 
 use std::borrow::Cow;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use futures::StreamExt;
 use reqwest::Client;
@@ -41,8 +44,12 @@ pub struct LegacySseWorker {
     base_url: String,
     /// The SSE endpoint path (e.g. "/sse")
     sse_path: String,
-    /// Optional extra headers
+    /// Optional extra headers, sent on the SSE GET and every POST
     headers: Vec<(String, String)>,
+    /// HTTP client shared by the SSE stream and message POSTs
+    client: Client,
+    /// Set while the SSE reader task is running, for health checks
+    alive: Arc<AtomicBool>,
 }
 
 impl LegacySseWorker {
@@ -66,6 +73,8 @@ impl LegacySseWorker {
             base_url,
             sse_path,
             headers: Vec::new(),
+            client: Client::new(),
+            alive: Arc::new(AtomicBool::new(false)),
         })
     }
 
@@ -75,11 +84,43 @@ impl LegacySseWorker {
         self
     }
 
+    /// Use a pre-built client (default headers, timeouts, HTTP/2 settings)
+    /// instead of a bare `Client::new()`
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Report the SSE reader's liveness through `alive`: it becomes true once
+    /// the session is initialized and false when the stream ends
+    pub fn with_liveness(mut self, alive: Arc<AtomicBool>) -> Self {
+        self.alive = alive;
+        self
+    }
+
+    /// Build a JSON-RPC POST to the messages endpoint
+    fn post_message(&self, messages_url: &str, body: String) -> reqwest::RequestBuilder {
+        let mut request = self
+            .client
+            .post(messages_url)
+            .header("Content-Type", "application/json");
+        for (key, value) in &self.headers {
+            request = request.header(key.as_str(), value.as_str());
+        }
+        request.body(body)
+    }
+
+    /// Resolve an endpoint against the SSE URL: absolute URLs are used as-is,
+    /// root-relative paths replace the path, and query-only endpoints
+    /// (`?sessionId=xxx`) are appended to the SSE path rather than the base.
     fn full_url(&self, path: &str) -> String {
         if path.starts_with("http://") || path.starts_with("https://") {
-            path.to_string()
-        } else {
-            format!("{}{}", self.base_url, path)
+            return path.to_string();
+        }
+        let sse_url = format!("{}{}", self.base_url, self.sse_path);
+        match reqwest::Url::parse(&sse_url).and_then(|base| base.join(path)) {
+            Ok(url) => url.to_string(),
+            Err(_) => format!("{}{}", self.base_url, path),
         }
     }
 }
@@ -107,14 +148,13 @@ impl Worker for LegacySseWorker {
         self,
         mut context: WorkerContext<Self>,
     ) -> Result<(), WorkerQuitReason<Self::Error>> {
-        let client = Client::new();
         let ct = context.cancellation_token.clone();
 
         // Step 1: Open the SSE stream
         tracing::info!("Legacy SSE: connecting to {}{}", self.base_url, self.sse_path);
 
         let sse_url = self.full_url(&self.sse_path);
-        let mut request = client.get(&sse_url);
+        let mut request = self.client.get(&sse_url);
         for (key, value) in &self.headers {
             request = request.header(key.as_str(), value.as_str());
         }
@@ -198,10 +238,8 @@ impl Worker for LegacySseWorker {
 
         tracing::debug!("Legacy SSE: sending initialize: {}", init_body);
 
-        match client
-            .post(&messages_url)
-            .header("Content-Type", "application/json")
-            .body(init_body)
+        match self
+            .post_message(&messages_url, init_body)
             .send()
             .await
         {
@@ -234,10 +272,8 @@ impl Worker for LegacySseWorker {
 
         tracing::debug!("Legacy SSE: sending initialized notification: {}", notif_body);
 
-        let _ = client
-            .post(&messages_url)
-            .header("Content-Type", "application/json")
-            .body(notif_body)
+        let _ = self
+            .post_message(&messages_url, notif_body)
             .send()
             .await
             .map_err(|e| {
@@ -250,6 +286,8 @@ impl Worker for LegacySseWorker {
 
         // Spawn SSE reader task
         let sse_ct = ct.clone();
+        let alive = Arc::clone(&self.alive);
+        alive.store(true, Ordering::SeqCst);
         tokio::spawn(async move {
             loop {
                 tokio::select! {
@@ -295,6 +333,7 @@ impl Worker for LegacySseWorker {
                     }
                 }
             }
+            alive.store(false, Ordering::SeqCst);
         });
 
         // Main loop: forward messages between rmcp handler and SSE
@@ -317,10 +356,8 @@ impl Worker for LegacySseWorker {
 
                     tracing::debug!("Legacy SSE: POST {}", body);
 
-                    match client
-                        .post(&messages_url)
-                        .header("Content-Type", "application/json")
-                        .body(body)
+                    match self
+                        .post_message(&messages_url, body)
                         .send()
                         .await
                     {
@@ -413,3 +450,26 @@ impl LegacySseWorker {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoints_resolve_against_the_sse_url() {
+        let worker = LegacySseWorker::from_url("http://127.0.0.1:8080/api/sse").unwrap();
+
+        assert_eq!(
+            worker.full_url("?sessionId=abc"),
+            "http://127.0.0.1:8080/api/sse?sessionId=abc"
+        );
+        assert_eq!(
+            worker.full_url("/messages?sessionId=abc"),
+            "http://127.0.0.1:8080/messages?sessionId=abc"
+        );
+        assert_eq!(
+            worker.full_url("https://other.example/messages"),
+            "https://other.example/messages"
+        );
+    }
+}
@@ -1,3 +1,5 @@
+pub mod client_handler;
 pub mod connection;
 pub mod legacy_sse;
 pub mod manager;
+pub mod signing;
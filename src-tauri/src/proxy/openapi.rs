@@ -0,0 +1,198 @@
+//! OpenAPI 3.1 description of an MCP server's tools.
+//!
+//! Each enabled tool becomes a `POST /tools/{name}` operation whose request
+//! body is the tool's input schema.  The paths are synthetic — the document
+//! is a read-only artifact for non-MCP HTTP tooling, generated from the
+//! cached tool list.
+
+use crate::types::{object_schema, Tool};
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+
+/// Build the OpenAPI document for the given tools
+pub fn build_spec(mcp_name: &str, server_url: &str, tools: &[Tool]) -> Value {
+    let mut paths = Map::new();
+
+    for tool in tools {
+        let mut operation = json!({
+            "operationId": operation_id(&tool.name),
+            "summary": tool.name,
+            "requestBody": {
+                "required": true,
+                "content": {
+                    "application/json": {
+                        "schema": request_schema(tool, object_schema)
+                    }
+                }
+            },
+            "responses": {
+                "200": {
+                    "description": "Tool call result",
+                    "content": {
+                        "application/json": {
+                            "schema": { "type": "object" }
+                        }
+                    }
+                }
+            }
+        });
+        if let Some(description) = &tool.description {
+            operation["description"] = json!(description);
+        }
+
+        paths.insert(
+            format!("/tools/{}", tool.name),
+            json!({ "post": operation }),
+        );
+    }
+
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": mcp_name,
+            "version": env!("CARGO_PKG_VERSION")
+        },
+        "servers": [{ "url": server_url }],
+        "paths": paths
+    })
+}
+
+/// Request body schema for a tool: the one prepared at fetch time, shared
+/// rather than copied, or normalized now with `normalize` for tools that
+/// weren't built from a tool list
+fn request_schema(tool: &Tool, normalize: impl FnOnce(&Value) -> Value) -> Arc<Value> {
+    if tool.request_schema.is_null() {
+        Arc::new(normalize(&tool.input_schema))
+    } else {
+        Arc::clone(&tool.request_schema)
+    }
+}
+
+/// OpenAPI operation ids must be unique identifiers; replace anything
+/// outside `[A-Za-z0-9_]`
+fn operation_id(tool_name: &str) -> String {
+    tool_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool(name: &str, input_schema: Value) -> Tool {
+        Tool::new(
+            name.to_string(),
+            Some(format!("{} things", name)),
+            input_schema,
+            None,
+        )
+    }
+
+    #[test]
+    fn spec_has_an_operation_per_tool_with_its_schema() {
+        let schema = json!({
+            "type": "object",
+            "properties": {"path": {"type": "string"}},
+            "required": ["path"]
+        });
+        let tools = [
+            tool("read_file", schema.clone()),
+            tool("list", json!({"type": "object"})),
+        ];
+
+        let spec = build_spec("fs", "http://127.0.0.1:27270/mcp/fs", &tools);
+
+        assert_eq!(spec["openapi"], "3.1.0");
+        assert_eq!(spec["servers"][0]["url"], "http://127.0.0.1:27270/mcp/fs");
+        assert_eq!(spec["paths"].as_object().unwrap().len(), 2);
+        let operation = &spec["paths"]["/tools/read_file"]["post"];
+        assert_eq!(operation["operationId"], "read_file");
+        assert_eq!(operation["description"], "read_file things");
+        assert_eq!(
+            operation["requestBody"]["content"]["application/json"]["schema"],
+            schema
+        );
+    }
+
+    #[test]
+    fn missing_or_untyped_schemas_become_objects() {
+        let tools = [
+            tool("bare", Value::Null),
+            tool("untyped", json!({"properties": {"n": {"type": "integer"}}})),
+        ];
+
+        let spec = build_spec("fs", "http://localhost", &tools);
+
+        let schema_of = |name: &str| {
+            spec["paths"][format!("/tools/{}", name)]["post"]["requestBody"]["content"]
+                ["application/json"]["schema"]
+                .clone()
+        };
+        assert_eq!(schema_of("bare"), json!({"type": "object"}));
+        assert_eq!(schema_of("untyped")["type"], "object");
+        assert_eq!(schema_of("untyped")["properties"]["n"]["type"], "integer");
+    }
+
+    #[test]
+    fn deserialized_tools_get_their_schema_normalized_on_demand() {
+        let tool: Tool = serde_json::from_value(json!({
+            "name": "stat",
+            "input_schema": {"properties": {}}
+        }))
+        .unwrap();
+        assert!(tool.request_schema.is_null());
+
+        let spec = build_spec("fs", "http://localhost", &[tool]);
+
+        let schema = &spec["paths"]["/tools/stat"]["post"]["requestBody"]["content"]
+            ["application/json"]["schema"];
+        assert_eq!(schema, &json!({"type": "object", "properties": {}}));
+    }
+
+    #[test]
+    fn cloned_tools_share_their_schemas() {
+        let original = tool("read_file", json!({"type": "object"}));
+        let clone = original.clone();
+
+        assert!(std::sync::Arc::ptr_eq(&original.input_schema, &clone.input_schema));
+        assert!(std::sync::Arc::ptr_eq(&original.request_schema, &clone.request_schema));
+        assert_eq!(*clone.request_schema, json!({"type": "object"}));
+    }
+
+    #[test]
+    fn prepared_schemas_are_reused_without_normalizing_again() {
+        let prepared = tool("read_file", json!({"properties": {}}));
+        let deserialized: Tool = serde_json::from_value(json!({
+            "name": "stat",
+            "input_schema": {"properties": {}}
+        }))
+        .unwrap();
+        let calls = std::cell::Cell::new(0);
+        let counting = |schema: &Value| {
+            calls.set(calls.get() + 1);
+            object_schema(schema)
+        };
+
+        let schema = request_schema(&prepared, counting);
+        assert!(Arc::ptr_eq(&schema, &prepared.request_schema));
+        assert_eq!(calls.get(), 0);
+
+        let schema = request_schema(&deserialized, counting);
+        assert_eq!(*schema, json!({"type": "object", "properties": {}}));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn operation_ids_replace_non_identifier_characters() {
+        assert_eq!(operation_id("github.search-issues"), "github_search_issues");
+        assert_eq!(operation_id("plain_name1"), "plain_name1");
+    }
+}
@@ -1,26 +1,47 @@
-use crate::mcp::connection::McpConnection;
-use crate::mcp::manager::McpManager;
+use crate::mcp::connection::{
+    ForwardedHeaders, InvalidParams, McpConnection, ProgressSender, TooManyRequests,
+};
+use crate::mcp::manager::{DisabledItems, McpManager, ToolAliases};
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::{IntoResponse, Json},
-    routing::get,
+    extract::{Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Json,
+    },
+    routing::{get, post},
     Router,
 };
+use futures::{Stream, StreamExt};
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::future::IntoFuture;
 use std::net::SocketAddr;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::sync::{Arc, Mutex as StdMutex};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{mpsc, oneshot, Mutex};
 use tower_http::cors::{Any, CorsLayer};
 
 /// Shared state for the proxy server
 #[derive(Clone)]
 pub struct ProxyState {
     pub manager: Arc<Mutex<McpManager>>,
+    /// Open legacy SSE sessions, by session id
+    legacy_sessions: LegacySessions,
 }
 
+/// Session id -> the MCP the session was opened for and the sender feeding
+/// responses into that client's SSE stream
+type LegacySessions =
+    Arc<StdMutex<HashMap<String, (String, mpsc::UnboundedSender<serde_json::Value>)>>>;
+
 /// Create the Axum router for the proxy server
 pub fn create_router(manager: Arc<Mutex<McpManager>>) -> Router {
-    let state = ProxyState { manager };
+    let state = ProxyState {
+        manager,
+        legacy_sessions: LegacySessions::default(),
+    };
 
     let cors = CorsLayer::new()
         .allow_origin(Any)
@@ -28,41 +49,102 @@ pub fn create_router(manager: Arc<Mutex<McpManager>>) -> Router {
         .allow_headers(Any);
 
     Router::new()
-        .route("/health", get(health_check))
         .route("/mcps", get(list_mcps))
+        .route("/summary", get(status_summary))
         .route(
             "/mcp/:id",
             get(streamable_http_get)
                 .post(streamable_http_post)
                 .delete(streamable_http_delete),
         )
+        .route("/mcp/:id/sse", get(legacy_sse_get))
+        .route("/mcp/:id/messages", post(legacy_messages_post))
         .route("/mcp/:id/tools", get(list_tools))
         .route("/mcp/:id/resources", get(list_resources))
+        .route("/mcp/:id/resource", get(read_resource))
+        .route("/mcp/:id/openapi", get(openapi_spec))
+        .route("/", get(status_page))
+        // Routes added after this layer (health) stay unauthenticated
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_token))
+        .route("/health", get(health_check))
         .layer(cors)
         .with_state(state)
 }
 
-/// Start the proxy server on the given port
+/// Start the proxy server on each of `addrs`, all sharing one router.
+/// The first address is the primary (loopback) listener: its bound address is
+/// sent on `bound` once ready, and failing to bind it is an error.  The sender
+/// is dropped without a value in that case.  Extra listeners that fail to bind
+/// are logged and skipped.
 pub async fn start_proxy_server(
-    port: u16,
+    addrs: Vec<SocketAddr>,
     manager: Arc<Mutex<McpManager>>,
+    bound: oneshot::Sender<SocketAddr>,
 ) -> anyhow::Result<()> {
     let app = create_router(manager);
+    let (primary, extras) = addrs
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("no proxy listen address configured"))?;
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], port));
-    tracing::info!("Starting MCP Streamable HTTP proxy on http://127.0.0.1:{}", port);
+    tracing::info!("Starting MCP Streamable HTTP proxy on http://{}", primary);
+    let mut listeners = vec![tokio::net::TcpListener::bind(primary).await?];
+    let _ = bound.send(listeners[0].local_addr()?);
 
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    for addr in extras {
+        match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => {
+                tracing::info!("Also serving MCP proxy on http://{}", addr);
+                listeners.push(listener);
+            }
+            Err(e) => tracing::error!("Failed to bind extra proxy listener {}: {}", addr, e),
+        }
+    }
+
+    let servers = listeners
+        .into_iter()
+        .map(|listener| axum::serve(listener, app.clone()).into_future());
+    for result in futures::future::join_all(servers).await {
+        result?;
+    }
 
     Ok(())
 }
 
+/// Reject requests without the configured bearer token, if one is set.
+/// The token is read per request so rotation takes effect immediately.
+async fn require_token(
+    State(state): State<ProxyState>,
+    request: Request,
+    next: Next,
+) -> axum::response::Response {
+    let expected = state
+        .manager
+        .lock()
+        .await
+        .get_config()
+        .proxy_token()
+        .map(str::to_string);
+    if let Some(expected) = expected {
+        let provided = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        if provided != Some(expected.as_str()) {
+            return (StatusCode::UNAUTHORIZED, "Missing or invalid proxy token").into_response();
+        }
+    }
+    next.run(request).await
+}
+
 // ---------------------------------------------------------------------------
 // Health & discovery endpoints
 // ---------------------------------------------------------------------------
 
-/// GET /health
+/// GET /health — `status` is `ok` when every MCP that should be up is
+/// connected, `degraded` when only some are, and `down` (with a 503) when
+/// none are.  Enabled MCPs without `auto_start`, or disconnected for
+/// inactivity, are not expected to be up and only count while connected.
 async fn health_check(State(state): State<ProxyState>) -> impl IntoResponse {
     let mgr = state.manager.lock().await;
     let statuses = mgr.list_statuses().await;
@@ -71,14 +153,103 @@ async fn health_check(State(state): State<ProxyState>) -> impl IntoResponse {
         .filter(|s| s.state == crate::types::ConnectionState::Connected)
         .count();
 
-    Json(serde_json::json!({
-        "status": "ok",
-        "total_mcps": statuses.len(),
-        "connected_mcps": connected,
-        "timestamp": chrono::Utc::now().to_rfc3339()
-    }))
+    let mut expected = 0;
+    let mut expected_connected = 0;
+    for mcp in mgr.get_config().mcps.iter().filter(|m| m.enabled) {
+        let Some(conn) = mgr.get_connection(&mcp.id) else {
+            continue;
+        };
+        let is_connected = conn.get_state().await == crate::types::ConnectionState::Connected;
+        if is_connected || (mcp.auto_start && !conn.is_idle_disconnected().await) {
+            expected += 1;
+            expected_connected += usize::from(is_connected);
+        }
+    }
+    let (status, code) = if expected_connected == expected {
+        ("ok", StatusCode::OK)
+    } else if expected_connected > 0 {
+        ("degraded", StatusCode::OK)
+    } else {
+        ("down", StatusCode::SERVICE_UNAVAILABLE)
+    };
+
+    (
+        code,
+        Json(serde_json::json!({
+            "status": status,
+            "total_mcps": statuses.len(),
+            "connected_mcps": connected,
+            "timestamp": chrono::Utc::now().to_rfc3339()
+        })),
+    )
+}
+
+/// GET / — a self-contained page polling /health and /mcps, served only
+/// with `enable_status_page`.  Like /mcps it needs the proxy token, if one
+/// is set, as a bearer `Authorization` header.
+async fn status_page(State(state): State<ProxyState>) -> axum::response::Response {
+    if !state.manager.lock().await.get_config().enable_status_page {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    axum::response::Html(STATUS_PAGE_HTML).into_response()
+}
+
+const STATUS_PAGE_HTML: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Local MCP Proxy</title>
+<style>
+  body { font: 14px system-ui, sans-serif; margin: 2em; color: #1e293b; }
+  table { border-collapse: collapse; min-width: 40em; }
+  th, td { text-align: left; padding: 0.4em 0.8em; border-bottom: 1px solid #e2e8f0; }
+  .connected { color: #059669; } .connecting, .reconnecting { color: #2563eb; }
+  .error { color: #dc2626; } .disconnected { color: #64748b; }
+</style>
+</head>
+<body>
+<h1>Local MCP Proxy</h1>
+<p id="health">Loading&hellip;</p>
+<table id="mcp-status">
+  <thead><tr><th>Name</th><th>State</th><th>Transport</th><th>Tools</th><th>Error</th></tr></thead>
+  <tbody></tbody>
+</table>
+<script>
+function cell(row, text, className) {
+  const td = row.insertCell();
+  td.textContent = text ?? "";
+  if (className) td.className = className;
 }
 
+async function refresh() {
+  try {
+    const health = await (await fetch("/health")).json();
+    document.getElementById("health").textContent =
+      `Status: ${health.status} (${health.connected_mcps}/${health.total_mcps} connected)`;
+    const response = await fetch("/mcps");
+    if (!response.ok) throw new Error(`/mcps returned ${response.status}`);
+    const body = document.querySelector("#mcp-status tbody");
+    body.replaceChildren();
+    for (const mcp of await response.json()) {
+      const row = body.insertRow();
+      cell(row, mcp.name);
+      cell(row, mcp.state, mcp.state);
+      cell(row, mcp.resolved_transport ?? mcp.transport_type);
+      cell(row, mcp.tools_count);
+      cell(row, mcp.error_message);
+    }
+  } catch (e) {
+    document.getElementById("health").textContent = `Proxy unreachable: ${e.message}`;
+  }
+}
+
+refresh();
+setInterval(refresh, 5000);
+</script>
+</body>
+</html>
+"##;
+
 /// GET /mcps
 async fn list_mcps(State(state): State<ProxyState>) -> impl IntoResponse {
     let mgr = state.manager.lock().await;
@@ -86,66 +257,419 @@ async fn list_mcps(State(state): State<ProxyState>) -> impl IntoResponse {
     Json(statuses)
 }
 
+/// GET /summary — status counts only, for frequent polling
+async fn status_summary(State(state): State<ProxyState>) -> impl IntoResponse {
+    let mgr = state.manager.lock().await;
+    Json(mgr.status_summary().await)
+}
+
 // ---------------------------------------------------------------------------
 // MCP Streamable HTTP transport  (spec 2025-03-26)
 // ---------------------------------------------------------------------------
 
 /// GET /mcp/:id — Open SSE stream for server-initiated notifications.
-/// Per the Streamable HTTP spec this is optional; we return 405 for now
-/// since we don't relay server notifications yet.
+/// Only proxy-originated notifications (e.g. `list_changed` after the
+/// disabled tools/resources change) are relayed; upstream server
+/// notifications are not forwarded yet.
 async fn streamable_http_get(
     Path(id): Path<String>,
     State(state): State<ProxyState>,
-) -> StatusCode {
-    let mgr = state.manager.lock().await;
-    let Some(conn) = mgr.get_connection(&id) else {
-        return StatusCode::NOT_FOUND;
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    let receiver = {
+        let mgr = state.manager.lock().await;
+        let conn = mgr.get_connection(&id).ok_or(StatusCode::NOT_FOUND)?;
+
+        let mcp_state = conn.get_state().await;
+        if mcp_state != crate::types::ConnectionState::Connected {
+            return Err(StatusCode::SERVICE_UNAVAILABLE);
+        }
+
+        mgr.subscribe_notifications()
     };
 
-    let mcp_state = conn.get_state().await;
-    if mcp_state != crate::types::ConnectionState::Connected {
-        return StatusCode::SERVICE_UNAVAILABLE;
-    }
+    let stream = futures::stream::unfold(receiver, move |mut receiver| {
+        let id = id.clone();
+        async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(notification) if notification.mcp_id == id => {
+                        let event = Event::default()
+                            .event("message")
+                            .data(notification.message.to_string());
+                        return Some((Ok(event), receiver));
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!(
+                            "MCP '{}': SSE client lagged, dropped {} notifications",
+                            id,
+                            skipped
+                        );
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        }
+    });
 
-    // The Streamable HTTP spec says GET is for server-initiated messages.
-    // We don't proxy those yet, so return 405 Method Not Allowed.
-    StatusCode::METHOD_NOT_ALLOWED
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
 }
 
 /// POST /mcp/:id — Main JSON-RPC endpoint.
 /// Accepts a single JSON-RPC request object or a batch (JSON array).
 /// Returns `application/json` with the JSON-RPC response(s), or 202 for
-/// pure notification messages (no `id` field).
+/// pure notification messages (no `id` field).  A `tools/call` that asks
+/// for progress may instead be answered with an SSE stream (see
+/// `stream_tool_call`).
 async fn streamable_http_post(
     Path(id): Path<String>,
     State(state): State<ProxyState>,
-    Json(body): Json<serde_json::Value>,
+    headers: HeaderMap,
+    raw_body: axum::body::Bytes,
 ) -> Result<axum::response::Response, StatusCode> {
-    let mgr = state.manager.lock().await;
-    let conn = mgr.get_connection(&id).ok_or(StatusCode::NOT_FOUND)?;
-    let disabled = mgr.get_disabled_items(&id);
+    // Parse by hand so malformed input gets a JSON-RPC error envelope
+    // instead of axum's plain-text extractor rejection
+    let body = match serde_json::from_slice::<serde_json::Value>(&raw_body) {
+        Ok(body @ (serde_json::Value::Object(_) | serde_json::Value::Array(_))) => body,
+        Ok(_) => return Ok(jsonrpc_error(serde_json::Value::Null, -32600, "Invalid Request")),
+        Err(e) => {
+            tracing::debug!("MCP '{}': unparseable request body: {}", id, e);
+            return Ok(jsonrpc_error(serde_json::Value::Null, -32700, "Parse error"));
+        }
+    };
+
+    // Release the manager lock before forwarding so a slow upstream doesn't
+    // block every other command and proxy request.
+    let (conn, disabled, safe_mode) = {
+        let mgr = state.manager.lock().await;
+        let conn = mgr.get_connection(&id).ok_or(StatusCode::NOT_FOUND)?;
+        (conn, mgr.get_disabled_items(&id).await, mgr.safe_mode_active())
+    };
+
+    if let Some(requests) = body.as_array() {
+        if let Err(message) = validate_batch(requests) {
+            return Ok(jsonrpc_error(serde_json::Value::Null, -32600, &message));
+        }
+    }
+
+    let mcp_state = conn.get_state().await;
+    if let Some(response) = busy_response(&body, mcp_state) {
+        tracing::debug!("MCP '{}': {:?}, asking the client to retry", id, mcp_state);
+        return Ok(response);
+    }
+
+    // Connect lazily instead of making the client wait for the health loop.
+    // Safe mode leaves connecting to the user.
+    if conn.config.enabled && mcp_state != crate::types::ConnectionState::Connected {
+        if safe_mode && !conn.is_user_requested() {
+            let message = format!("MCP '{}' is not connected (safe mode)", id);
+            return Ok(error_response(&body, -32000, &message, None));
+        }
+        tracing::info!("MCP '{}': not connected, connecting on demand", id);
+        if let Err(e) = conn.ensure_connected().await {
+            let message = format!("MCP '{}' is not connected: {:#}", id, e);
+            return Ok(error_response(&body, -32000, &message, None));
+        }
+    }
+
+    let forwarded = forwarded_headers(&headers, &conn.config.forward_headers);
+    let event_stream = prefers_event_stream(&headers);
 
     // Batch request
     if let Some(requests) = body.as_array() {
+        let request_ids: HashSet<String> = requests
+            .iter()
+            .filter_map(|r| r.get("id"))
+            .map(|id| id.to_string())
+            .collect();
+
         let mut responses = Vec::new();
         for req in requests {
-            if let Some(resp) = handle_single_request(req, &conn, &disabled).await {
-                responses.push(resp);
+            if let Some(resp) = handle_single_request(req, &conn, &disabled, None, &forwarded).await
+            {
+                let response_id = resp.get("id").map(|id| id.to_string()).unwrap_or_default();
+                if request_ids.contains(&response_id) {
+                    responses.push(resp);
+                } else {
+                    tracing::warn!(
+                        "MCP '{}': dropping batch response with unmatched id {}",
+                        id,
+                        response_id
+                    );
+                }
             }
         }
         if responses.is_empty() {
             return Ok(StatusCode::ACCEPTED.into_response());
         }
-        return Ok(Json(serde_json::Value::Array(responses)).into_response());
+        return Ok(rpc_response(serde_json::Value::Array(responses), event_stream));
     }
 
     // Single request
-    match handle_single_request(&body, &conn, &disabled).await {
-        Some(resp) => Ok(Json(resp).into_response()),
+    if let Some(progress_token) = streamed_progress_token(&body, &headers) {
+        return Ok(
+            stream_tool_call(body, conn, disabled, progress_token, forwarded, event_stream).await,
+        );
+    }
+    let started = std::time::Instant::now();
+    match handle_single_request(&body, &conn, &disabled, None, &forwarded).await {
+        Some(resp) => Ok(with_server_timing(rpc_response(resp, event_stream), started.elapsed())),
         None => Ok(StatusCode::ACCEPTED.into_response()),
     }
 }
 
+/// Whether the client's Accept header ranks `text/event-stream` above
+/// `application/json`. Ties, as in the usual `application/json,
+/// text/event-stream`, keep the JSON default.
+fn prefers_event_stream(headers: &HeaderMap) -> bool {
+    let Some(accept) = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    // Highest q-value of any media range covering `media_type`
+    let quality = |media_type: &str| -> f32 {
+        let (kind, _) = media_type.split_once('/').unwrap_or((media_type, ""));
+        accept
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';');
+                let range = parts.next()?.trim().to_ascii_lowercase();
+                let covers = range == media_type
+                    || range == "*/*"
+                    || range.strip_suffix("/*") == Some(kind);
+                if !covers {
+                    return None;
+                }
+                let q = parts
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .find_map(|q| q.trim().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                Some(q)
+            })
+            .fold(0.0, f32::max)
+    };
+    quality("text/event-stream") > quality("application/json")
+}
+
+/// A JSON-RPC response as plain JSON, or framed as a single SSE `message`
+/// event for clients that prefer an event stream
+fn rpc_response(response: serde_json::Value, event_stream: bool) -> axum::response::Response {
+    if !event_stream {
+        return Json(response).into_response();
+    }
+    let event = Event::default().event("message").data(response.to_string());
+    Sse::new(futures::stream::once(async move { Ok::<_, Infallible>(event) })).into_response()
+}
+
+/// The client's values for the headers named in `allowlist`, matched
+/// case-insensitively
+fn forwarded_headers(headers: &HeaderMap, allowlist: &[String]) -> ForwardedHeaders {
+    ForwardedHeaders(
+        allowlist
+            .iter()
+            .flat_map(|name| {
+                let name = name.to_ascii_lowercase();
+                headers
+                    .get_all(name.as_str())
+                    .iter()
+                    .filter_map(|value| value.to_str().ok())
+                    .map(|value| (name.clone(), value.to_string()))
+                    .collect::<Vec<_>>()
+            })
+            .collect(),
+    )
+}
+
+/// Report the time spent handling the request upstream as
+/// `Server-Timing: upstream;dur=<ms>`, so clients can tell it apart from
+/// proxy overhead
+fn with_server_timing(
+    mut response: axum::response::Response,
+    upstream: std::time::Duration,
+) -> axum::response::Response {
+    let timing = format!("upstream;dur={:.3}", upstream.as_secs_f64() * 1000.0);
+    if let Ok(value) = header::HeaderValue::from_str(&timing) {
+        response.headers_mut().insert("server-timing", value);
+    }
+    response
+}
+
+/// The client's progress token, if `body` is a `tools/call` whose progress
+/// can be streamed back (the client sent a token and accepts SSE)
+fn streamed_progress_token(
+    body: &serde_json::Value,
+    headers: &HeaderMap,
+) -> Option<serde_json::Value> {
+    let accepts_sse = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/event-stream"));
+    if !accepts_sse || body.get("method").and_then(|m| m.as_str()) != Some("tools/call") {
+        return None;
+    }
+    body.get("id")?;
+    body.pointer("/params/_meta/progressToken").cloned()
+}
+
+/// Run a `tools/call`, answering as `rpc_response` does if the result
+/// arrives before any progress.  Once the upstream reports progress the response
+/// switches to `text/event-stream`: each update is sent as a
+/// `notifications/progress` event carrying the client's token, and the
+/// JSON-RPC response is the final event.
+async fn stream_tool_call(
+    body: serde_json::Value,
+    conn: Arc<McpConnection>,
+    disabled: DisabledItems,
+    progress_token: serde_json::Value,
+    forwarded: ForwardedHeaders,
+    event_stream: bool,
+) -> axum::response::Response {
+    let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
+    let request_id = body.get("id").cloned().unwrap_or_default();
+    let mut call = tokio::spawn(async move {
+        handle_single_request(&body, &conn, &disabled, Some(&progress_tx), &forwarded).await
+    });
+
+    let first = tokio::select! {
+        biased;
+        Some(update) = progress_rx.recv() => update,
+        result = &mut call => {
+            return match result {
+                Ok(Some(resp)) => rpc_response(resp, event_stream),
+                Ok(None) => StatusCode::ACCEPTED.into_response(),
+                Err(e) => jsonrpc_error(request_id, -32603, &format!("Internal error: {}", e)),
+            };
+        }
+    };
+
+    let progress_event = move |update: rmcp::model::ProgressNotificationParam| {
+        let mut params = serde_json::to_value(&update).unwrap_or_default();
+        params["progressToken"] = progress_token.clone();
+        let notification = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/progress",
+            "params": params
+        });
+        Ok::<_, Infallible>(Event::default().event("message").data(notification.to_string()))
+    };
+    // The channel closes once the call finishes, so the result always
+    // follows the last progress event
+    let updates = futures::stream::once(async move { first })
+        .chain(tokio_stream::wrappers::UnboundedReceiverStream::new(progress_rx))
+        .map(progress_event);
+    let result = futures::stream::once(async move {
+        let response = match call.await {
+            Ok(Some(resp)) => resp,
+            Ok(None) => serde_json::Value::Null,
+            Err(e) => serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": request_id,
+                "error": { "code": -32603, "message": format!("Internal error: {}", e) }
+            }),
+        };
+        Ok(Event::default().event("message").data(response.to_string()))
+    });
+
+    Sse::new(updates.chain(result)).into_response()
+}
+
+/// Reject batches the JSON-RPC spec treats as invalid: empty arrays and
+/// (for our purposes) duplicate request ids, which would make responses
+/// ambiguous to correlate.
+fn validate_batch(requests: &[serde_json::Value]) -> Result<(), String> {
+    if requests.is_empty() {
+        return Err("Invalid Request: empty batch".to_string());
+    }
+
+    let mut seen = HashSet::new();
+    for id in requests.iter().filter_map(|r| r.get("id")) {
+        if !seen.insert(id.to_string()) {
+            return Err(format!("Invalid Request: duplicate id {} in batch", id));
+        }
+    }
+
+    Ok(())
+}
+
+/// Seconds clients are asked to wait before retrying while an MCP is
+/// (re)connecting
+const RECONNECT_RETRY_AFTER_SECS: u64 = 2;
+
+/// A -32003 "server reconnecting" reply for every request in `body` while
+/// `state` is a connect in progress, with the retry delay both as a
+/// `Retry-After` header and as `retryAfter` (seconds) in the error data
+fn busy_response(
+    body: &serde_json::Value,
+    state: crate::types::ConnectionState,
+) -> Option<axum::response::Response> {
+    use crate::types::ConnectionState;
+    if !matches!(state, ConnectionState::Connecting | ConnectionState::Reconnecting) {
+        return None;
+    }
+    let data = serde_json::json!({ "retryAfter": RECONNECT_RETRY_AFTER_SECS });
+    let mut response = error_response(
+        body,
+        -32003,
+        "Server reconnecting, retry shortly",
+        Some(data),
+    );
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, header::HeaderValue::from(RECONNECT_RETRY_AFTER_SECS));
+    Some(response)
+}
+
+/// Build a JSON-RPC error reply for every request in `body` (single or
+/// batch).  Falls back to 202 when the body only contains notifications.
+fn error_response(
+    body: &serde_json::Value,
+    code: i64,
+    message: &str,
+    data: Option<serde_json::Value>,
+) -> axum::response::Response {
+    let error_for = |req: &serde_json::Value| {
+        req.get("id").map(|id| {
+            let mut error = serde_json::json!({
+                "code": code,
+                "message": message
+            });
+            if let Some(data) = &data {
+                error["data"] = data.clone();
+            }
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": error
+            })
+        })
+    };
+
+    if let Some(requests) = body.as_array() {
+        let errors: Vec<_> = requests.iter().filter_map(error_for).collect();
+        if errors.is_empty() {
+            return StatusCode::ACCEPTED.into_response();
+        }
+        return Json(serde_json::Value::Array(errors)).into_response();
+    }
+
+    match error_for(body) {
+        Some(error) => Json(error).into_response(),
+        None => StatusCode::ACCEPTED.into_response(),
+    }
+}
+
+/// A single JSON-RPC error reply with the given id
+fn jsonrpc_error(id: serde_json::Value, code: i64, message: &str) -> axum::response::Response {
+    Json(serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {
+            "code": code,
+            "message": message
+        }
+    }))
+    .into_response()
+}
+
 /// DELETE /mcp/:id — Session termination (acknowledge and no-op).
 async fn streamable_http_delete(
     Path(id): Path<String>,
@@ -159,12 +683,118 @@ async fn streamable_http_delete(
     }
 }
 
+/// Server -> client requests (client capabilities).  The proxy answers these
+/// itself with -32601 rather than forwarding them to an upstream that would
+/// not understand them.
+const CLIENT_SIDE_METHODS: &[&str] = &[
+    "sampling/createMessage",
+    "roots/list",
+    "elicitation/create",
+];
+
+/// Capabilities the proxy advertises in its `initialize` result
+fn proxy_capabilities() -> serde_json::Value {
+    serde_json::json!({
+        "tools": { "listChanged": true },
+        "resources": { "subscribe": false, "listChanged": true },
+        "prompts": { "listChanged": false }
+    })
+}
+
 /// Dispatch a single JSON-RPC request object.
 /// Returns `None` for notifications (requests without an `id`).
+///
+/// The client's `id` never reaches the upstream: requests are re-issued
+/// through the rmcp client, which assigns its own per-connection ids and
+/// matches responses itself, and the reply is built here with the caller's
+/// original `id`.  Identical ids from different sessions therefore cannot
+/// collide.
+///
+/// With `record_requests` on, the exchange is kept for `get_recent_requests`.
 async fn handle_single_request(
     request: &serde_json::Value,
     conn: &McpConnection,
-    disabled: &(Vec<String>, Vec<String>),
+    disabled: &DisabledItems,
+    progress: Option<&ProgressSender>,
+    forwarded: &ForwardedHeaders,
+) -> Option<serde_json::Value> {
+    let started = std::time::Instant::now();
+    let response = respond_single_request(request, conn, disabled, progress, forwarded).await;
+
+    let bytes_in = json_size(request);
+    let bytes_out = response.as_ref().map_or(0, json_size);
+    tracing::debug!(
+        "MCP '{}': request {} bytes, response {} bytes",
+        conn.config.id,
+        bytes_in,
+        bytes_out
+    );
+    conn.record_traffic(bytes_in, bytes_out);
+
+    if conn.records_requests() {
+        if let Some(method) = request.get("method").and_then(|m| m.as_str()) {
+            conn.record_request(method, request, response.as_ref(), started.elapsed())
+                .await;
+        }
+    }
+    response
+}
+
+/// Drop disabled tools from an upstream tools/list and apply description
+/// overrides and aliases.  Lists can run to thousands of tools, so the
+/// disabled list is indexed once and unchanged tools are not reallocated.
+fn expose_tools(
+    tools: &mut Vec<serde_json::Value>,
+    disabled: &DisabledItems,
+    overrides: &HashMap<String, String>,
+    aliases: &ToolAliases,
+) {
+    let is_enabled = disabled.tool_filter();
+    tools.retain(|t| {
+        t.get("name")
+            .and_then(|n| n.as_str())
+            .map(&is_enabled)
+            .unwrap_or(true)
+    });
+    for tool in tools.iter_mut() {
+        let Some(name) = tool.get("name").and_then(|n| n.as_str()) else {
+            continue;
+        };
+        let description = overrides.get(name).cloned();
+        let alias = aliases.alias(name);
+        let renamed = (alias != name).then(|| alias.to_string());
+        if let Some(description) = description {
+            tool["description"] = serde_json::Value::String(description);
+        }
+        if let Some(alias) = renamed {
+            tool["name"] = serde_json::Value::String(alias);
+        }
+    }
+}
+
+/// Length of `value` serialized as compact JSON, counted without buffering
+fn json_size(value: &serde_json::Value) -> u64 {
+    struct Counter(u64);
+    impl std::io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len() as u64;
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let mut counter = Counter(0);
+    let _ = serde_json::to_writer(&mut counter, value);
+    counter.0
+}
+
+async fn respond_single_request(
+    request: &serde_json::Value,
+    conn: &McpConnection,
+    disabled: &DisabledItems,
+    progress: Option<&ProgressSender>,
+    forwarded: &ForwardedHeaders,
 ) -> Option<serde_json::Value> {
     let method = request.get("method")?.as_str()?;
     let params = request
@@ -185,11 +815,7 @@ async fn handle_single_request(
             "id": id,
             "result": {
                 "protocolVersion": "2025-03-26",
-                "capabilities": {
-                    "tools": { "listChanged": false },
-                    "resources": { "subscribe": false, "listChanged": false },
-                    "prompts": { "listChanged": false }
-                },
+                "capabilities": proxy_capabilities(),
                 "serverInfo": {
                     "name": "Local MCP Proxy",
                     "version": "0.1.0"
@@ -198,27 +824,92 @@ async fn handle_single_request(
         }));
     }
 
+    // Client-side methods would never be answered by an upstream server
+    if CLIENT_SIDE_METHODS.contains(&method) {
+        return Some(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {
+                "code": -32601,
+                "message": format!("Method not found: {} is a client capability", method),
+                "data": { "capabilities": proxy_capabilities() }
+            }
+        }));
+    }
+
+    let aliases = if method == "tools/list" || method == "tools/call" {
+        conn.tool_aliases().await
+    } else {
+        ToolAliases::default()
+    };
+
+    // Clients call aliased tools by their alias; the upstream only knows the original
+    let mut params = params;
+    if method == "tools/call" {
+        if let Some(name) = params.get("name").and_then(|n| n.as_str()) {
+            let original = aliases.original(name).to_string();
+            // Hidden tools are only left out of tools/list; refuse direct
+            // calls too so knowing the name isn't enough
+            if !disabled.is_tool_enabled(&original) {
+                return Some(serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {
+                        "code": -32602,
+                        "message": format!("Tool '{}' is disabled", name)
+                    }
+                }));
+            }
+            if let Some(defaults) = conn.config.tool_defaults.get(&original) {
+                if let Some(params) = params.as_object_mut() {
+                    let arguments = params.entry("arguments").or_insert(serde_json::Value::Null);
+                    if arguments.is_null() {
+                        *arguments = serde_json::json!({});
+                    }
+                    merge_defaults(arguments, defaults);
+                }
+            }
+            params["name"] = serde_json::Value::String(original);
+        }
+    }
+
     // Forward everything else to the underlying MCP server
-    match conn.execute_request(method, params).await {
+    match conn
+        .execute_request_with_progress(method, params, progress, forwarded)
+        .await
+    {
         Ok(mut result) => {
-            // Filter disabled tools from tools/list responses
+            // Filter disabled tools from tools/list responses, apply description
+            // overrides and expose aliases
             if method == "tools/list" {
                 if let Some(tools) = result.get_mut("tools").and_then(|t| t.as_array_mut()) {
-                    tools.retain(|t| {
-                        t.get("name")
-                            .and_then(|n| n.as_str())
-                            .map(|name| !disabled.0.contains(&name.to_string()))
-                            .unwrap_or(true)
-                    });
+                    let overrides = &conn.config.tool_description_overrides;
+                    expose_tools(tools, disabled, overrides, &aliases);
                 }
             }
             // Filter disabled resources from resources/list responses
             if method == "resources/list" {
                 if let Some(resources) = result.get_mut("resources").and_then(|r| r.as_array_mut()) {
+                    let is_enabled = disabled.resource_filter();
                     resources.retain(|r| {
                         r.get("uri")
                             .and_then(|u| u.as_str())
-                            .map(|uri| !disabled.1.contains(&uri.to_string()))
+                            .map(&is_enabled)
+                            .unwrap_or(true)
+                    });
+                }
+            }
+            // Templates are matched by their `uriTemplate` against the same
+            // disabled-resource patterns, so `file:///secret/*` hides both
+            if method == "resources/templates/list" {
+                if let Some(templates) =
+                    result.get_mut("resourceTemplates").and_then(|t| t.as_array_mut())
+                {
+                    let is_enabled = disabled.resource_filter();
+                    templates.retain(|t| {
+                        t.get("uriTemplate")
+                            .and_then(|u| u.as_str())
+                            .map(&is_enabled)
                             .unwrap_or(true)
                     });
                 }
@@ -230,7 +921,11 @@ async fn handle_single_request(
             }))
         }
         Err(e) => {
-            let code = if e.to_string().contains("Method not found") {
+            let code = if e.downcast_ref::<TooManyRequests>().is_some() {
+                -32029 // Too many concurrent requests
+            } else if e.downcast_ref::<InvalidParams>().is_some() {
+                -32602 // Invalid params
+            } else if e.to_string().contains("Method not found") {
                 -32601 // Method not found
             } else {
                 -32000 // Server error
@@ -247,6 +942,138 @@ async fn handle_single_request(
     }
 }
 
+/// Fill in `defaults` wherever `target` lacks a value, recursing into
+/// objects present on both sides; anything the client sent wins
+fn merge_defaults(target: &mut serde_json::Value, defaults: &serde_json::Value) {
+    let (Some(target), Some(defaults)) = (target.as_object_mut(), defaults.as_object()) else {
+        return;
+    };
+    for (key, default) in defaults {
+        match target.get_mut(key) {
+            Some(value) => merge_defaults(value, default),
+            None => {
+                target.insert(key.clone(), default.clone());
+            }
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Legacy HTTP+SSE transport  (spec 2024-11-05)
+// ---------------------------------------------------------------------------
+
+/// Removes a legacy session from the registry when its SSE stream is dropped
+struct LegacySessionGuard {
+    session_id: String,
+    sessions: LegacySessions,
+}
+
+impl Drop for LegacySessionGuard {
+    fn drop(&mut self) {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.remove(&self.session_id);
+        }
+    }
+}
+
+/// GET /mcp/:id/sse — Open a legacy SSE session.  The first event is
+/// `endpoint`, naming the `/messages` URL to POST requests to; responses to
+/// those requests and proxy notifications for this MCP follow as `message`
+/// events.
+async fn legacy_sse_get(
+    Path(id): Path<String>,
+    State(state): State<ProxyState>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    let notifications = {
+        let mgr = state.manager.lock().await;
+        mgr.get_connection(&id).ok_or(StatusCode::NOT_FOUND)?;
+        mgr.subscribe_notifications()
+    };
+
+    let session_id = uuid::Uuid::new_v4().simple().to_string();
+    let (tx, rx) = mpsc::unbounded_channel();
+    state
+        .legacy_sessions
+        .lock()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .insert(session_id.clone(), (id.clone(), tx));
+    tracing::debug!("MCP '{}': legacy SSE session {} opened", id, session_id);
+
+    let endpoint = Event::default()
+        .event("endpoint")
+        .data(format!("/mcp/{}/messages?sessionId={}", id, session_id));
+    let guard = LegacySessionGuard {
+        session_id,
+        sessions: Arc::clone(&state.legacy_sessions),
+    };
+    let responses = futures::stream::unfold((rx, guard), |(mut rx, guard)| async move {
+        let message = rx.recv().await?;
+        Some((message, (rx, guard)))
+    });
+    let notifications = futures::stream::unfold(notifications, move |mut receiver| {
+        let id = id.clone();
+        async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(notification) if notification.mcp_id == id => {
+                        return Some((notification.message, receiver));
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        }
+    });
+
+    let messages = futures::stream::select(responses, notifications).map(|message| {
+        Ok(Event::default().event("message").data(message.to_string()))
+    });
+    let stream = futures::stream::once(async move { Ok(endpoint) }).chain(messages);
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[derive(serde::Deserialize)]
+struct LegacySessionQuery {
+    #[serde(rename = "sessionId")]
+    session_id: String,
+}
+
+/// POST /mcp/:id/messages?sessionId=... — Accept a JSON-RPC message for a
+/// legacy SSE session.  It is handled like `POST /mcp/:id`, but the reply
+/// is delivered on the session's SSE stream and the POST itself gets a 202.
+/// Sessions only accept messages for the MCP they were opened for.
+async fn legacy_messages_post(
+    Path(id): Path<String>,
+    Query(query): Query<LegacySessionQuery>,
+    State(state): State<ProxyState>,
+    mut headers: HeaderMap,
+    raw_body: axum::body::Bytes,
+) -> Result<StatusCode, StatusCode> {
+    let sender = state
+        .legacy_sessions
+        .lock()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .get(&query.session_id)
+        .filter(|(mcp_id, _)| *mcp_id == id)
+        .map(|(_, sender)| sender.clone())
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    // The reply is relayed as one JSON message, so never ask for SSE framing
+    headers.insert(header::ACCEPT, header::HeaderValue::from_static("application/json"));
+    let response = streamable_http_post(Path(id), State(state), headers, raw_body).await?;
+    if response.status() == StatusCode::ACCEPTED {
+        return Ok(StatusCode::ACCEPTED);
+    }
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let message: serde_json::Value =
+        serde_json::from_slice(&body).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // The client has gone away if the stream was closed; nothing to deliver to
+    let _ = sender.send(message);
+    Ok(StatusCode::ACCEPTED)
+}
+
 // ---------------------------------------------------------------------------
 // Convenience endpoints (non-MCP-transport)
 // ---------------------------------------------------------------------------
@@ -258,12 +1085,21 @@ async fn list_tools(
 ) -> Result<impl IntoResponse, StatusCode> {
     let mgr = state.manager.lock().await;
     let conn = mgr.get_connection(&id).ok_or(StatusCode::NOT_FOUND)?;
-    let (disabled_tools, _) = mgr.get_disabled_items(&id);
+    let disabled = mgr.get_disabled_items(&id).await;
+    let aliases = conn.tool_aliases().await;
+    let is_enabled = disabled.tool_filter();
     let tools: Vec<_> = conn
         .get_tools()
         .await
         .into_iter()
-        .filter(|t| !disabled_tools.contains(&t.name))
+        .filter(|t| is_enabled(&t.name))
+        .map(|mut t| {
+            if let Some(description) = conn.config.tool_description_overrides.get(&t.name) {
+                t.description = Some(description.clone());
+            }
+            t.name = aliases.alias(&t.name).to_string();
+            t
+        })
         .collect();
     Ok(Json(tools))
 }
@@ -275,12 +1111,1324 @@ async fn list_resources(
 ) -> Result<impl IntoResponse, StatusCode> {
     let mgr = state.manager.lock().await;
     let conn = mgr.get_connection(&id).ok_or(StatusCode::NOT_FOUND)?;
-    let (_, disabled_resources) = mgr.get_disabled_items(&id);
+    let disabled = mgr.get_disabled_items(&id).await;
+    let is_enabled = disabled.resource_filter();
     let resources: Vec<_> = conn
         .get_resources()
         .await
         .into_iter()
-        .filter(|r| !disabled_resources.contains(&r.uri))
+        .filter(|r| is_enabled(&r.uri))
         .collect();
     Ok(Json(resources))
 }
+
+#[derive(serde::Deserialize)]
+struct ResourceQuery {
+    uri: String,
+}
+
+/// GET /mcp/:id/resource?uri=... — Read a resource.  A single blob content
+/// is returned as raw bytes with its MIME type so browsers and tools can
+/// fetch images/PDFs directly; anything else is the JSON `resources/read`
+/// result.
+async fn read_resource(
+    Path(id): Path<String>,
+    Query(query): Query<ResourceQuery>,
+    State(state): State<ProxyState>,
+) -> Result<axum::response::Response, StatusCode> {
+    let (conn, disabled) = {
+        let mgr = state.manager.lock().await;
+        let conn = mgr.get_connection(&id).ok_or(StatusCode::NOT_FOUND)?;
+        (conn, mgr.get_disabled_items(&id).await)
+    };
+    if !disabled.is_resource_enabled(&query.uri) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let result = conn
+        .execute_request("resources/read", serde_json::json!({ "uri": query.uri }))
+        .await
+        .map_err(|e| {
+            tracing::warn!("MCP '{}': resources/read {} failed: {:#}", id, query.uri, e);
+            StatusCode::BAD_GATEWAY
+        })?;
+
+    let single_blob = match result.get("contents").and_then(|c| c.as_array()) {
+        Some(contents) if contents.len() == 1 => contents[0]
+            .get("blob")
+            .and_then(|b| b.as_str())
+            .map(|blob| (blob, contents[0].get("mimeType").and_then(|m| m.as_str()))),
+        _ => None,
+    };
+
+    match single_blob {
+        Some((blob, mime_type)) => {
+            use base64::Engine;
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(blob)
+                .map_err(|_| StatusCode::BAD_GATEWAY)?;
+            let content_type = mime_type.unwrap_or("application/octet-stream").to_string();
+            Ok((
+                [
+                    (axum::http::header::CONTENT_TYPE, content_type),
+                    (axum::http::header::CONTENT_LENGTH, bytes.len().to_string()),
+                ],
+                bytes,
+            )
+                .into_response())
+        }
+        None => Ok(Json(result).into_response()),
+    }
+}
+
+/// GET /mcp/:id/openapi — OpenAPI 3.1 document describing the enabled tools
+async fn openapi_spec(
+    Path(id): Path<String>,
+    State(state): State<ProxyState>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let mgr = state.manager.lock().await;
+    let conn = mgr.get_connection(&id).ok_or(StatusCode::NOT_FOUND)?;
+    let disabled = mgr.get_disabled_items(&id).await;
+    let aliases = conn.tool_aliases().await;
+    let tools: Vec<_> = conn
+        .get_tools()
+        .await
+        .into_iter()
+        .filter(|t| disabled.is_tool_enabled(&t.name))
+        .map(|mut t| {
+            if let Some(description) = conn.config.tool_description_overrides.get(&t.name) {
+                t.description = Some(description.clone());
+            }
+            t.name = aliases.alias(&t.name).to_string();
+            t
+        })
+        .collect();
+    let spec = super::openapi::build_spec(&conn.config.name, &mgr.get_proxy_url(&id), &tools);
+    Ok(Json(spec))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{
+        http_mcp_config, mcp_config, post_rpc, spawn_proxy, tool_call, unreachable_url,
+        FakeUpstream, FAKE_PNG, FAKE_PROGRESS_TOOL,
+    };
+    use crate::types::{AppConfig, ConnectionState, McpServerConfig, TrafficStats};
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+
+    /// A manager for `config` after `initialize`, served on a random port
+    async fn serve(config: AppConfig) -> (Arc<Mutex<McpManager>>, String) {
+        let mut mgr = McpManager::new(config);
+        mgr.initialize(None).await;
+        let manager = Arc::new(Mutex::new(mgr));
+        let base = spawn_proxy(Arc::clone(&manager)).await;
+        (manager, base)
+    }
+
+    fn config_with(mcps: Vec<McpServerConfig>) -> AppConfig {
+        AppConfig {
+            mcps,
+            ..AppConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn proxy_server_reports_its_bound_address() {
+        let manager = Arc::new(Mutex::new(McpManager::new(AppConfig::default())));
+        let (bound_tx, bound_rx) = oneshot::channel();
+        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        tokio::spawn(start_proxy_server(vec![addr], manager, bound_tx));
+
+        let bound = bound_rx.await.expect("listener bound");
+        assert_eq!(bound.ip(), addr.ip());
+        assert_ne!(bound.port(), 0);
+    }
+
+    #[tokio::test]
+    async fn proxy_server_drops_the_bound_sender_when_binding_fails() {
+        let taken = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let manager = Arc::new(Mutex::new(McpManager::new(AppConfig::default())));
+        let (bound_tx, bound_rx) = oneshot::channel();
+
+        let result = start_proxy_server(vec![taken.local_addr().unwrap()], manager, bound_tx).await;
+
+        assert!(result.is_err());
+        assert!(bound_rx.await.is_err());
+    }
+
+    #[tokio::test]
+    async fn extra_listeners_serve_the_same_router_and_skip_failed_binds() {
+        let taken = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let extra = {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap()
+        };
+        let manager = Arc::new(Mutex::new(McpManager::new(AppConfig::default())));
+        let (bound_tx, bound_rx) = oneshot::channel();
+        let addrs = vec![
+            "127.0.0.1:0".parse().unwrap(),
+            taken.local_addr().unwrap(),
+            extra,
+        ];
+        tokio::spawn(start_proxy_server(addrs, manager, bound_tx));
+        bound_rx.await.expect("primary listener bound");
+
+        let mut last_err = None;
+        for _ in 0..50 {
+            match reqwest::get(format!("http://{}/health", extra)).await {
+                Ok(response) => {
+                    assert!(response.status().is_success());
+                    return;
+                }
+                Err(e) => last_err = Some(e),
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        panic!("extra listener never answered: {:?}", last_err);
+    }
+
+    #[tokio::test]
+    async fn proxy_token_guards_everything_but_health_and_rotates_at_once() {
+        let (manager, base) = serve(AppConfig {
+            proxy_auth_token: Some("first".to_string()),
+            ..AppConfig::default()
+        })
+        .await;
+        let client = reqwest::Client::new();
+        let status = |path: &'static str, token: Option<&'static str>| {
+            let mut request = client.get(format!("{}{}", base, path));
+            if let Some(token) = token {
+                request = request.bearer_auth(token);
+            }
+            async move { request.send().await.unwrap().status() }
+        };
+
+        assert_eq!(status("/mcps", None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status("/mcps", Some("wrong")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status("/mcps", Some("first")).await, StatusCode::OK);
+        assert_ne!(status("/health", None).await, StatusCode::UNAUTHORIZED);
+
+        manager
+            .lock()
+            .await
+            .set_proxy_auth_token("second".to_string());
+        assert_eq!(status("/mcps", Some("first")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status("/mcps", Some("second")).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn an_empty_proxy_token_counts_as_unset() {
+        let (_manager, base) = serve(AppConfig {
+            proxy_auth_token: Some(String::new()),
+            ..AppConfig::default()
+        })
+        .await;
+
+        let response = reqwest::Client::new()
+            .get(format!("{}/mcps", base))
+            .header(header::AUTHORIZATION, "Bearer ")
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn request_connects_a_disconnected_mcp_on_demand() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let (manager, base) = serve(config_with(vec![http_mcp_config("fs", &upstream.url)])).await;
+        let conn = manager.lock().await.get_connection("fs").unwrap();
+        conn.disconnect().await;
+        assert_eq!(conn.get_state().await, ConnectionState::Disconnected);
+
+        let response = post_rpc(&base, "fs", tool_call(1, "echo")).await;
+
+        assert_eq!(response["result"]["content"][0]["text"], "echo from up");
+        assert_eq!(conn.get_state().await, ConnectionState::Connected);
+    }
+
+    #[tokio::test]
+    async fn disabling_a_tool_notifies_clients_on_the_get_stream() {
+        let upstream = FakeUpstream::spawn("up", &["echo", "write"]).await;
+        let (manager, base) = serve(config_with(vec![http_mcp_config("fs", &upstream.url)])).await;
+        let response = reqwest::get(format!("{}/mcp/fs", base)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut stream = response.bytes_stream();
+        let mut buffer = String::new();
+
+        manager
+            .lock()
+            .await
+            .set_disabled_items("fs", vec!["write".to_string()], vec![])
+            .unwrap();
+
+        let (event, data) = next_sse_event(&mut stream, &mut buffer).await;
+        assert_eq!(event, "message");
+        let notification: Value = serde_json::from_str(&data).unwrap();
+        assert_eq!(notification["method"], "notifications/tools/list_changed");
+    }
+
+    #[tokio::test]
+    async fn safe_mode_answers_requests_without_connecting_on_demand() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let (manager, base) = serve(AppConfig {
+            safe_mode: true,
+            ..config_with(vec![http_mcp_config("fs", &upstream.url)])
+        })
+        .await;
+        let conn = manager.lock().await.get_connection("fs").unwrap();
+
+        let response = post_rpc(&base, "fs", tool_call(1, "echo")).await;
+
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["error"]["code"], -32000);
+        assert_eq!(conn.get_state().await, ConnectionState::Disconnected);
+        assert!(upstream.methods().is_empty());
+
+        // Once the user has connected it, a dropped MCP is revived on demand
+        conn.connect(crate::types::ReconnectReason::UserRequested)
+            .await
+            .unwrap();
+        conn.disconnect().await;
+        let response = post_rpc(&base, "fs", tool_call(2, "echo")).await;
+        assert_eq!(response["id"], 2);
+        assert!(response.get("error").is_none(), "{}", response);
+        assert_eq!(conn.get_state().await, ConnectionState::Connected);
+    }
+
+    #[tokio::test]
+    async fn requests_during_a_connect_are_told_to_retry() {
+        let upstream =
+            FakeUpstream::spawn_slow("up", &["echo"], std::time::Duration::from_millis(500)).await;
+        let (manager, base) = serve(config_with(vec![http_mcp_config("fs", &upstream.url)])).await;
+        let conn = manager.lock().await.get_connection("fs").unwrap();
+        conn.disconnect().await;
+        let connecting = {
+            let conn = Arc::clone(&conn);
+            tokio::spawn(async move {
+                conn.connect(crate::types::ReconnectReason::UserRequested).await
+            })
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert_eq!(conn.get_state().await, ConnectionState::Connecting);
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/mcp/fs", base))
+            .json(&tool_call(1, "echo"))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.headers()["retry-after"], "2");
+        let reply: Value = response.json().await.unwrap();
+        assert_eq!(reply["id"], 1);
+        assert_eq!(reply["error"]["code"], -32003);
+        assert_eq!(reply["error"]["data"]["retryAfter"], 2);
+        connecting.await.unwrap().unwrap();
+        assert!(upstream.params_of("tools/call").is_empty());
+    }
+
+    #[tokio::test]
+    async fn only_connecting_and_reconnecting_states_are_busy() {
+        let batch = json!([tool_call(1, "echo"), tool_call(2, "echo")]);
+        for state in [ConnectionState::Connecting, ConnectionState::Reconnecting] {
+            let response = busy_response(&batch, state).expect("busy");
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let replies: Value = serde_json::from_slice(&body).unwrap();
+            let codes: Vec<&Value> = replies
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|r| &r["error"]["code"])
+                .collect();
+            assert_eq!(codes, [&json!(-32003), &json!(-32003)], "{:?}", state);
+        }
+        for state in [
+            ConnectionState::Connected,
+            ConnectionState::Disconnected,
+            ConnectionState::Error,
+        ] {
+            assert!(busy_response(&batch, state).is_none(), "{:?}", state);
+        }
+    }
+
+    #[test]
+    fn large_tool_lists_are_filtered_quickly_and_exactly() {
+        let tools: Vec<Value> = (0..5000)
+            .map(|i| json!({"name": format!("tool_{}", i), "inputSchema": {}}))
+            .collect();
+        let disabled = DisabledItems {
+            tools: (0..5000)
+                .step_by(3)
+                .map(|i| format!("tool_{}", i))
+                .chain(["tool_49*".to_string(), "tool_?".to_string()])
+                .collect(),
+            ..DisabledItems::default()
+        };
+        let overrides = HashMap::from([("tool_1".to_string(), "Renamed".to_string())]);
+        let aliases = ToolAliases::new(
+            &HashMap::from([("tool_2".to_string(), "two".to_string())]),
+            tools.iter().filter_map(|t| t["name"].as_str()),
+        );
+
+        // What the per-item lookups produce
+        let expected: Vec<Value> = tools
+            .iter()
+            .filter(|t| disabled.is_tool_enabled(t["name"].as_str().unwrap()))
+            .cloned()
+            .map(|mut t| {
+                let name = t["name"].as_str().unwrap().to_string();
+                if let Some(description) = overrides.get(&name) {
+                    t["description"] = json!(description);
+                }
+                t["name"] = json!(aliases.alias(&name));
+                t
+            })
+            .collect();
+
+        let mut filtered = tools.clone();
+        let started = std::time::Instant::now();
+        expose_tools(&mut filtered, &disabled, &overrides, &aliases);
+        let elapsed = started.elapsed();
+
+        assert_eq!(filtered, expected);
+        assert!(filtered.len() > 3000 && filtered.len() < 3400, "{}", filtered.len());
+        assert!(elapsed < std::time::Duration::from_millis(250), "{:?}", elapsed);
+    }
+
+    #[test]
+    fn batch_validation_rejects_empty_and_duplicate_ids() {
+        assert_eq!(
+            validate_batch(&[]).unwrap_err(),
+            "Invalid Request: empty batch"
+        );
+        let duplicate = [tool_call(1, "echo"), tool_call(1, "echo")];
+        assert_eq!(
+            validate_batch(&duplicate).unwrap_err(),
+            "Invalid Request: duplicate id 1 in batch"
+        );
+        // Same value, different type: not a duplicate
+        let mixed = [
+            tool_call(1, "echo"),
+            json!({"jsonrpc": "2.0", "id": "1", "method": "ping"}),
+        ];
+        assert!(validate_batch(&mixed).is_ok());
+    }
+
+    #[test]
+    fn batch_validation_ignores_notifications() {
+        let notification = json!({"jsonrpc": "2.0", "method": "notifications/initialized"});
+        let batch = [notification.clone(), notification, tool_call(1, "echo")];
+        assert!(validate_batch(&batch).is_ok());
+    }
+
+    #[tokio::test]
+    async fn invalid_batches_get_a_jsonrpc_error() {
+        let url = unreachable_url().await;
+        let (_manager, base) = serve(config_with(vec![http_mcp_config("fs", &url)])).await;
+
+        let empty = post_rpc(&base, "fs", json!([])).await;
+        assert_eq!(empty["error"]["code"], -32600);
+        assert_eq!(empty["id"], Value::Null);
+
+        let duplicate = post_rpc(
+            &base,
+            "fs",
+            json!([tool_call(7, "echo"), tool_call(7, "echo")]),
+        )
+        .await;
+        assert_eq!(duplicate["error"]["code"], -32600);
+    }
+
+    #[tokio::test]
+    async fn well_formed_batch_gets_a_response_per_request() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let (_manager, base) = serve(config_with(vec![http_mcp_config("fs", &upstream.url)])).await;
+        let batch = json!([
+            tool_call(1, "echo"),
+            {"jsonrpc": "2.0", "method": "notifications/initialized"},
+            tool_call(2, "echo")
+        ]);
+
+        let response = post_rpc(&base, "fs", batch).await;
+
+        let responses = response.as_array().expect("batch response");
+        let ids: Vec<&Value> = responses.iter().map(|r| &r["id"]).collect();
+        assert_eq!(ids, [&json!(1), &json!(2)]);
+        for r in responses {
+            assert_eq!(r["result"]["content"][0]["text"], "echo from up");
+        }
+    }
+
+    #[tokio::test]
+    async fn openapi_endpoint_lists_only_enabled_tools() {
+        let upstream = FakeUpstream::spawn("up", &["read", "delete"]).await;
+        let (manager, base) = serve(config_with(vec![http_mcp_config("fs", &upstream.url)])).await;
+        manager
+            .lock()
+            .await
+            .set_disabled_items("fs", vec!["delete".into()], vec![])
+            .unwrap();
+
+        let spec: Value = reqwest::get(format!("{}/mcp/fs/openapi", base))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+
+        let paths: Vec<&String> = spec["paths"].as_object().unwrap().keys().collect();
+        assert_eq!(paths, ["/tools/read"]);
+    }
+
+    #[tokio::test]
+    async fn disabled_resource_patterns_hide_matching_templates() {
+        let upstream = FakeUpstream::spawn("up", &[]).await;
+        let (manager, base) = serve(config_with(vec![http_mcp_config("fs", &upstream.url)])).await;
+        let list = json!({"jsonrpc": "2.0", "id": 1, "method": "resources/templates/list"});
+        let template_uris = |response: Value| -> Vec<String> {
+            response["result"]["resourceTemplates"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|t| t["uriTemplate"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        assert_eq!(
+            template_uris(post_rpc(&base, "fs", list.clone()).await),
+            ["file:///public/{path}", "file:///secret/{path}"]
+        );
+
+        manager
+            .lock()
+            .await
+            .set_disabled_items("fs", vec![], vec!["file:///secret/*".into()])
+            .unwrap();
+        assert_eq!(
+            template_uris(post_rpc(&base, "fs", list).await),
+            ["file:///public/{path}"]
+        );
+    }
+
+    #[tokio::test]
+    async fn client_side_methods_get_a_clean_method_not_found() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let (_manager, base) = serve(config_with(vec![http_mcp_config("fs", &upstream.url)])).await;
+
+        for (id, method) in CLIENT_SIDE_METHODS.iter().enumerate() {
+            let probe = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": {}});
+            let response = post_rpc(&base, "fs", probe).await;
+
+            assert_eq!(response["id"], id);
+            assert_eq!(response["error"]["code"], -32601);
+            assert_eq!(
+                response["error"]["data"]["capabilities"],
+                proxy_capabilities()
+            );
+        }
+        let forwarded = upstream.methods();
+        assert!(CLIENT_SIDE_METHODS
+            .iter()
+            .all(|m| !forwarded.contains(&m.to_string())));
+    }
+
+    /// Names of the tools the proxy lists for MCP "fs"
+    async fn listed_tools(base: &str) -> Vec<String> {
+        let reply = post_rpc(
+            base,
+            "fs",
+            json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}),
+        )
+        .await;
+        reply["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn tools_list_hides_tools_matching_disabled_globs() {
+        let upstream = FakeUpstream::spawn("up", &["admin_reset", "admin_drop", "read_file"]).await;
+        let mut config = http_mcp_config("fs", &upstream.url);
+        config.disabled_tools = vec!["admin_*".to_string()];
+        let (_manager, base) = serve(config_with(vec![config])).await;
+
+        assert_eq!(listed_tools(&base).await, ["read_file"]);
+    }
+
+    #[tokio::test]
+    async fn calls_to_disabled_tools_are_refused_before_the_upstream() {
+        let upstream = FakeUpstream::spawn("up", &["admin_reset", "read_file"]).await;
+        let mut config = http_mcp_config("fs", &upstream.url);
+        config.disabled_tools = vec!["admin_*".to_string()];
+        let (_manager, base) = serve(config_with(vec![config])).await;
+
+        let reply = post_rpc(&base, "fs", tool_call(2, "admin_reset")).await;
+
+        assert_eq!(reply["error"]["code"], -32602);
+        assert_eq!(reply["error"]["message"], "Tool 'admin_reset' is disabled");
+        assert!(upstream.params_of("tools/call").is_empty());
+    }
+
+    #[tokio::test]
+    async fn hide_destructive_tools_hides_unannotated_tools() {
+        // The fake upstream reports no annotations, so every tool counts as
+        // destructive
+        let upstream = FakeUpstream::spawn("up", &["read_file", "delete_file"]).await;
+        let mut config = http_mcp_config("fs", &upstream.url);
+        config.hide_destructive_tools = Some(true);
+        let (_manager, base) = serve(config_with(vec![config])).await;
+
+        assert!(listed_tools(&base).await.is_empty());
+        let reply = post_rpc(&base, "fs", tool_call(2, "delete_file")).await;
+        assert_eq!(reply["error"]["code"], -32602);
+        assert!(upstream.params_of("tools/call").is_empty());
+    }
+
+    #[test]
+    fn only_read_only_or_non_destructive_tools_are_safe() {
+        use crate::types::{Tool, ToolAnnotations};
+        let tool = |read_only_hint, destructive_hint| {
+            let annotations = ToolAnnotations {
+                read_only_hint,
+                destructive_hint,
+                ..ToolAnnotations::default()
+            };
+            Tool::new("t".to_string(), None, json!({}), Some(annotations))
+        };
+
+        assert!(Tool::new("t".to_string(), None, json!({}), None).is_destructive());
+        assert!(tool(None, None).is_destructive());
+        assert!(tool(Some(false), None).is_destructive());
+        assert!(!tool(Some(true), None).is_destructive());
+        assert!(!tool(None, Some(false)).is_destructive());
+    }
+
+    #[tokio::test]
+    async fn requests_are_recorded_only_when_enabled() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let mut recorded = http_mcp_config("fs", &upstream.url);
+        recorded.record_requests = Some(true);
+        let (manager, base) = serve(config_with(vec![
+            recorded,
+            http_mcp_config("quiet", &upstream.url),
+        ]))
+        .await;
+
+        post_rpc(&base, "fs", tool_call(2, "echo")).await;
+        post_rpc(&base, "quiet", tool_call(3, "echo")).await;
+
+        let mgr = manager.lock().await;
+        let requests = mgr.get_connection("fs").unwrap().get_recorded_requests().await;
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "tools/call");
+        assert_eq!(
+            requests[0].response.as_ref().unwrap()["result"]["content"][0]["text"],
+            "echo from up"
+        );
+        let quiet = mgr.get_connection("quiet").unwrap();
+        assert!(quiet.get_recorded_requests().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn only_allowlisted_headers_are_forwarded_upstream() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let mut config = http_mcp_config("fs", &upstream.url);
+        config.forward_headers = vec!["X-Tenant-Id".to_string()];
+        let (_manager, base) = serve(config_with(vec![config])).await;
+
+        let response: Value = reqwest::Client::new()
+            .post(format!("{}/mcp/fs", base))
+            .header("x-tenant-id", "acme")
+            .header("x-internal-secret", "hunter2")
+            .json(&tool_call(1, "echo"))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(response["result"]["content"][0]["text"], "echo from up");
+
+        // Only the proxied call carries it, not the proxy's own handshake
+        let tenants: Vec<String> = upstream
+            .header_values("x-tenant-id")
+            .into_iter()
+            .filter_map(|(_, value)| value)
+            .collect();
+        assert_eq!(tenants, ["acme"]);
+        assert!(upstream
+            .header_values("x-internal-secret")
+            .iter()
+            .all(|(_, value)| value.is_none()));
+    }
+
+    #[tokio::test]
+    async fn forwarded_headers_do_not_change_how_requests_are_checked() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let mut config = http_mcp_config("fs", &upstream.url);
+        config.forward_headers = vec!["X-Tenant-Id".to_string()];
+        let (_manager, base) = serve(config_with(vec![config])).await;
+        let send = |body: Value| {
+            let url = format!("{}/mcp/fs", base);
+            async move {
+                let response = reqwest::Client::new()
+                    .post(url)
+                    .header("x-tenant-id", "acme")
+                    .json(&body)
+                    .send()
+                    .await
+                    .unwrap();
+                response.json::<Value>().await.unwrap()
+            }
+        };
+
+        let bad_params = send(serde_json::json!({
+            "jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": {"arguments": {}}
+        }))
+        .await;
+        assert_eq!(bad_params["error"]["code"], -32602);
+
+        let unknown = send(serde_json::json!({
+            "jsonrpc": "2.0", "id": 2, "method": "tasks/list", "params": {}
+        }))
+        .await;
+        assert_eq!(unknown["error"]["code"], -32601);
+        assert!(!upstream.methods().contains(&"tasks/list".to_string()));
+    }
+
+    #[tokio::test]
+    async fn traffic_totals_accumulate_per_mcp() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let (manager, base) = serve(config_with(vec![
+            http_mcp_config("fs", &upstream.url),
+            http_mcp_config("quiet", &upstream.url),
+        ]))
+        .await;
+
+        let (mut bytes_in, mut bytes_out) = (0, 0);
+        for id in 1..=3 {
+            let request = tool_call(id, "echo");
+            bytes_in += request.to_string().len() as u64;
+            bytes_out += post_rpc(&base, "fs", request).await.to_string().len() as u64;
+        }
+        let notification = json!({"jsonrpc": "2.0", "method": "notifications/initialized"});
+        bytes_in += notification.to_string().len() as u64;
+        let accepted = reqwest::Client::new()
+            .post(format!("{}/mcp/fs", base))
+            .json(&notification)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(accepted.status(), StatusCode::ACCEPTED);
+
+        let stats = manager.lock().await.traffic_stats();
+        assert_eq!(
+            stats,
+            [
+                TrafficStats {
+                    mcp_id: "fs".to_string(),
+                    bytes_in,
+                    bytes_out,
+                    request_count: 4,
+                },
+                TrafficStats {
+                    mcp_id: "quiet".to_string(),
+                    ..TrafficStats::default()
+                },
+            ]
+        );
+    }
+
+    /// Read the next SSE event from `stream` as (event name, data)
+    async fn next_sse_event(
+        stream: &mut (impl futures::Stream<Item = reqwest::Result<axum::body::Bytes>> + Unpin),
+        buffer: &mut String,
+    ) -> (String, String) {
+        loop {
+            if let Some(end) = buffer.find("\n\n") {
+                let block: String = buffer.drain(..end + 2).collect();
+                let field = |name: &str| {
+                    block
+                        .lines()
+                        .find_map(|line| line.strip_prefix(name))
+                        .map(|v| v.trim_start().to_string())
+                };
+                // Keep-alive comments carry no data
+                if let Some(data) = field("data:") {
+                    return (field("event:").unwrap_or_default(), data);
+                }
+                continue;
+            }
+            let chunk = tokio::time::timeout(std::time::Duration::from_secs(5), stream.next())
+                .await
+                .expect("SSE event in time")
+                .expect("SSE stream open")
+                .unwrap();
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+        }
+    }
+
+    #[tokio::test]
+    async fn legacy_sse_clients_get_replies_on_their_stream() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let (_manager, base) = serve(config_with(vec![http_mcp_config("fs", &upstream.url)])).await;
+        let client = reqwest::Client::new();
+        let response = client.get(format!("{}/mcp/fs/sse", base)).send().await.unwrap();
+        let mut stream = response.bytes_stream();
+        let mut buffer = String::new();
+
+        let (event, endpoint) = next_sse_event(&mut stream, &mut buffer).await;
+        assert_eq!(event, "endpoint");
+        assert!(endpoint.starts_with("/mcp/fs/messages?sessionId="), "{}", endpoint);
+
+        let post = client
+            .post(format!("{}{}", base, endpoint))
+            .json(&tool_call(7, "echo"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(post.status(), StatusCode::ACCEPTED);
+
+        let (event, data) = next_sse_event(&mut stream, &mut buffer).await;
+        assert_eq!(event, "message");
+        let reply: Value = serde_json::from_str(&data).unwrap();
+        assert_eq!(reply["id"], 7);
+        assert_eq!(reply["result"]["content"][0]["text"], "echo from up");
+    }
+
+    #[tokio::test]
+    async fn legacy_messages_need_an_open_session() {
+        let (_manager, base) = serve(config_with(vec![mcp_config("fs")])).await;
+        let client = reqwest::Client::new();
+
+        let post = client
+            .post(format!("{}/mcp/fs/messages?sessionId=unknown", base))
+            .json(&tool_call(1, "echo"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(post.status(), StatusCode::NOT_FOUND);
+        let get = client.get(format!("{}/mcp/missing/sse", base)).send().await.unwrap();
+        assert_eq!(get.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn legacy_sessions_are_tied_to_their_mcp_and_forward_headers() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let mut fs = http_mcp_config("fs", &upstream.url);
+        fs.forward_headers = vec!["X-Tenant-Id".to_string()];
+        let other = FakeUpstream::spawn("other", &["echo"]).await;
+        let (_manager, base) =
+            serve(config_with(vec![fs, http_mcp_config("other", &other.url)])).await;
+        let client = reqwest::Client::new();
+        let response = client.get(format!("{}/mcp/fs/sse", base)).send().await.unwrap();
+        let mut stream = response.bytes_stream();
+        let mut buffer = String::new();
+        let (_, endpoint) = next_sse_event(&mut stream, &mut buffer).await;
+
+        let wrong_mcp = endpoint.replacen("/mcp/fs/", "/mcp/other/", 1);
+        let post = client
+            .post(format!("{}{}", base, wrong_mcp))
+            .json(&tool_call(1, "echo"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(post.status(), StatusCode::NOT_FOUND);
+        assert!(other.params_of("tools/call").is_empty());
+
+        let post = client
+            .post(format!("{}{}", base, endpoint))
+            .header("x-tenant-id", "acme")
+            .header("Accept", "text/event-stream")
+            .json(&tool_call(2, "echo"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(post.status(), StatusCode::ACCEPTED);
+        let (_, data) = next_sse_event(&mut stream, &mut buffer).await;
+        let reply: Value = serde_json::from_str(&data).unwrap();
+        assert_eq!(reply["result"]["content"][0]["text"], "echo from up");
+        let tenants: Vec<String> = upstream
+            .header_values("x-tenant-id")
+            .into_iter()
+            .filter_map(|(_, value)| value)
+            .collect();
+        assert_eq!(tenants, ["acme"]);
+    }
+
+    #[test]
+    fn defaults_fill_gaps_without_overriding_the_client() {
+        let mut arguments = json!({"path": "/tmp", "options": {"depth": 1}});
+        let defaults = json!({
+            "path": "/",
+            "encoding": "utf-8",
+            "options": {"depth": 5, "hidden": false}
+        });
+
+        merge_defaults(&mut arguments, &defaults);
+
+        assert_eq!(
+            arguments,
+            json!({
+                "path": "/tmp",
+                "encoding": "utf-8",
+                "options": {"depth": 1, "hidden": false}
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn tool_defaults_are_merged_into_calls_by_alias() {
+        let upstream = FakeUpstream::spawn("up", &["search_code"]).await;
+        let mut config = http_mcp_config("fs", &upstream.url);
+        config.tool_aliases = HashMap::from([("search_code".to_string(), "search".to_string())]);
+        config.tool_defaults =
+            HashMap::from([("search_code".to_string(), json!({"limit": 10, "repo": "main"}))]);
+        let (_manager, base) = serve(config_with(vec![config])).await;
+
+        post_rpc(&base, "fs", tool_call(2, "search")).await;
+        let mut call = tool_call(3, "search");
+        call["params"]["arguments"] = json!({"limit": 3});
+        post_rpc(&base, "fs", call).await;
+        let mut call = tool_call(4, "search");
+        call["params"]["arguments"] = Value::Null;
+        post_rpc(&base, "fs", call).await;
+
+        let arguments: Vec<Value> = upstream
+            .params_of("tools/call")
+            .into_iter()
+            .map(|p| p["arguments"].clone())
+            .collect();
+        assert_eq!(
+            arguments,
+            [
+                json!({"limit": 10, "repo": "main"}),
+                json!({"limit": 3, "repo": "main"}),
+                json!({"limit": 10, "repo": "main"}),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn aliased_tools_are_listed_and_called_by_their_alias() {
+        let upstream = FakeUpstream::spawn("up", &["filesystem__read_file", "stat"]).await;
+        let mut config = http_mcp_config("fs", &upstream.url);
+        config.tool_aliases =
+            HashMap::from([("filesystem__read_file".to_string(), "read".to_string())]);
+        let (_manager, base) = serve(config_with(vec![config])).await;
+
+        assert_eq!(listed_tools(&base).await, ["read", "stat"]);
+        let reply = post_rpc(&base, "fs", tool_call(2, "read")).await;
+        assert_eq!(
+            reply["result"]["content"][0]["text"],
+            "filesystem__read_file from up"
+        );
+        assert_eq!(
+            upstream.params_of("tools/call")[0]["name"],
+            "filesystem__read_file"
+        );
+    }
+
+    /// POST `body` verbatim to the proxy endpoint of MCP "fs"
+    async fn post_raw(base: &str, body: &'static str) -> Value {
+        reqwest::Client::new()
+            .post(format!("{}/mcp/fs", base))
+            .header("Content-Type", "application/json")
+            .header("Accept", "application/json, text/event-stream")
+            .body(body)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn malformed_bodies_get_a_jsonrpc_error() {
+        let url = unreachable_url().await;
+        let (_manager, base) = serve(config_with(vec![http_mcp_config("fs", &url)])).await;
+
+        for (body, code) in [
+            (r#"{"jsonrpc": "2.0", "id": 1,"#, -32700),
+            ("", -32700),
+            (r#""tools/list""#, -32600),
+            ("null", -32600),
+            ("42", -32600),
+        ] {
+            let reply = post_raw(&base, body).await;
+            assert_eq!(reply["jsonrpc"], "2.0", "{}", body);
+            assert_eq!(reply["id"], Value::Null, "{}", body);
+            assert_eq!(reply["error"]["code"], code, "{}", body);
+        }
+    }
+
+    #[tokio::test]
+    async fn resource_endpoint_returns_a_single_blob_as_raw_bytes() {
+        let upstream = FakeUpstream::spawn("up", &[]).await;
+        let (_manager, base) = serve(config_with(vec![http_mcp_config("fs", &upstream.url)])).await;
+
+        let response = reqwest::get(format!("{}/mcp/fs/resource?uri=file:///logo.png", base))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-type"], "image/png");
+        assert_eq!(
+            response.headers()["content-length"],
+            FAKE_PNG.len().to_string().as_str()
+        );
+        assert_eq!(response.bytes().await.unwrap().as_ref(), FAKE_PNG);
+    }
+
+    #[tokio::test]
+    async fn resource_endpoint_falls_back_to_json_for_text() {
+        let upstream = FakeUpstream::spawn("up", &[]).await;
+        let mut config = http_mcp_config("fs", &upstream.url);
+        config.disabled_resources = vec!["file:///secret/*".to_string()];
+        let (_manager, base) = serve(config_with(vec![config])).await;
+
+        let response = reqwest::get(format!("{}/mcp/fs/resource?uri=file:///notes.txt", base))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["content-type"], "application/json");
+        let result: Value = response.json().await.unwrap();
+        assert_eq!(
+            result["contents"][0]["text"],
+            "contents of file:///notes.txt"
+        );
+
+        let hidden = reqwest::get(format!("{}/mcp/fs/resource?uri=file:///secret/a.png", base))
+            .await
+            .unwrap();
+        assert_eq!(hidden.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn requests_beyond_max_inflight_are_rejected() {
+        let upstream =
+            FakeUpstream::spawn_slow("up", &["echo"], std::time::Duration::from_millis(500)).await;
+        let mut config = http_mcp_config("fs", &upstream.url);
+        config.max_inflight_requests = Some(2);
+        let (manager, base) = serve(config_with(vec![config])).await;
+        let conn = manager.lock().await.get_connection("fs").unwrap();
+
+        let running: Vec<_> = (1..=2)
+            .map(|id| {
+                let base = base.clone();
+                tokio::spawn(async move { post_rpc(&base, "fs", tool_call(id, "echo")).await })
+            })
+            .collect();
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        let status = conn.status(&DisabledItems::default()).await;
+        assert_eq!(status.in_flight_requests, 2);
+
+        let rejected = post_rpc(&base, "fs", tool_call(3, "echo")).await;
+        assert_eq!(rejected["error"]["code"], -32029);
+
+        for call in running {
+            let reply = call.await.unwrap();
+            assert_eq!(reply["result"]["content"][0]["text"], "echo from up");
+        }
+        assert_eq!(upstream.params_of("tools/call").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn description_overrides_replace_only_the_description() {
+        let upstream = FakeUpstream::spawn("up", &["read_file", "stat"]).await;
+        let mut config = http_mcp_config("fs", &upstream.url);
+        config.tool_description_overrides =
+            HashMap::from([("read_file".to_string(), "Read a UTF-8 file".to_string())]);
+        let (_manager, base) = serve(config_with(vec![config])).await;
+
+        let reply = post_rpc(
+            &base,
+            "fs",
+            json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}),
+        )
+        .await;
+        let tools = &reply["result"]["tools"];
+        assert_eq!(tools[0]["description"], "Read a UTF-8 file");
+        assert_eq!(tools[0]["inputSchema"], json!({"type": "object"}));
+        assert_eq!(tools[1]["description"], "stat tool");
+
+        let listed: Value = reqwest::get(format!("{}/mcp/fs/tools", base))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(listed[0]["description"], "Read a UTF-8 file");
+        assert_eq!(listed[1]["description"], "stat tool");
+    }
+
+    #[tokio::test]
+    async fn concurrent_sessions_reusing_an_id_each_get_their_own_reply() {
+        let upstream = FakeUpstream::spawn_slow(
+            "up",
+            &["read_file", "stat"],
+            std::time::Duration::from_millis(200),
+        )
+        .await;
+        let (_manager, base) = serve(config_with(vec![http_mcp_config("fs", &upstream.url)])).await;
+
+        let (read, stat) = tokio::join!(
+            post_rpc(&base, "fs", tool_call(1, "read_file")),
+            post_rpc(&base, "fs", tool_call(1, "stat")),
+        );
+
+        assert_eq!(read["id"], 1);
+        assert_eq!(read["result"]["content"][0]["text"], "read_file from up");
+        assert_eq!(stat["id"], 1);
+        assert_eq!(stat["result"]["content"][0]["text"], "stat from up");
+        // Upstream saw proxy-assigned ids, distinct from each other
+        let upstream_ids: HashSet<String> = upstream
+            .requests_for("tools/call")
+            .iter()
+            .map(|body| body["id"].to_string())
+            .collect();
+        assert_eq!(upstream_ids.len(), 2);
+    }
+
+    /// POST a `tools/call` for `tool` carrying a progress token, returning
+    /// the response's content type and body
+    async fn call_with_progress(base: &str, tool: &str) -> (String, String) {
+        let mut request = tool_call(5, tool);
+        request["params"]["_meta"] = json!({"progressToken": "tok-1"});
+        let response = reqwest::Client::new()
+            .post(format!("{}/mcp/fs", base))
+            .header("Accept", "application/json, text/event-stream")
+            .json(&request)
+            .send()
+            .await
+            .unwrap();
+        let content_type = response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        (content_type, response.text().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn tool_progress_is_streamed_before_the_result() {
+        let upstream = FakeUpstream::spawn("up", &[FAKE_PROGRESS_TOOL]).await;
+        let (_manager, base) = serve(config_with(vec![http_mcp_config("fs", &upstream.url)])).await;
+
+        let (content_type, body) = call_with_progress(&base, FAKE_PROGRESS_TOOL).await;
+
+        assert!(
+            content_type.starts_with("text/event-stream"),
+            "{}",
+            content_type
+        );
+        let events: Vec<Value> = body
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect();
+        assert_eq!(events.len(), 3, "{}", body);
+        for (event, step) in events[..2].iter().zip(1..) {
+            assert_eq!(event["method"], "notifications/progress");
+            assert_eq!(event["params"]["progressToken"], "tok-1");
+            assert_eq!(event["params"]["progress"], f64::from(step));
+        }
+        assert_eq!(events[2]["id"], 5);
+        assert_eq!(
+            events[2]["result"]["content"][0]["text"],
+            "long_task from up"
+        );
+    }
+
+    #[tokio::test]
+    async fn tool_calls_without_progress_stay_plain_json() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let (_manager, base) = serve(config_with(vec![http_mcp_config("fs", &upstream.url)])).await;
+
+        let (content_type, body) = call_with_progress(&base, "echo").await;
+
+        assert!(
+            content_type.starts_with("application/json"),
+            "{}",
+            content_type
+        );
+        let reply: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(reply["result"]["content"][0]["text"], "echo from up");
+    }
+
+    /// POST an `echo` call with the given Accept header, returning the
+    /// response's content type and body
+    async fn call_accepting(base: &str, accept: &str) -> (String, String) {
+        let response = reqwest::Client::new()
+            .post(format!("{}/mcp/fs", base))
+            .header("Accept", accept)
+            .json(&tool_call(9, "echo"))
+            .send()
+            .await
+            .unwrap();
+        let content_type = response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        (content_type, response.text().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn responses_are_framed_as_sse_when_the_client_prefers_it() {
+        let upstream = FakeUpstream::spawn("up", &["echo"]).await;
+        let (_manager, base) = serve(config_with(vec![http_mcp_config("fs", &upstream.url)])).await;
+
+        for accept in ["text/event-stream", "application/json;q=0.5, text/event-stream"] {
+            let (content_type, body) = call_accepting(&base, accept).await;
+            assert!(content_type.starts_with("text/event-stream"), "{}", content_type);
+            let events: Vec<Value> = body
+                .lines()
+                .filter_map(|line| line.strip_prefix("data: "))
+                .map(|data| serde_json::from_str(data).unwrap())
+                .collect();
+            assert_eq!(events.len(), 1, "{}", body);
+            assert_eq!(events[0]["id"], 9);
+            assert_eq!(events[0]["result"]["content"][0]["text"], "echo from up");
+        }
+
+        for accept in ["application/json", "application/json, text/event-stream", "*/*"] {
+            let (content_type, body) = call_accepting(&base, accept).await;
+            assert!(content_type.starts_with("application/json"), "{}", content_type);
+            let reply: Value = serde_json::from_str(&body).unwrap();
+            assert_eq!(reply["result"]["content"][0]["text"], "echo from up");
+        }
+    }
+
+    async fn health(base: &str) -> (u16, Value) {
+        let response = reqwest::get(format!("{}/health", base)).await.unwrap();
+        (response.status().as_u16(), response.json().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn health_is_ok_when_every_enabled_mcp_is_connected() {
+        let upstream = FakeUpstream::spawn("up", &[]).await;
+        let (_manager, base) = serve(config_with(vec![
+            http_mcp_config("fs", &upstream.url),
+            mcp_config("off"),
+        ]))
+        .await;
+
+        let (code, body) = health(&base).await;
+
+        assert_eq!(code, 200);
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["total_mcps"], 2);
+        assert_eq!(body["connected_mcps"], 1);
+    }
+
+    #[tokio::test]
+    async fn health_is_degraded_when_only_some_mcps_are_connected() {
+        let upstream = FakeUpstream::spawn("up", &[]).await;
+        let (_manager, base) = serve(config_with(vec![
+            http_mcp_config("fs", &upstream.url),
+            http_mcp_config("gone", &unreachable_url().await),
+        ]))
+        .await;
+
+        let (code, body) = health(&base).await;
+
+        assert_eq!(code, 200);
+        assert_eq!(body["status"], "degraded");
+    }
+
+    #[tokio::test]
+    async fn health_ignores_idle_and_manually_started_mcps_that_are_down() {
+        let upstream = FakeUpstream::spawn("up", &[]).await;
+        let mut manual = http_mcp_config("manual", &unreachable_url().await);
+        manual.auto_start = false;
+        let (manager, base) = serve(config_with(vec![
+            http_mcp_config("fs", &upstream.url),
+            http_mcp_config("idle", &upstream.url),
+            manual,
+        ]))
+        .await;
+        let idle = manager.lock().await.get_connection("idle").unwrap();
+        idle.disconnect_idle().await;
+
+        let (code, body) = health(&base).await;
+
+        assert_eq!(code, 200);
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["connected_mcps"], 1);
+
+        manager.lock().await.get_connection("fs").unwrap().disconnect().await;
+        let (code, body) = health(&base).await;
+        assert_eq!(code, 503);
+        assert_eq!(body["status"], "down");
+    }
+
+    #[tokio::test]
+    async fn status_page_is_served_only_when_enabled() {
+        let (_manager, base) = serve(AppConfig {
+            enable_status_page: true,
+            ..AppConfig::default()
+        })
+        .await;
+        let response = reqwest::get(format!("{}/", base)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let content_type = response.headers()[header::CONTENT_TYPE].to_str().unwrap();
+        assert!(content_type.starts_with("text/html"));
+        let page = response.text().await.unwrap();
+        assert!(page.contains(r#"<table id="mcp-status">"#));
+        assert!(page.contains("/mcps") && page.contains("/health"));
+
+        let (_manager, base) = serve(AppConfig::default()).await;
+        let response = reqwest::get(format!("{}/", base)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn status_page_requires_the_proxy_token() {
+        let (_manager, base) = serve(AppConfig {
+            enable_status_page: true,
+            proxy_auth_token: Some("secret".to_string()),
+            ..AppConfig::default()
+        })
+        .await;
+        let client = reqwest::Client::new();
+
+        let response = client.get(format!("{}/", base)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = client
+            .get(format!("{}/?token=secret", base))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = client
+            .get(format!("{}/", base))
+            .bearer_auth("secret")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn health_is_down_with_a_503_when_no_mcp_is_connected() {
+        let (_manager, base) = serve(config_with(vec![http_mcp_config(
+            "gone",
+            &unreachable_url().await,
+        )]))
+        .await;
+
+        let (code, body) = health(&base).await;
+
+        assert_eq!(code, 503);
+        assert_eq!(body["status"], "down");
+        assert_eq!(body["connected_mcps"], 0);
+    }
+}
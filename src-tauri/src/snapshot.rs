@@ -0,0 +1,126 @@
+use crate::mcp::manager::McpManager;
+use crate::types::RuntimeSnapshot;
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time;
+
+/// How often the runtime snapshot is written
+const SNAPSHOT_INTERVAL_SECS: u64 = 60;
+
+/// Persists runtime connection state (separate from config.json) so a
+/// restart after a crash knows which servers were failing
+pub struct SnapshotStore {
+    snapshot_path: PathBuf,
+}
+
+impl SnapshotStore {
+    /// Create a new SnapshotStore with the given path
+    pub fn new(snapshot_path: PathBuf) -> Self {
+        Self { snapshot_path }
+    }
+
+    /// Initialize SnapshotStore using the Tauri app data directory
+    pub fn from_app_handle(app_handle: &tauri::AppHandle) -> Result<Self> {
+        use tauri::Manager;
+        let app_dir = app_handle
+            .path()
+            .app_data_dir()
+            .context("Failed to resolve app data directory")?;
+
+        Ok(Self::new(app_dir.join("runtime-snapshot.json")))
+    }
+
+    /// Load the last snapshot.  A missing or unreadable snapshot is not an
+    /// error — startup simply proceeds without it.
+    pub fn load(&self) -> Option<RuntimeSnapshot> {
+        let data = std::fs::read_to_string(&self.snapshot_path).ok()?;
+        match serde_json::from_str(&data) {
+            Ok(snapshot) => Some(snapshot),
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable runtime snapshot: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Write the snapshot to disk
+    pub fn save(&self, snapshot: &RuntimeSnapshot) -> Result<()> {
+        if let Some(parent) = self.snapshot_path.parent() {
+            std::fs::create_dir_all(parent)
+                .context("Failed to create snapshot directory")?;
+        }
+
+        let data = serde_json::to_string_pretty(snapshot)
+            .context("Failed to serialize runtime snapshot")?;
+
+        std::fs::write(&self.snapshot_path, data)
+            .context("Failed to write runtime snapshot")?;
+
+        tracing::debug!("Saved runtime snapshot to {:?}", self.snapshot_path);
+        Ok(())
+    }
+}
+
+/// Start the background loop that periodically persists the runtime snapshot
+pub fn start_snapshot_loop(manager: Arc<Mutex<McpManager>>, store: SnapshotStore) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            time::sleep(time::Duration::from_secs(SNAPSHOT_INTERVAL_SECS)).await;
+
+            let snapshot = {
+                let mgr = manager.lock().await;
+                mgr.runtime_snapshot().await
+            };
+            if let Err(e) = store.save(&snapshot) {
+                tracing::warn!("Failed to save runtime snapshot: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::ScratchDir;
+    use crate::types::{ConnectionState, McpSnapshot};
+
+    #[test]
+    fn snapshot_round_trips_through_disk() {
+        let dir = ScratchDir::new();
+        let store = SnapshotStore::new(dir.path().join("nested").join("runtime-snapshot.json"));
+        let snapshot = RuntimeSnapshot {
+            saved_at: "2026-01-01T00:00:00Z".to_string(),
+            mcps: vec![McpSnapshot {
+                id: "fs".to_string(),
+                state: ConnectionState::Error,
+                reconnect_attempts: 3,
+                last_error: Some("connection refused".to_string()),
+            }],
+        };
+
+        store.save(&snapshot).unwrap();
+        let loaded = store.load().unwrap();
+
+        assert_eq!(loaded.saved_at, snapshot.saved_at);
+        assert_eq!(loaded.mcps.len(), 1);
+        assert_eq!(loaded.mcps[0].id, "fs");
+        assert_eq!(loaded.mcps[0].state, ConnectionState::Error);
+        assert_eq!(loaded.mcps[0].reconnect_attempts, 3);
+        assert_eq!(
+            loaded.mcps[0].last_error.as_deref(),
+            Some("connection refused")
+        );
+    }
+
+    #[test]
+    fn missing_or_corrupt_snapshots_load_as_none() {
+        let dir = ScratchDir::new();
+        let store = SnapshotStore::new(dir.path().join("runtime-snapshot.json"));
+        assert!(store.load().is_none());
+
+        std::fs::write(dir.path().join("runtime-snapshot.json"), "{not json").unwrap();
+        assert!(store.load().is_none());
+    }
+}
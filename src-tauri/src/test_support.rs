@@ -0,0 +1,535 @@
+//! Helpers shared by the unit tests: config builders, a proxy server on a
+//! random port and a minimal MCP server (streamable HTTP and legacy SSE) to
+//! proxy to.
+
+use crate::mcp::manager::McpManager;
+use crate::types::McpServerConfig;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::sse::{Event, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::sync::Mutex as StdMutex;
+use std::time::Duration;
+use tokio::sync::{broadcast, oneshot, Mutex};
+
+/// A stdio MCP config that is disabled, so adding it never connects
+pub fn mcp_config(id: &str) -> McpServerConfig {
+    serde_json::from_value(json!({
+        "id": id,
+        "name": id,
+        "transport_type": "stdio",
+        "command": "true",
+        "enabled": false
+    }))
+    .expect("valid MCP config")
+}
+
+/// A fresh directory under the system temp dir, removed on drop
+pub struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    pub fn new() -> Self {
+        let path =
+            std::env::temp_dir().join(format!("local-mcp-proxy-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&path).expect("create scratch dir");
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// An enabled streamable-HTTP MCP config pointing at `url`
+pub fn http_mcp_config(id: &str, url: &str) -> McpServerConfig {
+    serde_json::from_value(json!({
+        "id": id,
+        "name": id,
+        "transport_type": "streamable_http",
+        "url": url
+    }))
+    .expect("valid MCP config")
+}
+
+/// An enabled stdio MCP config running `script` with `sh -c`
+pub fn shell_mcp_config(id: &str, script: &str) -> McpServerConfig {
+    serde_json::from_value(json!({
+        "id": id,
+        "name": id,
+        "transport_type": "stdio",
+        "command": "sh",
+        "args": ["-c", script]
+    }))
+    .expect("valid MCP config")
+}
+
+/// Serve the proxy router for `manager` on a random loopback port and
+/// return its base URL
+pub async fn spawn_proxy(manager: Arc<Mutex<McpManager>>) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let router = crate::proxy::server::create_router(manager);
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    format!("http://{}", addr)
+}
+
+/// POST a JSON-RPC message to the proxy endpoint of MCP `id` and return the
+/// decoded response body
+pub async fn post_rpc(base: &str, id: &str, body: Value) -> Value {
+    reqwest::Client::new()
+        .post(format!("{}/mcp/{}", base, id))
+        .header("Accept", "application/json, text/event-stream")
+        .json(&body)
+        .send()
+        .await
+        .expect("proxy reachable")
+        .json()
+        .await
+        .expect("JSON response")
+}
+
+/// A `tools/call` request for `tool` with no arguments
+pub fn tool_call(id: u64, tool: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": "tools/call",
+        "params": {"name": tool, "arguments": {}}
+    })
+}
+
+#[derive(Clone)]
+struct FakeUpstreamState {
+    label: String,
+    tools: Vec<String>,
+    requests: Arc<StdMutex<Vec<Value>>>,
+    /// HTTP method and headers of every request, probes included
+    headers: Arc<StdMutex<Vec<(String, HeaderMap)>>>,
+    /// Replies to legacy SSE clients, delivered on their event stream
+    sse_replies: broadcast::Sender<Value>,
+    delay: Duration,
+    /// Number of upcoming requests to fail with 503
+    failures: Arc<AtomicUsize>,
+    /// Protocol version answered to `initialize` instead of the client's
+    protocol_version: Arc<StdMutex<Option<String>>>,
+    initializing: Arc<AtomicUsize>,
+    max_initializing: Arc<AtomicUsize>,
+    /// Gzip JSON replies for clients that accept it
+    gzip: Arc<AtomicBool>,
+    gzipped_replies: Arc<AtomicUsize>,
+}
+
+/// Bytes a `FakeUpstream` serves for `resources/read` of any `*.png` URI;
+/// other URIs read as the text "contents of <uri>"
+pub const FAKE_PNG: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Tool whose `tools/call` a `FakeUpstream` answers with two progress
+/// notifications before the result
+pub const FAKE_PROGRESS_TOOL: &str = "long_task";
+
+/// An MCP server answering streamable HTTP on `url` with plain JSON, and
+/// legacy SSE on `sse_url`.  `tools/call` replies with "<tool> from <label>"
+/// so tests can tell upstreams apart.
+pub struct FakeUpstream {
+    pub url: String,
+    pub sse_url: String,
+    requests: Arc<StdMutex<Vec<Value>>>,
+    headers: Arc<StdMutex<Vec<(String, HeaderMap)>>>,
+    sse_replies: broadcast::Sender<Value>,
+    failures: Arc<AtomicUsize>,
+    protocol_version: Arc<StdMutex<Option<String>>>,
+    max_initializing: Arc<AtomicUsize>,
+    gzip: Arc<AtomicBool>,
+    gzipped_replies: Arc<AtomicUsize>,
+    shutdown: Option<oneshot::Sender<()>>,
+    server: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl FakeUpstream {
+    pub async fn spawn(label: &str, tools: &[&str]) -> Self {
+        Self::spawn_slow(label, tools, Duration::ZERO).await
+    }
+
+    /// Like `spawn`, but each `initialize` and `tools/call` takes `delay`
+    /// to answer
+    pub async fn spawn_slow(label: &str, tools: &[&str], delay: Duration) -> Self {
+        let requests = Arc::new(StdMutex::new(Vec::new()));
+        let headers = Arc::new(StdMutex::new(Vec::new()));
+        let sse_replies = broadcast::channel(16).0;
+        let failures = Arc::new(AtomicUsize::new(0));
+        let protocol_version = Arc::new(StdMutex::new(None));
+        let max_initializing = Arc::new(AtomicUsize::new(0));
+        let gzip = Arc::new(AtomicBool::new(false));
+        let gzipped_replies = Arc::new(AtomicUsize::new(0));
+        let state = FakeUpstreamState {
+            label: label.to_string(),
+            tools: tools.iter().map(|t| t.to_string()).collect(),
+            requests: requests.clone(),
+            headers: headers.clone(),
+            sse_replies: sse_replies.clone(),
+            delay,
+            failures: failures.clone(),
+            protocol_version: protocol_version.clone(),
+            initializing: Arc::new(AtomicUsize::new(0)),
+            max_initializing: max_initializing.clone(),
+            gzip: gzip.clone(),
+            gzipped_replies: gzipped_replies.clone(),
+        };
+        let router = Router::new()
+            .route(
+                "/mcp",
+                post(fake_upstream_post).get(|| async { StatusCode::METHOD_NOT_ALLOWED }),
+            )
+            .route("/sse", get(fake_sse_stream))
+            .route("/messages", post(fake_sse_message))
+            .with_state(state);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let url = format!("http://{}/mcp", addr);
+        let sse_url = format!("http://{}/sse", addr);
+        let (shutdown, signal) = oneshot::channel::<()>();
+        let server = tokio::spawn(async move {
+            let _ = axum::serve(listener, router)
+                .with_graceful_shutdown(async {
+                    let _ = signal.await;
+                })
+                .await;
+        });
+        Self {
+            url,
+            sse_url,
+            requests,
+            headers,
+            sse_replies,
+            failures,
+            protocol_version,
+            max_initializing,
+            gzip,
+            gzipped_replies,
+            shutdown: Some(shutdown),
+            server: Some(server),
+        }
+    }
+
+    /// Methods of the messages received so far, in order, excluding the
+    /// reachability probe
+    pub fn methods(&self) -> Vec<String> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|body| body["method"].as_str().map(str::to_string))
+            .collect()
+    }
+
+    /// Params of each `method` request received so far, in order
+    pub fn params_of(&self, method: &str) -> Vec<Value> {
+        self.requests_for(method)
+            .into_iter()
+            .map(|body| body["params"].clone())
+            .collect()
+    }
+
+    /// Full bodies of the `method` requests received so far, in order
+    pub fn requests_for(&self, method: &str) -> Vec<Value> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|body| body["method"] == method)
+            .cloned()
+            .collect()
+    }
+
+    /// Value of header `name` on every request received so far, paired
+    /// with the request's HTTP method
+    pub fn header_values(&self, name: &str) -> Vec<(String, Option<String>)> {
+        self.headers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(method, headers)| {
+                let value = headers
+                    .get(name)
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::to_string);
+                (method.clone(), value)
+            })
+            .collect()
+    }
+
+    /// Push a server -> client message (e.g. a `sampling/createMessage`
+    /// request) down the legacy SSE stream
+    pub fn send_sse(&self, message: Value) {
+        self.sse_replies
+            .send(message)
+            .expect("an SSE client is connected");
+    }
+
+    /// Wait for the client's reply to the request with id `id`
+    pub async fn wait_for_response(&self, id: &str) -> Value {
+        for _ in 0..250 {
+            let reply = self
+                .requests
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|body| body.get("method").is_none() && body["id"] == id)
+                .cloned();
+            if let Some(reply) = reply {
+                return reply;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("no response to request '{}'", id);
+    }
+
+    /// Answer the next `count` streamable HTTP requests with a 503
+    pub fn fail_next(&self, count: usize) {
+        self.failures.store(count, Ordering::SeqCst);
+    }
+
+    /// Answer `initialize` with `version` rather than echoing the client's
+    pub fn set_protocol_version(&self, version: &str) {
+        *self.protocol_version.lock().unwrap() = Some(version.to_string());
+    }
+
+    /// Most `initialize` requests that were being answered at the same time
+    pub fn max_concurrent_initializes(&self) -> usize {
+        self.max_initializing.load(Ordering::SeqCst)
+    }
+
+    /// Gzip the JSON replies on `url` for clients sending
+    /// `Accept-Encoding: gzip`
+    pub fn gzip_responses(&self) {
+        self.gzip.store(true, Ordering::SeqCst);
+    }
+
+    /// Number of replies sent gzipped so far
+    pub fn gzipped_replies(&self) -> usize {
+        self.gzipped_replies.load(Ordering::SeqCst)
+    }
+
+    /// Stop listening and close open connections, so later requests fail at
+    /// the transport level
+    pub async fn stop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(server) = self.server.take() {
+            let _ = server.await;
+        }
+    }
+}
+
+impl Drop for FakeUpstream {
+    fn drop(&mut self) {
+        if let Some(server) = &self.server {
+            server.abort();
+        }
+    }
+}
+
+async fn fake_upstream_post(
+    State(state): State<FakeUpstreamState>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Response {
+    let gzip = state.gzip.load(Ordering::SeqCst)
+        && headers
+            .get(header::ACCEPT_ENCODING)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.contains("gzip"));
+    state
+        .headers
+        .lock()
+        .unwrap()
+        .push(("POST".to_string(), headers));
+    let is_request = body.get("method").is_some() && body.get("id").is_some();
+    let failing = is_request
+        && state
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+    if failing {
+        state.requests.lock().unwrap().push(body);
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+    let progress_token = (body["method"] == "tools/call"
+        && body["params"]["name"] == FAKE_PROGRESS_TOOL)
+        .then(|| body["params"]["_meta"]["progressToken"].clone());
+    match (fake_reply(&state, body).await, progress_token) {
+        (Some(reply), Some(token)) => progress_then_reply(token, reply),
+        (Some(reply), None) if gzip => {
+            state.gzipped_replies.fetch_add(1, Ordering::SeqCst);
+            gzipped_json(&reply)
+        }
+        (Some(reply), None) => Json(reply).into_response(),
+        (None, _) => StatusCode::ACCEPTED.into_response(),
+    }
+}
+
+/// `reply` as a gzip-encoded JSON response
+fn gzipped_json(reply: &Value) -> Response {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(reply.to_string().as_bytes()).unwrap();
+    let body = encoder.finish().unwrap();
+    (
+        [
+            (header::CONTENT_TYPE, "application/json"),
+            (header::CONTENT_ENCODING, "gzip"),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// Answer as an SSE stream: two progress notifications for `token`, a
+/// pause, then `reply`
+fn progress_then_reply(token: Value, reply: Value) -> Response {
+    let progress = (1..=2).map(move |step| {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/progress",
+            "params": {"progressToken": token, "progress": step, "total": 2}
+        })
+    });
+    let events = futures::stream::iter(progress)
+        .chain(futures::stream::once(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            reply
+        }))
+        .map(|message| {
+            Ok::<_, std::convert::Infallible>(Event::default().data(message.to_string()))
+        });
+    Sse::new(events).into_response()
+}
+
+/// Legacy SSE stream: announces the messages endpoint, then carries the
+/// replies to messages POSTed there
+async fn fake_sse_stream(State(state): State<FakeUpstreamState>, headers: HeaderMap) -> Response {
+    state
+        .headers
+        .lock()
+        .unwrap()
+        .push(("GET".to_string(), headers));
+    let endpoint = Event::default().event("endpoint").data("/messages");
+    let replies =
+        futures::stream::unfold(state.sse_replies.subscribe(), |mut receiver| async move {
+            let reply = receiver.recv().await.ok()?;
+            Some((Event::default().data(reply.to_string()), receiver))
+        });
+    let events = futures::stream::once(async { endpoint })
+        .chain(replies)
+        .map(Ok::<_, std::convert::Infallible>);
+    Sse::new(events).into_response()
+}
+
+async fn fake_sse_message(
+    State(state): State<FakeUpstreamState>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> StatusCode {
+    state
+        .headers
+        .lock()
+        .unwrap()
+        .push(("POST".to_string(), headers));
+    if let Some(reply) = fake_reply(&state, body).await {
+        let _ = state.sse_replies.send(reply);
+    }
+    StatusCode::ACCEPTED
+}
+
+/// The JSON-RPC response to `body`, or `None` for notifications
+async fn fake_reply(state: &FakeUpstreamState, body: Value) -> Option<Value> {
+    let method = body["method"].as_str().unwrap_or_default().to_string();
+    // The connect probe is a ping with id 0; leave it out of the log
+    if !(method == "ping" && body["id"] == json!(0)) {
+        state.requests.lock().unwrap().push(body.clone());
+    }
+    // Replies to our own requests need no answer
+    body.get("method")?;
+    let id = body.get("id").cloned()?;
+    if method == "initialize" {
+        let current = state.initializing.fetch_add(1, Ordering::SeqCst) + 1;
+        state.max_initializing.fetch_max(current, Ordering::SeqCst);
+        tokio::time::sleep(state.delay).await;
+        state.initializing.fetch_sub(1, Ordering::SeqCst);
+    } else if method == "tools/call" {
+        tokio::time::sleep(state.delay).await;
+    }
+    let result = match method.as_str() {
+        "initialize" => json!({
+            "protocolVersion": state
+                .protocol_version
+                .lock()
+                .unwrap()
+                .clone()
+                .map_or(body["params"]["protocolVersion"].clone(), Value::from),
+            "capabilities": {"tools": {}, "resources": {}},
+            "serverInfo": {"name": state.label, "version": "1.0.0"}
+        }),
+        "tools/list" => json!({
+            "tools": state.tools.iter().map(|name| json!({
+                "name": name,
+                "description": format!("{} tool", name),
+                "inputSchema": {"type": "object"}
+            })).collect::<Vec<_>>()
+        }),
+        "tools/call" => json!({
+            "content": [{
+                "type": "text",
+                "text": format!("{} from {}", body["params"]["name"].as_str().unwrap_or_default(), state.label)
+            }]
+        }),
+        "resources/list" => json!({"resources": []}),
+        "resources/read" => {
+            let uri = body["params"]["uri"].as_str().unwrap_or_default();
+            let content = if uri.ends_with(".png") {
+                use base64::Engine;
+                let blob = base64::engine::general_purpose::STANDARD.encode(FAKE_PNG);
+                json!({"uri": uri, "mimeType": "image/png", "blob": blob})
+            } else {
+                json!({"uri": uri, "mimeType": "text/plain", "text": format!("contents of {}", uri)})
+            };
+            json!({"contents": [content]})
+        }
+        "resources/templates/list" => json!({
+            "resourceTemplates": [
+                {"uriTemplate": "file:///public/{path}", "name": "public"},
+                {"uriTemplate": "file:///secret/{path}", "name": "secret"}
+            ]
+        }),
+        "prompts/list" => json!({"prompts": []}),
+        "ping" => json!({}),
+        _ => {
+            return Some(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {"code": -32601, "message": format!("Method not found: {}", method)}
+            }))
+        }
+    };
+    Some(json!({"jsonrpc": "2.0", "id": id, "result": result}))
+}
+
+/// A loopback URL nothing is listening on
+pub async fn unreachable_url() -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    format!("http://{}/mcp", listener.local_addr().unwrap())
+}
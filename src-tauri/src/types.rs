@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Transport type for connecting to an MCP server
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -8,6 +9,8 @@ pub enum TransportType {
     Stdio,
     Sse,
     StreamableHttp,
+    /// Probe the URL with streamable HTTP, falling back to legacy SSE
+    Auto,
 }
 
 /// Connection state machine
@@ -21,6 +24,72 @@ pub enum ConnectionState {
     Reconnecting,
 }
 
+/// Why a connection is being (re)established.  Only failure-driven
+/// reconnects count against `max_reconnect_attempts`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconnectReason {
+    /// Startup, add or config update
+    Initial,
+    /// The health loop found the connection down or errored
+    HealthFailure,
+    /// Explicit connect from the UI
+    UserRequested,
+    /// Connected lazily by an incoming proxy request
+    OnDemand,
+    /// Revived by a proxy request after an idle disconnect
+    IdleRevival,
+    /// Planned reconnect after `max_connection_lifetime_secs`
+    LifetimeRecycle,
+}
+
+/// Broad cause of a failed connect, so the UI can suggest a fix
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectErrorKind {
+    /// The stdio command could not be started (missing, not executable)
+    SpawnFailed,
+    Timeout,
+    /// The upstream rejected our credentials (HTTP 401/403)
+    AuthFailed,
+    /// Any other network or stream failure
+    TransportError,
+    /// Reachable, but the MCP handshake or a response was malformed
+    ProtocolError,
+    DnsError,
+    /// Nothing is listening at the upstream address
+    Refused,
+}
+
+impl ReconnectReason {
+    /// Whether this reconnect consumes the failure budget
+    pub fn counts_as_failure(self) -> bool {
+        matches!(self, ReconnectReason::HealthFailure)
+    }
+}
+
+/// A connection lifecycle event, kept per MCP separately from the logs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionEvent {
+    pub timestamp: String,
+    pub kind: ConnectionEventKind,
+    /// Why the connect was started (`connect_attempt` only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<ReconnectReason>,
+    /// Error message or other context
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionEventKind {
+    ConnectAttempt,
+    Connected,
+    Failed,
+    Disconnected,
+}
+
 /// Configuration for a single MCP server
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct McpServerConfig {
@@ -31,18 +100,189 @@ pub struct McpServerConfig {
     pub command: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub args: Option<Vec<String>>,
+    /// Alternative to `args`: one string split shell-style (quotes and
+    /// backslash escapes honored) when the server is spawned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args_raw: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub env: Option<HashMap<String, String>>,
+    /// Variables inherited from the app's environment in addition to the
+    /// global `inherited_env_allowlist` (stdio only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inherit_env: Vec<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub headers: Option<HashMap<String, String>>,
     #[serde(default = "default_true")]
     pub enabled: bool,
+    /// Connect when the app starts.  An enabled MCP with this off is still
+    /// managed and can be connected on demand or by the user.
+    #[serde(default = "default_true")]
+    pub auto_start: bool,
+    /// Tool names hidden from clients; globs such as `admin_*`, `tool_?`,
+    /// `[!a]*` or `{read,write}_*` allowed (see `DisabledItems`)
     #[serde(default)]
     pub disabled_tools: Vec<String>,
+    /// Resource URIs hidden from clients; the same globs as for tools.
+    /// Resource templates whose `uriTemplate` matches are hidden too.
     #[serde(default)]
     pub disabled_resources: Vec<String>,
+    /// Hide tools that may be destructive, i.e. all but those annotated
+    /// `readOnlyHint: true` or `destructiveHint: false`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hide_destructive_tools: Option<bool>,
+    /// Alternate URLs (HTTP/SSE) or commands (stdio) tried in order when
+    /// the primary upstream is unavailable
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_upstreams: Vec<String>,
+    /// Disconnect after this many seconds without proxy requests; the
+    /// connection is revived on the next request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout_secs: Option<u64>,
+    /// Recycle (disconnect and reconnect) the connection once it has been
+    /// up this long, for servers that leak resources over long uptimes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_connection_lifetime_secs: Option<u64>,
+    /// Re-list tools and resources from the health loop at this interval,
+    /// for servers that never send list_changed; 0 or unset disables it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capability_refresh_interval_secs: Option<u64>,
+    /// Names of client request headers (e.g. a tenant id) copied onto the
+    /// upstream request; streamable HTTP only.  Anything not listed is never
+    /// passed through, and `Authorization` and `Cookie` cannot be listed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub forward_headers: Vec<String>,
+    /// Tool renames exposed to clients (original name -> alias)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tool_aliases: HashMap<String, String>,
+    /// Replacement descriptions exposed to clients (original tool name ->
+    /// description); input schemas are left untouched
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tool_description_overrides: HashMap<String, String>,
+    /// Default `tools/call` arguments per tool (original name), deep-merged
+    /// under the client's arguments so clients may omit them
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tool_defaults: HashMap<String, serde_json::Value>,
+    /// Maximum concurrent proxy requests to this MCP; further requests are
+    /// rejected instead of queueing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_inflight_requests: Option<usize>,
+    /// Retries for transient upstream failures (transport errors, timeouts).
+    /// Only read-only methods are retried unless `idempotent` is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u32>,
+    /// The server's tools are safe to call more than once, so `tools/call`
+    /// may be retried too
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotent: Option<bool>,
+    /// Filesystem roots (paths or URIs) served to the upstream via `roots/list`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roots: Vec<String>,
+    /// TCP keepalive for HTTP/SSE upstreams in seconds (0 disables)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tcp_keepalive_secs: Option<u64>,
+    /// HTTP/2 keepalive ping interval for HTTP/SSE upstreams in seconds (0 disables)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http2_keep_alive_interval_secs: Option<u64>,
+    /// Use HTTP/2 adaptive flow-control windows for HTTP/SSE upstreams
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http2_adaptive_window: Option<bool>,
+    /// How long idle pooled HTTP connections are kept, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_pool_idle_timeout_secs: Option<u64>,
+    /// TCP/TLS connect timeout for HTTP/SSE upstreams, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_connect_timeout_secs: Option<u64>,
+    /// Maximum idle pooled HTTP connections per host (unlimited if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_pool_max_idle_per_host: Option<usize>,
+    /// Transparently decode gzip/brotli responses from HTTP/SSE upstreams
+    /// (default on); turn off if a compressing server stalls SSE streams
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_decompression: Option<bool>,
+    /// Connect to a fixed IP for a host name in the URL, like an
+    /// `/etc/hosts` entry; the URL keeps its host name for TLS SNI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_override: Option<HostOverride>,
+    /// Client name/version sent to the upstream server in `initialize`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_info: Option<ClientIdentity>,
+    /// MCP protocol version requested in `initialize` instead of the
+    /// latest one, for servers that misbehave unless pinned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_protocol_version: Option<String>,
+    /// Per-request HMAC signature for streamable HTTP upstreams
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_signing: Option<RequestSigning>,
+    /// Explicit position in listings; unordered MCPs follow, sorted by name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<u32>,
+    /// Display color for dashboards, as `#rrggbb`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Short label shown where the full name doesn't fit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub short_label: Option<String>,
+    /// Keep the most recent proxied requests and responses (secrets
+    /// redacted) for `get_recent_requests`; meant for debugging
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record_requests: Option<bool>,
+    /// Strip ANSI escape sequences (colors, cursor movement) from captured
+    /// stderr lines (default on)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strip_ansi: Option<bool>,
+    /// IDs of MCPs that must be connected before this one is at startup.
+    /// Disabled or non-auto-start dependencies are ignored.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// MCPs sharing a group name are reconnected together: the health loop
+    /// retries all failing members on one shared budget, and
+    /// `reconnect_group` force-reconnects the enabled ones at once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reconnect_group: Option<String>,
+}
+
+/// Client implementation info presented to an upstream MCP server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientIdentity {
+    pub name: String,
+    pub version: String,
+}
+
+/// Resolve `host` to `ip` for one MCP's HTTP/SSE connections
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostOverride {
+    pub host: String,
+    pub ip: std::net::IpAddr,
+}
+
+/// HMAC-SHA256 signing of each POST sent to an HTTP upstream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestSigning {
+    pub secret: String,
+    /// Header carrying the hex-encoded signature
+    pub header: String,
+    #[serde(default)]
+    pub payload: SigningPayload,
+    /// Header carrying the Unix timestamp, when it is part of the payload
+    #[serde(default = "default_timestamp_header")]
+    pub timestamp_header: String,
+}
+
+/// What a request signature covers
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SigningPayload {
+    /// The raw request body
+    #[default]
+    Body,
+    /// `"{timestamp}.{body}"`
+    TimestampAndBody,
+}
+
+fn default_timestamp_header() -> String {
+    "X-Timestamp".to_string()
 }
 
 fn default_true() -> bool {
@@ -57,17 +297,130 @@ pub struct McpStatus {
     pub state: ConnectionState,
     pub transport_type: TransportType,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub short_label: Option<String>,
+    /// Transport picked by auto-detection for the active upstream
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved_transport: Option<TransportType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub connected_at: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub last_ping: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error_message: Option<String>,
+    /// Classification of `error_message` when it came from a failed connect
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<ConnectErrorKind>,
     pub tools_count: usize,
     pub resources_count: usize,
+    /// How many of the cached tools/resources are hidden from clients
+    pub disabled_tools_count: usize,
+    pub disabled_resources_count: usize,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub uptime_seconds: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub proxy_url: Option<String>,
+    /// Index of the upstream in use: 0 is the primary, 1.. are fallbacks
+    pub active_upstream: usize,
+    /// Requests currently being executed against the upstream
+    pub in_flight_requests: usize,
+}
+
+/// Size of the configuration, with a warning once it grows past what the
+/// proxy handles comfortably
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigStats {
+    pub mcp_count: usize,
+    pub enabled_mcp_count: usize,
+    /// Size of the config file on disk; 0 if it has not been written yet
+    pub config_file_bytes: u64,
+    pub exceeds_recommended: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+/// Bytes and messages proxied for one MCP since its connection was created.
+/// `bytes_in` is what clients sent, `bytes_out` what they were answered.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TrafficStats {
+    pub mcp_id: String,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub request_count: u64,
+}
+
+/// The child process of a stdio MCP, for spotting leaked or stuck servers.
+/// `pid` is unset when no child is running.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SpawnedProcess {
+    pub mcp_id: String,
+    pub pid: Option<u32>,
+    /// Command line of the most recent spawn
+    pub command: Option<String>,
+    pub alive: bool,
+}
+
+/// A proxied JSON-RPC exchange kept for debugging
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub timestamp: String,
+    pub method: String,
+    pub request: serde_json::Value,
+    /// `None` for notifications
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<serde_json::Value>,
+    pub duration_ms: u64,
+}
+
+/// Runtime state persisted periodically (apart from config.json) so the
+/// next startup can go easy on servers that were failing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuntimeSnapshot {
+    pub saved_at: String,
+    pub mcps: Vec<McpSnapshot>,
+}
+
+/// Last known runtime state of one MCP
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpSnapshot {
+    pub id: String,
+    pub state: ConnectionState,
+    pub reconnect_attempts: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// What a config reload changed, by MCP id
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReloadSummary {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    /// Updated MCPs whose connection had to be re-established
+    pub reconnected: Vec<String>,
+}
+
+/// Outcome of switching config profiles
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileSwitch {
+    /// Profile the previous, unsaved config was kept as, if it had none
+    pub saved_as: Option<String>,
+    /// The profile listens on a different `proxy_port`, which only takes
+    /// effect after a restart
+    pub restart_required: bool,
+}
+
+/// Compact status counts for menu-bar/tray UIs that poll frequently
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatusSummary {
+    pub total: usize,
+    pub connected: usize,
+    pub error: usize,
+    /// Connecting or reconnecting
+    pub connecting: usize,
+    /// Names of the MCPs currently in the Error state
+    pub error_names: Vec<String>,
 }
 
 /// Tool metadata from an MCP server
@@ -76,7 +429,108 @@ pub struct Tool {
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
-    pub input_schema: serde_json::Value,
+    /// Shared so the cached tool list can be cloned without copying schemas
+    pub input_schema: Arc<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+    /// `input_schema` normalized to an object schema, prepared once when
+    /// the tool list is fetched (see `object_schema`)
+    #[serde(skip)]
+    pub request_schema: Arc<serde_json::Value>,
+}
+
+/// Behaviour hints an upstream server reports for a tool.  Unset hints take
+/// the MCP spec defaults (not read-only, destructive).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolAnnotations {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
+}
+
+impl Tool {
+    /// Build a tool, preparing its normalized request schema up front
+    pub fn new(
+        name: String,
+        description: Option<String>,
+        input_schema: serde_json::Value,
+        annotations: Option<ToolAnnotations>,
+    ) -> Self {
+        let request_schema = Arc::new(object_schema(&input_schema));
+        Self {
+            name,
+            description,
+            input_schema: Arc::new(input_schema),
+            annotations,
+            request_schema,
+        }
+    }
+
+    /// Whether the tool may modify its environment destructively.  Tools
+    /// without annotations count as destructive, as the spec defaults say.
+    pub fn is_destructive(&self) -> bool {
+        let annotations = self.annotations.as_ref();
+        let read_only = annotations.and_then(|a| a.read_only_hint).unwrap_or(false);
+        let destructive = annotations.and_then(|a| a.destructive_hint).unwrap_or(true);
+        !read_only && destructive
+    }
+}
+
+/// Normalize a tool input schema into an object schema.  Missing or
+/// non-object schemas become an empty object schema.
+pub fn object_schema(input_schema: &serde_json::Value) -> serde_json::Value {
+    match input_schema {
+        serde_json::Value::Object(schema) if !schema.is_empty() => {
+            let mut schema = schema.clone();
+            schema
+                .entry("type")
+                .or_insert_with(|| serde_json::Value::String("object".to_string()));
+            serde_json::Value::Object(schema)
+        }
+        _ => serde_json::json!({ "type": "object" }),
+    }
+}
+
+/// A cached tool together with whether it is exposed through the proxy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolState {
+    #[serde(flatten)]
+    pub tool: Tool,
+    pub enabled: bool,
+}
+
+/// A tool in the catalog spanning every connected MCP
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogTool {
+    pub mcp_id: String,
+    pub mcp_name: String,
+    pub tool: Tool,
+    /// Hidden from clients; only listed when disabled tools were requested
+    pub disabled: bool,
+}
+
+/// Outcome of `bulk_set_tool_disabled` for one MCP
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkToolUpdate {
+    pub mcp_id: String,
+    pub name: String,
+    /// Cached tools whose enabled state changed
+    pub affected: usize,
+}
+
+/// Input schema of a single tool, for lazily loading the call form
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolSchema {
+    pub name: String,
+    pub enabled: bool,
+    pub input_schema: Arc<serde_json::Value>,
 }
 
 /// Resource metadata from an MCP server
@@ -98,6 +552,8 @@ pub struct McpDetail {
     pub status: McpStatus,
     pub tools: Vec<Tool>,
     pub resources: Vec<Resource>,
+    /// Most recent stderr lines of a stdio server, oldest first
+    pub recent_stderr: Vec<String>,
 }
 
 /// Application-level configuration
@@ -105,6 +561,15 @@ pub struct McpDetail {
 pub struct AppConfig {
     #[serde(default = "default_proxy_port")]
     pub proxy_port: u16,
+    /// When set, proxy requests (except `/health`) must carry
+    /// `Authorization: Bearer <token>`; rotated with `rotate_proxy_token`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_auth_token: Option<String>,
+    /// Extra `ip:port` addresses the proxy also listens on (e.g. a LAN
+    /// address), in addition to `127.0.0.1:proxy_port`.  Non-loopback
+    /// addresses require `proxy_auth_token`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_listen_addresses: Vec<String>,
     #[serde(default = "default_health_interval")]
     pub health_check_interval_secs: u64,
     #[serde(default = "default_true")]
@@ -113,10 +578,55 @@ pub struct AppConfig {
     pub max_reconnect_attempts: u32,
     #[serde(default = "default_connection_timeout")]
     pub connection_timeout_secs: u64,
+    /// How long a newly added MCP whose first connect fails is shown as
+    /// Connecting rather than Error, while the health loop retries it
+    #[serde(default = "default_connect_grace_period")]
+    pub connect_grace_period_secs: u64,
+    /// Maximum number of MCP handshakes running at the same time
+    #[serde(default = "default_max_concurrent_connects")]
+    pub max_concurrent_connects: usize,
+    /// How long shutdown waits for MCPs to disconnect before force-killing
+    /// remaining stdio processes
+    #[serde(default = "default_shutdown_timeout")]
+    pub shutdown_timeout_secs: u64,
+    /// When set, stdio servers inherit only these variables from the app's
+    /// environment (plus each MCP's `inherit_env` and explicit `env`).
+    /// Unset inherits everything.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inherited_env_allowlist: Option<Vec<String>>,
+    /// Optional LLM backend used to answer `sampling/createMessage`
+    /// requests from upstream servers; sampling is disabled when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<SamplingConfig>,
+    /// Prepended to MCP names to form Claude Desktop entry keys (e.g.
+    /// `mcphub:`), keeping our entries apart from servers added by hand
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claude_name_prefix: Option<String>,
+    /// Profile this config was last switched to or saved as; edits are
+    /// written back to it on the next switch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_profile: Option<String>,
+    /// Serve a small HTML status page at the proxy's `/`, for headless use
+    #[serde(default)]
+    pub enable_status_page: bool,
+    /// Create connections on startup but connect none of them, so a config
+    /// that breaks startup can be fixed. Also forced by `LOCAL_MCP_SAFE_MODE`.
+    #[serde(default)]
+    pub safe_mode: bool,
     #[serde(default)]
     pub mcps: Vec<McpServerConfig>,
 }
 
+/// OpenAI-compatible chat completions backend for sampling requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SamplingConfig {
+    /// Base URL, e.g. `https://api.openai.com/v1`
+    pub endpoint: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    pub model: String,
+}
+
 /// Log entry captured from tracing
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogEntry {
@@ -126,6 +636,94 @@ pub struct LogEntry {
     pub message: String,
 }
 
+/// Payload of the `startup-complete` event, emitted once MCP initialization
+/// and the proxy listener bind have both finished
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartupComplete {
+    pub proxy_port: u16,
+    /// `None` if the proxy failed to bind
+    pub proxy_bound_address: Option<String>,
+    pub mcps_total: usize,
+    pub mcps_connected: usize,
+    pub init_duration_ms: u64,
+}
+
+/// Version and build metadata, for bug reports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildInfo {
+    pub app_version: String,
+    /// The bridge is built from the same package, so it shares the app version
+    pub bridge_version: String,
+    pub target_triple: String,
+    pub rmcp_version: String,
+    /// RFC 3339 time the build script last ran
+    pub build_timestamp: String,
+}
+
+/// Result of a single environment readiness check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvironmentCheck {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// Reachability of one bridge entry found in Claude Desktop's config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaudeIntegrationCheck {
+    /// Entry name under `mcpServers`
+    pub name: String,
+    pub mcp_id: String,
+    /// Whether the MCP id still exists in this proxy's config
+    pub mcp_exists: bool,
+    pub bridge_exists: bool,
+    /// Whether the proxy answered a ping on /mcp/:id
+    pub proxy_reachable: bool,
+    pub detail: String,
+}
+
+/// Whether Claude Desktop's config is safe to edit, checked before an
+/// add/remove touches it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaudeConfigValidity {
+    /// False when the file does not exist yet (it is created on demand)
+    pub exists: bool,
+    /// Parses as JSON and `mcpServers`, if present, is an object
+    pub valid: bool,
+    pub has_mcp_servers: bool,
+    /// Entry names under `mcpServers`, sorted
+    pub server_names: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// Results of calling one tool repeatedly (see `benchmark_tool`).
+/// Percentiles cover successful calls only and are unset if none succeeded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolBenchmark {
+    pub total_requests: usize,
+    /// Calls in flight at once, after capping at `max_inflight_requests`
+    pub concurrency: usize,
+    /// Failed requests and calls answered with `isError`
+    pub errors: usize,
+    pub error_rate: f64,
+    pub duration_ms: f64,
+    pub requests_per_sec: f64,
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+}
+
+/// Round-trip time of a ping sent through the proxy's HTTP endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyMeasurement {
+    pub total_ms: f64,
+    /// Time the proxy spent waiting on the upstream, from its
+    /// `Server-Timing` header; `None` if the header was missing
+    pub upstream_ms: Option<f64>,
+    /// `total_ms - upstream_ms`: HTTP, routing and locking in the proxy
+    pub proxy_overhead_ms: Option<f64>,
+}
+
 fn default_proxy_port() -> u16 {
     3001
 }
@@ -142,15 +740,47 @@ fn default_connection_timeout() -> u64 {
     30
 }
 
+fn default_connect_grace_period() -> u64 {
+    60
+}
+
+fn default_max_concurrent_connects() -> usize {
+    4
+}
+
+fn default_shutdown_timeout() -> u64 {
+    5
+}
+
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
             proxy_port: default_proxy_port(),
+            proxy_auth_token: None,
+            extra_listen_addresses: Vec::new(),
             health_check_interval_secs: default_health_interval(),
             auto_reconnect: true,
             max_reconnect_attempts: default_max_reconnect(),
             connection_timeout_secs: default_connection_timeout(),
+            connect_grace_period_secs: default_connect_grace_period(),
+            max_concurrent_connects: default_max_concurrent_connects(),
+            shutdown_timeout_secs: default_shutdown_timeout(),
+            inherited_env_allowlist: None,
+            sampling: None,
+            claude_name_prefix: None,
+            active_profile: None,
+            enable_status_page: false,
+            safe_mode: false,
             mcps: Vec::new(),
         }
     }
 }
+
+impl AppConfig {
+    /// The proxy auth token, if one is set.  An empty token counts as
+    /// unset, so it can neither be matched by a bare `Bearer ` header nor
+    /// stand in for a token on non-loopback addresses.
+    pub fn proxy_token(&self) -> Option<&str> {
+        self.proxy_auth_token.as_deref().filter(|token| !token.is_empty())
+    }
+}
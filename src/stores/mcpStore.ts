@@ -8,6 +8,24 @@ import type {
   McpServerConfig,
   AppConfig,
   LogEntry,
+  EnvironmentCheck,
+  ConnectionEvent,
+  RecordedRequest,
+  BulkToolUpdate,
+  CatalogTool,
+  ToolSchema,
+  BuildInfo,
+  ClaudeIntegrationCheck,
+  ClaudeConfigValidity,
+  ConfigStats,
+  LatencyMeasurement,
+  ToolBenchmark,
+  TrafficStats,
+  SpawnedProcess,
+  StartupComplete,
+  StatusSummary,
+  ReloadSummary,
+  ProfileSwitch,
 } from "@/types";
 import { ConnectionState } from "@/types";
 
@@ -20,6 +38,8 @@ export const useMcpStore = defineStore("mcp", () => {
   const error = ref<string | null>(null);
   const initialized = ref(false);
   const logs = ref<LogEntry[]>([]);
+  const startup = ref<StartupComplete | null>(null);
+  const configWarning = ref<string | null>(null);
 
   // Computed
   const totalCount = computed(() => statuses.value.length);
@@ -75,6 +95,21 @@ export const useMcpStore = defineStore("mcp", () => {
     }
   }
 
+  async function getToolSchema(
+    mcpId: string,
+    toolName: string,
+  ): Promise<ToolSchema> {
+    return await invoke<ToolSchema>("get_tool_schema", { mcpId, toolName });
+  }
+
+  async function getConnectionEvents(mcpId: string): Promise<ConnectionEvent[]> {
+    return await invoke<ConnectionEvent[]>("get_connection_events", { mcpId });
+  }
+
+  async function getRecentRequests(mcpId: string): Promise<RecordedRequest[]> {
+    return await invoke<RecordedRequest[]>("get_recent_requests", { mcpId });
+  }
+
   async function addMcp(config: McpServerConfig): Promise<string> {
     const id = await invoke<string>("add_mcp", { config });
     await fetchStatuses();
@@ -102,16 +137,87 @@ export const useMcpStore = defineStore("mcp", () => {
     await fetchDetail(id);
   }
 
+  async function bulkSetToolDisabled(
+    pattern: string,
+    disabled: boolean,
+    mcpIds?: string[],
+  ): Promise<BulkToolUpdate[]> {
+    return await invoke<BulkToolUpdate[]>("bulk_set_tool_disabled", {
+      pattern,
+      disabled,
+      mcpIds: mcpIds ?? null,
+    });
+  }
+
+  async function getAllTools(includeDisabled = false): Promise<CatalogTool[]> {
+    return await invoke<CatalogTool[]>("get_all_tools", { includeDisabled });
+  }
+
+  async function pruneDisabledItems(id: string): Promise<number> {
+    const pruned = await invoke<number>("prune_disabled_items", { id });
+    await fetchDetail(id);
+    return pruned;
+  }
+
+  async function reorderMcps(ids: string[]) {
+    await invoke("reorder_mcps", { ids });
+    await fetchStatuses();
+  }
+
+  async function pauseHealthChecks(timeoutSecs?: number) {
+    await invoke("pause_health_checks", { timeoutSecs });
+  }
+
+  async function resumeHealthChecks() {
+    await invoke("resume_health_checks");
+  }
+
+  async function setSafeMode(enabled: boolean): Promise<boolean> {
+    const active = await invoke<boolean>("set_safe_mode", { enabled });
+    await fetchAppConfig();
+    return active;
+  }
+
   async function connectMcp(id: string) {
     await invoke("connect_mcp", { id });
     await fetchStatuses();
   }
 
+  async function connectMcpByName(name: string) {
+    await invoke("connect_mcp_by_name", { name });
+    await fetchStatuses();
+  }
+
+  async function getMcpDetailByName(name: string): Promise<McpDetail> {
+    return await invoke<McpDetail>("get_mcp_detail_by_name", { name });
+  }
+
   async function disconnectMcp(id: string) {
     await invoke("disconnect_mcp", { id });
     await fetchStatuses();
   }
 
+  async function reconnectGroup(name: string) {
+    await invoke("reconnect_group", { name });
+    await fetchStatuses();
+  }
+
+  async function clearError(id: string): Promise<ConnectionState> {
+    const state = await invoke<ConnectionState>("clear_error", { id });
+    await fetchStatuses();
+    return state;
+  }
+
+  async function getStatusSummary(): Promise<StatusSummary> {
+    return await invoke<StatusSummary>("get_status_summary");
+  }
+
+  async function probeServer(
+    config: McpServerConfig
+  ): Promise<Record<string, unknown>> {
+    return await invoke<Record<string, unknown>>("probe_server", { config });
+  }
+
   async function getProxyUrl(id: string): Promise<string> {
     return await invoke<string>("get_proxy_url", { id });
   }
@@ -124,6 +230,39 @@ export const useMcpStore = defineStore("mcp", () => {
     }
   }
 
+  async function reloadConfig(): Promise<ReloadSummary> {
+    const summary = await invoke<ReloadSummary>("reload_config");
+    await Promise.all([fetchStatuses(), fetchAppConfig()]);
+    return summary;
+  }
+
+  async function listProfiles(): Promise<string[]> {
+    return await invoke<string[]>("list_profiles");
+  }
+
+  async function saveCurrentAsProfile(name: string) {
+    await invoke("save_current_as_profile", { name });
+    await fetchAppConfig();
+  }
+
+  async function switchProfile(name: string): Promise<ProfileSwitch> {
+    const result = await invoke<ProfileSwitch>("switch_profile", { name });
+    await Promise.all([fetchStatuses(), fetchAppConfig()]);
+    return result;
+  }
+
+  async function flushConfig() {
+    await invoke("flush_config");
+  }
+
+  async function getConfigStats(): Promise<ConfigStats> {
+    return await invoke<ConfigStats>("get_config_stats");
+  }
+
+  async function getEffectiveConfig(): Promise<AppConfig> {
+    return await invoke<AppConfig>("get_effective_config");
+  }
+
   async function fetchLogs() {
     try {
       logs.value = await invoke<LogEntry[]>("get_logs");
@@ -132,11 +271,78 @@ export const useMcpStore = defineStore("mcp", () => {
     }
   }
 
+  async function exportLogs(path?: string): Promise<string> {
+    return await invoke<string>("export_logs", { path: path ?? null });
+  }
+
+  async function exportDiagnostics(path: string): Promise<string> {
+    return await invoke<string>("export_diagnostics", { path });
+  }
+
   async function updateAppConfig(config: AppConfig) {
     await invoke("update_app_config", { config });
     appConfig.value = config;
   }
 
+  async function rotateProxyToken(): Promise<string> {
+    const token = await invoke<string>("rotate_proxy_token");
+    await fetchAppConfig();
+    return token;
+  }
+
+  async function isPortAvailable(port: number): Promise<boolean> {
+    return await invoke<boolean>("is_port_available", { port });
+  }
+
+  async function checkEnvironment(): Promise<EnvironmentCheck[]> {
+    return await invoke<EnvironmentCheck[]>("check_environment");
+  }
+
+  async function getBuildInfo(): Promise<BuildInfo> {
+    return await invoke<BuildInfo>("get_build_info");
+  }
+
+  async function verifyClaudeIntegration(): Promise<ClaudeIntegrationCheck[]> {
+    return await invoke<ClaudeIntegrationCheck[]>("verify_claude_integration");
+  }
+
+  async function checkClaudeConfigValid(): Promise<ClaudeConfigValidity> {
+    return await invoke<ClaudeConfigValidity>("check_claude_config_valid");
+  }
+
+  async function measureLatency(mcpId: string): Promise<LatencyMeasurement> {
+    return await invoke<LatencyMeasurement>("measure_latency", { mcpId });
+  }
+
+  async function benchmarkTool(
+    mcpId: string,
+    toolName: string,
+    args: Record<string, unknown>,
+    concurrency: number,
+    totalRequests: number,
+  ): Promise<ToolBenchmark> {
+    return await invoke<ToolBenchmark>("benchmark_tool", {
+      mcpId,
+      toolName,
+      args,
+      concurrency,
+      totalRequests,
+    });
+  }
+
+  async function getTrafficStats(): Promise<TrafficStats[]> {
+    return await invoke<TrafficStats[]>("get_traffic_stats");
+  }
+
+  async function getSpawnedProcesses(): Promise<SpawnedProcess[]> {
+    return await invoke<SpawnedProcess[]>("get_spawned_processes");
+  }
+
+  async function killProcess(mcpId: string) {
+    await invoke("kill_process", { mcpId });
+    await fetchStatuses();
+  }
+
   // Initialize: fetch data + subscribe to Tauri events
   async function init() {
     if (initialized.value) return;
@@ -145,12 +351,17 @@ export const useMcpStore = defineStore("mcp", () => {
     await fetchStatuses();
     await fetchAppConfig();
     await fetchLogs();
+    configWarning.value = await invoke<string | null>("get_config_warning");
 
     // Listen for real-time status updates from the Rust backend
     listen<McpStatus[]>("mcp-statuses-changed", (event) => {
       statuses.value = event.payload;
     });
 
+    listen<StartupComplete>("startup-complete", (event) => {
+      startup.value = event.payload;
+    });
+
     listen<LogEntry>("log-entry", (event) => {
       logs.value.push(event.payload);
       if (logs.value.length > 500) {
@@ -177,6 +388,8 @@ export const useMcpStore = defineStore("mcp", () => {
     loading,
     error,
     logs,
+    startup,
+    configWarning,
     // Computed
     totalCount,
     connectedCount,
@@ -188,15 +401,51 @@ export const useMcpStore = defineStore("mcp", () => {
     init,
     fetchStatuses,
     fetchDetail,
+    getToolSchema,
+    getConnectionEvents,
+    getRecentRequests,
     addMcp,
     updateMcp,
     removeMcp,
     setDisabledItems,
+    pruneDisabledItems,
+    bulkSetToolDisabled,
+    getAllTools,
+    reorderMcps,
+    pauseHealthChecks,
+    resumeHealthChecks,
+    setSafeMode,
     connectMcp,
+    connectMcpByName,
+    getMcpDetailByName,
     disconnectMcp,
+    clearError,
+    reconnectGroup,
+    getStatusSummary,
+    probeServer,
     getProxyUrl,
     fetchAppConfig,
+    getEffectiveConfig,
+    getConfigStats,
+    reloadConfig,
+    listProfiles,
+    saveCurrentAsProfile,
+    switchProfile,
+    flushConfig,
     fetchLogs,
+    exportLogs,
+    exportDiagnostics,
     updateAppConfig,
+    isPortAvailable,
+    rotateProxyToken,
+    checkEnvironment,
+    verifyClaudeIntegration,
+    checkClaudeConfigValid,
+    measureLatency,
+    benchmarkTool,
+    getTrafficStats,
+    getSpawnedProcesses,
+    killProcess,
+    getBuildInfo,
   };
 });
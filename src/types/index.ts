@@ -2,6 +2,7 @@ export enum TransportType {
   Stdio = "stdio",
   Sse = "sse",
   StreamableHttp = "streamable_http",
+  Auto = "auto",
 }
 
 export enum ConnectionState {
@@ -12,18 +13,115 @@ export enum ConnectionState {
   Reconnecting = "reconnecting",
 }
 
+export enum ReconnectReason {
+  Initial = "initial",
+  HealthFailure = "health_failure",
+  UserRequested = "user_requested",
+  OnDemand = "on_demand",
+  IdleRevival = "idle_revival",
+  LifetimeRecycle = "lifetime_recycle",
+}
+
+export enum ConnectErrorKind {
+  SpawnFailed = "spawn_failed",
+  Timeout = "timeout",
+  AuthFailed = "auth_failed",
+  TransportError = "transport_error",
+  ProtocolError = "protocol_error",
+  DnsError = "dns_error",
+  Refused = "refused",
+}
+
+export enum ConnectionEventKind {
+  ConnectAttempt = "connect_attempt",
+  Connected = "connected",
+  Failed = "failed",
+  Disconnected = "disconnected",
+}
+
+export interface ConnectionEvent {
+  timestamp: string;
+  kind: ConnectionEventKind;
+  reason?: ReconnectReason;
+  detail?: string;
+}
+
 export interface McpServerConfig {
   id: string;
   name: string;
   transport_type: TransportType;
   command?: string;
   args?: string[];
+  args_raw?: string;
   url?: string;
   env?: Record<string, string>;
+  inherit_env?: string[];
   headers?: Record<string, string>;
   enabled: boolean;
+  auto_start: boolean;
   disabled_tools?: string[];
   disabled_resources?: string[];
+  hide_destructive_tools?: boolean;
+  fallback_upstreams?: string[];
+  idle_timeout_secs?: number;
+  max_connection_lifetime_secs?: number;
+  capability_refresh_interval_secs?: number;
+  forward_headers?: string[];
+  tool_aliases?: Record<string, string>;
+  tool_description_overrides?: Record<string, string>;
+  tool_defaults?: Record<string, unknown>;
+  max_inflight_requests?: number;
+  max_retries?: number;
+  idempotent?: boolean;
+  roots?: string[];
+  tcp_keepalive_secs?: number;
+  http2_keep_alive_interval_secs?: number;
+  http2_adaptive_window?: boolean;
+  http_pool_idle_timeout_secs?: number;
+  http_connect_timeout_secs?: number;
+  http_pool_max_idle_per_host?: number;
+  http_decompression?: boolean;
+  host_override?: HostOverride;
+  client_info?: ClientIdentity;
+  upstream_protocol_version?: string;
+  request_signing?: RequestSigning;
+  order?: number;
+  color?: string;
+  short_label?: string;
+  record_requests?: boolean;
+  strip_ansi?: boolean;
+  depends_on?: string[];
+  reconnect_group?: string;
+}
+
+export interface ClientIdentity {
+  name: string;
+  version: string;
+}
+
+export interface HostOverride {
+  host: string;
+  ip: string;
+}
+
+export enum SigningPayload {
+  Body = "body",
+  TimestampAndBody = "timestamp_and_body",
+}
+
+export interface RequestSigning {
+  secret: string;
+  header: string;
+  payload?: SigningPayload;
+  timestamp_header?: string;
+}
+
+export interface RecordedRequest {
+  timestamp: string;
+  method: string;
+  request: Record<string, unknown>;
+  response?: Record<string, unknown>;
+  duration_ms: number;
 }
 
 export interface McpStatus {
@@ -31,19 +129,79 @@ export interface McpStatus {
   name: string;
   state: ConnectionState;
   transport_type: TransportType;
+  color?: string;
+  short_label?: string;
+  resolved_transport?: TransportType;
   connected_at?: string;
   last_ping?: string;
   error_message?: string;
+  error_kind?: ConnectErrorKind;
   tools_count: number;
   resources_count: number;
+  disabled_tools_count: number;
+  disabled_resources_count: number;
   uptime_seconds?: number;
   proxy_url?: string;
+  active_upstream: number;
+  in_flight_requests: number;
+}
+
+export interface ReloadSummary {
+  added: string[];
+  updated: string[];
+  removed: string[];
+  reconnected: string[];
+}
+
+export interface ProfileSwitch {
+  saved_as: string | null;
+  restart_required: boolean;
+}
+
+export interface StatusSummary {
+  total: number;
+  connected: number;
+  error: number;
+  connecting: number;
+  error_names: string[];
 }
 
 export interface Tool {
   name: string;
   description?: string;
   input_schema: Record<string, unknown>;
+  annotations?: ToolAnnotations;
+}
+
+export interface ToolAnnotations {
+  title?: string;
+  read_only_hint?: boolean;
+  destructive_hint?: boolean;
+  idempotent_hint?: boolean;
+  open_world_hint?: boolean;
+}
+
+export interface ToolState extends Tool {
+  enabled: boolean;
+}
+
+export interface CatalogTool {
+  mcp_id: string;
+  mcp_name: string;
+  tool: Tool;
+  disabled: boolean;
+}
+
+export interface BulkToolUpdate {
+  mcp_id: string;
+  name: string;
+  affected: number;
+}
+
+export interface ToolSchema {
+  name: string;
+  enabled: boolean;
+  input_schema: Record<string, unknown>;
 }
 
 export interface Resource {
@@ -58,17 +216,35 @@ export interface McpDetail {
   status: McpStatus;
   tools: Tool[];
   resources: Resource[];
+  recent_stderr: string[];
 }
 
 export interface AppConfig {
   proxy_port: number;
+  proxy_auth_token?: string;
+  extra_listen_addresses?: string[];
   health_check_interval_secs: number;
   auto_reconnect: boolean;
   max_reconnect_attempts: number;
   connection_timeout_secs: number;
+  connect_grace_period_secs: number;
+  max_concurrent_connects: number;
+  shutdown_timeout_secs: number;
+  inherited_env_allowlist?: string[];
+  sampling?: SamplingConfig;
+  claude_name_prefix?: string;
+  active_profile?: string;
+  enable_status_page: boolean;
+  safe_mode: boolean;
   mcps: McpServerConfig[];
 }
 
+export interface SamplingConfig {
+  endpoint: string;
+  api_key?: string;
+  model: string;
+}
+
 export interface LogEntry {
   timestamp: string;
   level: string;
@@ -76,6 +252,85 @@ export interface LogEntry {
   message: string;
 }
 
+export interface StartupComplete {
+  proxy_port: number;
+  proxy_bound_address: string | null;
+  mcps_total: number;
+  mcps_connected: number;
+  init_duration_ms: number;
+}
+
+export interface BuildInfo {
+  app_version: string;
+  bridge_version: string;
+  target_triple: string;
+  rmcp_version: string;
+  build_timestamp: string;
+}
+
+export interface EnvironmentCheck {
+  name: string;
+  passed: boolean;
+  detail: string;
+}
+
+export interface ClaudeIntegrationCheck {
+  name: string;
+  mcp_id: string;
+  mcp_exists: boolean;
+  bridge_exists: boolean;
+  proxy_reachable: boolean;
+  detail: string;
+}
+
+export interface ClaudeConfigValidity {
+  exists: boolean;
+  valid: boolean;
+  has_mcp_servers: boolean;
+  server_names: string[];
+  error?: string;
+}
+
+export interface ConfigStats {
+  mcp_count: number;
+  enabled_mcp_count: number;
+  config_file_bytes: number;
+  exceeds_recommended: boolean;
+  warning?: string;
+}
+
+export interface TrafficStats {
+  mcp_id: string;
+  bytes_in: number;
+  bytes_out: number;
+  request_count: number;
+}
+
+export interface SpawnedProcess {
+  mcp_id: string;
+  pid?: number;
+  command?: string;
+  alive: boolean;
+}
+
+export interface ToolBenchmark {
+  total_requests: number;
+  concurrency: number;
+  errors: number;
+  error_rate: number;
+  duration_ms: number;
+  requests_per_sec: number;
+  p50_ms?: number;
+  p95_ms?: number;
+  p99_ms?: number;
+}
+
+export interface LatencyMeasurement {
+  total_ms: number;
+  upstream_ms?: number;
+  proxy_overhead_ms?: number;
+}
+
 export const CONNECTION_STATE_COLORS: Record<ConnectionState, string> = {
   [ConnectionState.Connected]: "emerald",
   [ConnectionState.Connecting]: "blue",
@@ -84,8 +339,26 @@ export const CONNECTION_STATE_COLORS: Record<ConnectionState, string> = {
   [ConnectionState.Disconnected]: "slate",
 };
 
+export const CONNECT_ERROR_HINTS: Record<ConnectErrorKind, string> = {
+  [ConnectErrorKind.SpawnFailed]:
+    "The command could not be started. Check that it is installed and on PATH.",
+  [ConnectErrorKind.Timeout]:
+    "The server did not answer in time. Try raising the connection timeout.",
+  [ConnectErrorKind.AuthFailed]:
+    "The server rejected the credentials. Check the configured headers.",
+  [ConnectErrorKind.TransportError]:
+    "The connection failed or dropped. Check the server and network.",
+  [ConnectErrorKind.ProtocolError]:
+    "The server answered, but not as an MCP server. Check the URL or command.",
+  [ConnectErrorKind.DnsError]:
+    "The host name could not be resolved. Check the URL for typos.",
+  [ConnectErrorKind.Refused]:
+    "Nothing is listening at that address. Is the server running?",
+};
+
 export const TRANSPORT_LABELS: Record<TransportType, string> = {
   [TransportType.Stdio]: "Stdio (Local Process)",
   [TransportType.Sse]: "Server-Sent Events",
   [TransportType.StreamableHttp]: "Streamable HTTP",
+  [TransportType.Auto]: "Auto-detect (HTTP/SSE)",
 };